@@ -0,0 +1,90 @@
+use inator_automata::*;
+use std::collections::BTreeMap;
+
+/// Go to `dst` on any digit, computing the output with `update`.
+fn digit(dst: usize, update: Update<char>) -> (Range<char>, Transition<char, usize>) {
+    (
+        Range {
+            first: '0',
+            last: '9',
+        },
+        Transition::Lateral {
+            dst,
+            update: Some(update),
+        },
+    )
+}
+
+/// Open parentheses, parse a number inside them (`detour`), then go to `dst` with that number.
+fn parenthesized(detour: usize, dst: usize) -> (Range<char>, Transition<char, usize>) {
+    (
+        Range::unit('('),
+        Transition::Call {
+            region: "parentheses",
+            detour,
+            dst: Box::new(Transition::Lateral { dst, update: None }),
+            combine: ff!(|(), n: usize| n),
+        },
+    )
+}
+
+/// Close parentheses.
+fn close() -> (Range<char>, Transition<char, usize>) {
+    (
+        Range::unit(')'),
+        Transition::Return {
+            region: "parentheses",
+        },
+    )
+}
+
+/// State with only these transitions.
+fn state<T: IntoIterator<Item = (Range<char>, Transition<char, usize>)>>(
+    transitions: T,
+    error: Option<&str>,
+) -> State<char, usize> {
+    State {
+        transitions: Curry::Scrutinize {
+            filter: RangeMap(transitions.into_iter().collect()),
+            fallback: None,
+        },
+        non_accepting: error.map(Rejection::from).into_iter().collect(),
+    }
+}
+
+/// Parse a number in any (positive) number of parentheses, e.g. `((42))`, into the number itself.
+fn delimited_expr() -> Deterministic<char> {
+    let first_digit = update!(|(), c: char| "0123456789".find(c).unwrap());
+    let next_digit = update!(|n: usize, c: char| n * 10 + "0123456789".find(c).unwrap());
+    Graph {
+        states: vec![
+            state([parenthesized(1, 3)], Some("Expected `(`")),
+            state(
+                [digit(2, first_digit), parenthesized(1, 4)],
+                Some("Expected a number or `(`"),
+            ),
+            state([digit(2, next_digit), close()], Some("Expected `)`")),
+            state(BTreeMap::new(), None),
+            state([close()], Some("Expected `)`")),
+        ],
+        initial: 0,
+    }
+}
+
+pub fn main() {
+    let parser = delimited_expr();
+    parser.check().unwrap();
+    let typed = parser.clone().typed::<usize>().unwrap();
+
+    for (s, n) in [("(5)", 5), ("(42)", 42), ("((1234))", 1234)] {
+        println!("{s:?}");
+        // The detour's output (the number) is the right-hand argument to `combine`:
+        assert_eq!(parser.accept(s.chars()), Ok("usize".to_owned()));
+        assert_eq!(typed.accept(s.chars()), Ok(n));
+    }
+
+    for s in ["", "5", "()", "(5", "5)", "((5)", "(5))"] {
+        println!("{s:?}");
+        assert!(typed.accept(s.chars()).is_err());
+    }
+}
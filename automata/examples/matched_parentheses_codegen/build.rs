@@ -1,7 +1,54 @@
-use inator_automata::{dyck_d, IllFormed};
-use std::io;
+use inator_automata::{
+    dyck_d, Curry, Deterministic, Graph, IllFormed, Range, RangeMap, State, Transition,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io, iter,
+};
+
+/// Accept exactly this sequence of bytes.
+fn literal(bytes: &[u8]) -> Deterministic<u8> {
+    Graph {
+        states: bytes
+            .iter()
+            .enumerate()
+            .map(|(i, &byte)| State {
+                transitions: Curry::Scrutinize {
+                    filter: RangeMap(
+                        iter::once((
+                            Range::unit(byte),
+                            Transition::Lateral {
+                                dst: i + 1,
+                                update: None,
+                            },
+                        ))
+                        .collect(),
+                    ),
+                    fallback: None,
+                },
+                non_accepting: iter::once(format!("Expected {byte}").into()).collect(),
+            })
+            .chain(iter::once(State {
+                transitions: Curry::Scrutinize {
+                    filter: RangeMap(BTreeMap::new()),
+                    fallback: None,
+                },
+                non_accepting: BTreeSet::new(),
+            }))
+            .collect(),
+        initial: 0,
+    }
+}
+
+pub fn main() -> Result<io::Result<()>, IllFormed<u8, usize>> {
+    // Decide acceptance of a fixed byte string entirely at compile time.
+    let const_src = literal(b"ab").to_const_src()?;
+    if let Err(e) = fs::write("src/const_parser.rs", const_src) {
+        return Ok(Err(e));
+    }
 
-pub fn main() -> Result<io::Result<()>, IllFormed<char, usize>> {
     // Very manually constructed parser recognizing only valid parentheses.
-    dyck_d().to_file("src/parser.rs")
+    Ok(dyck_d()
+        .to_file("src/parser.rs")
+        .expect("`dyck_d` should be well-formed"))
 }
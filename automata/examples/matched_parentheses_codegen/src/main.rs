@@ -1,7 +1,15 @@
 #![allow(unreachable_code, unused_variables)]
 
+mod const_parser;
 mod parser;
 
+// Evaluated at compile time: a wrong answer here fails the build.
+const OK: bool = const_parser::accept_bytes(b"ab");
+const _: () = assert!(OK);
+const _: () = assert!(!const_parser::accept_bytes(b"a"));
+const _: () = assert!(!const_parser::accept_bytes(b"abc"));
+const _: () = assert!(!const_parser::accept_bytes(b"ba"));
+
 use rand::{thread_rng, RngCore};
 
 /// Generate test cases (has nothing to do with automata!).
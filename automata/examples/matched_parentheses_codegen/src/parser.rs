@@ -55,18 +55,18 @@ fn state_0<I: Iterator<Item = (usize, char)>>(
             Err(Error::Unclosed { region, opened })
         }),
         Some((index, token)) => match token {
-            '('..='(' => {
-                let detour = state_0(input, (), Some(("parentheses", index)))?;
-                let postprocessed = (|(), ()| ())(acc, detour);
-                state_0(input, acc, stack_top)
-            }
-            ')'..=')' => match stack_top {
+            ')' => match stack_top {
                 Some((region, _)) if region == "parentheses" => Ok(acc),
                 _ => Err(Error::Unopened {
                     what_was_open: stack_top,
                     index,
                 }),
             },
+            '(' => {
+                let detour = state_0(input, (), Some(("parentheses", index)))?;
+                let acc = (|(), ()| ())(acc, detour);
+                state_0(input, acc, stack_top)
+            }
             _ => Err(Error::Absurd { index, token }),
         },
     }
@@ -0,0 +1,85 @@
+use inator_automata::*;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Move to another state on one particular (owned, non-`Copy`) token.
+fn on(tokens: &[&str], dst: usize) -> Curry<String, usize> {
+    Curry::Scrutinize {
+        filter: RangeMap(
+            tokens
+                .iter()
+                .map(|&token| {
+                    (
+                        Range::unit(token.to_owned()),
+                        Transition::Lateral { dst, update: None },
+                    )
+                })
+                .collect(),
+        ),
+        fallback: None,
+    }
+}
+
+/// Accept `let <x or y> = <0 or 1> ;` as a stream of already-lexed tokens.
+fn assignment() -> Deterministic<String> {
+    let expected = |what: &str| [format!("Expected {what}").into()].into_iter().collect();
+    Graph {
+        states: vec![
+            State {
+                transitions: on(&["let"], 1),
+                non_accepting: expected("`let`"),
+            },
+            State {
+                transitions: on(&["x", "y"], 2),
+                non_accepting: expected("a variable"),
+            },
+            State {
+                transitions: on(&["="], 3),
+                non_accepting: expected("`=`"),
+            },
+            State {
+                transitions: on(&["0", "1"], 4),
+                non_accepting: expected("a value"),
+            },
+            State {
+                transitions: on(&[";"], 5),
+                non_accepting: expected("`;`"),
+            },
+            State {
+                transitions: Curry::Scrutinize {
+                    filter: RangeMap(BTreeMap::new()),
+                    fallback: None,
+                },
+                non_accepting: BTreeSet::new(),
+            },
+        ],
+        initial: 0,
+    }
+}
+
+/// Split on whitespace, as a (very) simple lexer would.
+fn lex(s: &str) -> Vec<String> {
+    s.split_whitespace().map(str::to_owned).collect()
+}
+
+pub fn main() {
+    let parser = assignment();
+    parser.check().unwrap();
+
+    for s in ["let x = 1 ;", "let y = 0 ;"] {
+        let tokens = lex(s);
+        println!("{tokens:?}");
+        let mut run = tokens.into_iter().run(&parser);
+        println!("    {run:?}");
+        while let Some(r) = run.next() {
+            let Ok(c) = r else { panic!("{r:?}") };
+            println!("{c:?} {run:?}");
+        }
+        assert!(parser.accept(lex(s)).is_ok());
+    }
+
+    for s in ["let z = 1 ;", "let x = 1", "let x = = 1 ;", "x = 1 ;"] {
+        let tokens = lex(s);
+        println!("{tokens:?}");
+        assert!(parser.accept(tokens).is_err());
+    }
+}
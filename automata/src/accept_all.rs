@@ -0,0 +1,200 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Run a parser without determinizing it, following every path at once, and compute the output of each path that accepts.
+
+use crate::{
+    f::Dynamic,
+    walk::{take, Frame, Visit},
+    Ctrl, Graph, IllFormed, Input, ParseError, ToSrc, Update, FF,
+};
+use std::collections::BTreeSet;
+
+/// Something to do to the output, recorded along one path and done only if that path accepts.
+#[derive(Clone)]
+enum Action<'graph, I: Input> {
+    /// Call an update on a token.
+    Update(&'graph Update<I>, I),
+    /// Open a region, setting aside the output so far.
+    Open,
+    /// Close a region, combining the output set aside with the output from inside it.
+    Close(&'graph FF),
+}
+
+/// One path through the parser.
+#[derive(Clone)]
+struct Thread<'graph, I: Input, C: Ctrl<I>> {
+    /// Current state.
+    state: usize,
+    /// Regions open along this path.
+    stack: Vec<Frame<'graph, I, C, ()>>,
+    /// Everything to do to the output along this path, in order.
+    path: Vec<Action<'graph, I>>,
+}
+
+/// Record what to do to the output instead of doing it, since this path might not accept.
+impl<'graph, I: Input, C: Ctrl<I>> Visit<'graph, I, C> for Vec<Action<'graph, I>> {
+    type Payload = ();
+    type Saved = ();
+
+    #[inline]
+    fn lateral(
+        &mut self,
+        update: Option<&'graph Update<I>>,
+        token: &I,
+        _: usize,
+        _: usize,
+    ) -> Result<(), ParseError<I, C>> {
+        if let Some(u) = update {
+            self.push(Action::Update(u, token.clone()));
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn open(&mut self) {
+        self.push(Action::Open);
+    }
+
+    #[inline]
+    fn close(
+        &mut self,
+        combine: &'graph FF,
+        (): (),
+        _: usize,
+        _: usize,
+    ) -> Result<(), ParseError<I, C>> {
+        self.push(Action::Close(combine));
+        Ok(())
+    }
+}
+
+impl<I: Input, C: Ctrl<I>> Graph<I, C> {
+    /// Run this parser directly, without determinizing it, following every path at once,
+    /// and compute the output of each path that accepts (sorted, without duplicates).
+    /// Meant for intentionally ambiguous grammars, which can't be determinized.
+    /// Input that no path accepts simply has no outputs.
+    /// Each path is tracked separately, so highly ambiguous input can take exponential time.
+    /// # Errors
+    /// If any function along an accepting path was written only as source code
+    /// (i.e. not with `update!` or `ff!`), or if any output isn't an `O`.
+    #[inline]
+    pub fn accept_all<O: 'static + Ord + ToSrc, In: IntoIterator<Item = I>>(
+        &self,
+        input: In,
+    ) -> Result<Vec<O>, IllFormed<I, C>> {
+        let mut threads: Vec<Thread<'_, I, C>> = self
+            .initial
+            .view()
+            .map(|state| Thread {
+                state,
+                stack: vec![],
+                path: vec![],
+            })
+            .collect();
+        for token in input {
+            let mut next = vec![];
+            for mut thread in threads {
+                let transition = self
+                    .states
+                    .get(thread.state)
+                    .ok_or(IllFormed::OutOfBounds(thread.state))?
+                    .transitions
+                    .get(&token)?;
+                let Some(t) = transition else {
+                    continue;
+                };
+                // Closing a region this path never opened just ends the path.
+                if let Ok(dst) = take(t, token.clone(), 0, 0, &mut thread.stack, &mut thread.path) {
+                    next.extend(branch(thread, dst));
+                }
+            }
+            if next.is_empty() {
+                return Ok(vec![]);
+            }
+            threads = next;
+        }
+        let outputs = threads
+            .into_iter()
+            .filter(|thread| {
+                thread.stack.is_empty() && get!(self.states, thread.state).non_accepting.is_empty()
+            })
+            .map(|thread| replay(thread.path))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut distinct = BTreeSet::new();
+        for (output, output_t) in outputs {
+            let Ok(o) = output.downcast::<O>() else {
+                return Err(IllFormed::WrongReturnType(output_t, O::src_type()));
+            };
+            let _ = distinct.insert(*o);
+        }
+        Ok(distinct.into_iter().collect())
+    }
+}
+
+/// Continue one path into each of these states (copying it only when there's more than one).
+#[inline]
+fn branch<'graph, I: Input, C: Ctrl<I>>(
+    mut thread: Thread<'graph, I, C>,
+    dst: &C,
+) -> Vec<Thread<'graph, I, C>> {
+    let states: Vec<usize> = dst.view().collect();
+    let Some((&last, rest)) = states.split_last() else {
+        return vec![];
+    };
+    let mut threads: Vec<_> = rest
+        .iter()
+        .map(|&state| Thread {
+            state,
+            ..thread.clone()
+        })
+        .collect();
+    thread.state = last;
+    threads.push(thread);
+    threads
+}
+
+/// Do everything recorded along a path, in order, and return the output with its type.
+#[inline]
+fn replay<I: Input, C: Ctrl<I>>(
+    path: Vec<Action<'_, I>>,
+) -> Result<(Dynamic, String), IllFormed<I, C>> {
+    let mut acc: Dynamic = Box::new(());
+    let mut output_t = "()";
+    let mut saved = vec![];
+    for action in path {
+        acc = match action {
+            Action::Update(update, token) => {
+                let f = update
+                    .ptr
+                    .0
+                    .as_ref()
+                    .ok_or_else(|| IllFormed::Uncallable(update.src.to_string()))?;
+                output_t = &update.output_t;
+                f(acc, token).ok_or_else(|| {
+                    IllFormed::TypeMismatch(update.input_t.to_string(), update.output_t.to_string())
+                })?
+            }
+            Action::Open => {
+                saved.push(acc);
+                output_t = "()";
+                Box::new(())
+            }
+            Action::Close(combine) => {
+                let f = combine
+                    .ptr
+                    .0
+                    .as_ref()
+                    .ok_or_else(|| IllFormed::Uncallable(combine.src.to_string()))?;
+                output_t = &combine.output_t;
+                f(unwrap!(saved.pop()), acc).ok_or_else(|| {
+                    IllFormed::TypeMismatch(combine.lhs_t.to_string(), combine.rhs_t.to_string())
+                })?
+            }
+        };
+    }
+    Ok((acc, output_t.to_owned()))
+}
@@ -0,0 +1,156 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Choose what to do with input left over after a parser could have stopped.
+
+use crate::{Ctrl, Graph, Input, InputError, ParseError, Run};
+
+/// What to do with input left over after a parser could have stopped.
+#[allow(clippy::exhaustive_enums)]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum AcceptMode {
+    /// Reject anything left over (i.e. exactly `accept`).
+    #[default]
+    Strict,
+    /// Run on the longest prefix the parser accepts and return everything after it.
+    Prefix,
+    /// Run on the longest prefix the parser accepts and only count how many tokens came after it.
+    IgnoreTrailing,
+}
+
+/// Result of a successful run with `accept_mode`.
+#[non_exhaustive]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AcceptOutcome<I: Input> {
+    /// Output type (as in `accept`).
+    pub output: String,
+    /// Number of tokens the parser accepted.
+    pub consumed: usize,
+    /// Number of tokens after those (always zero in `AcceptMode::Strict`).
+    pub ignored: usize,
+    /// Tokens after those (only in `AcceptMode::Prefix`; otherwise empty).
+    pub remainder: Vec<I>,
+}
+
+impl<I: Input, C: Ctrl<I>> Graph<I, C> {
+    /// Run this parser and decide what to do with any input left over after it could have stopped.
+    /// # Errors
+    /// If the parser rejects this input (in `Strict` mode) or every prefix of it (otherwise).
+    #[inline]
+    pub fn accept_mode<In: IntoIterator<Item = I>>(
+        &self,
+        input: In,
+        mode: AcceptMode,
+    ) -> Result<AcceptOutcome<I>, ParseError<I, C>> {
+        if mode == AcceptMode::Strict {
+            let mut consumed: usize = 0;
+            let output = self.run_into(input, &mut consumed, |n, _| {
+                *n = n.saturating_add(1);
+            })?;
+            return Ok(AcceptOutcome {
+                output,
+                consumed,
+                ignored: 0,
+                remainder: vec![],
+            });
+        }
+        let mut tokens = input.into_iter();
+        let (accepted, output, mut pending) = self.longest_prefix(&mut tokens)?;
+        Ok(if mode == AcceptMode::Prefix {
+            pending.extend(tokens);
+            AcceptOutcome {
+                output,
+                consumed: accepted,
+                ignored: pending.len(),
+                remainder: pending,
+            }
+        } else {
+            AcceptOutcome {
+                output,
+                consumed: accepted,
+                ignored: pending.len().saturating_add(tokens.count()),
+                remainder: vec![],
+            }
+        })
+    }
+
+    /// Run this parser on the longest prefix of `input` that it accepts,
+    /// returning the output from that prefix and how many tokens it took (e.g. to split several messages out of one buffer).
+    /// Unlike `accept_mode`, this stops pulling tokens as soon as the parser can't go any further,
+    /// but it may already have read a few tokens past the prefix by then, so skip ahead by the count rather than trusting the iterator.
+    /// # Errors
+    /// If the parser rejects every prefix of this input (including the empty one).
+    #[inline]
+    pub fn accept_prefix<In: IntoIterator<Item = I>>(
+        &self,
+        input: In,
+    ) -> Result<(String, usize), ParseError<I, C>> {
+        let (accepted, output, _) = self.longest_prefix(&mut input.into_iter())?;
+        Ok((output, accepted))
+    }
+
+    /// Run each parser on the longest prefix of `input` it accepts and pick whichever took the most tokens
+    /// (i.e. maximal munch across rules, as a lexer would), breaking ties in favor of the earliest parser.
+    /// Unlike `|`, the parsers never have to agree on anything: each runs on its own.
+    /// Returns the index of the winning parser, how many tokens it took, and its output,
+    /// or `None` if no parser accepts any prefix of `input` (including the empty one).
+    #[inline]
+    pub fn longest_alternative<In: IntoIterator<Item = I>>(
+        parsers: &[Self],
+        input: In,
+    ) -> Option<(usize, usize, String)> {
+        let tokens: Vec<I> = input.into_iter().collect();
+        parsers
+            .iter()
+            .enumerate()
+            .filter_map(|(i, parser)| {
+                let (output, consumed) = parser.accept_prefix(tokens.iter().cloned()).ok()?;
+                Some((i, consumed, output))
+            })
+            .fold(None, |best, (i, consumed, output)| match best {
+                Some((_, most, _)) if most >= consumed => best,
+                Some(..) | None => Some((i, consumed, output)),
+            })
+    }
+
+    /// Feed the parser one token at a time until it stops, and return the length of and output from the longest prefix it accepted,
+    /// along with every token it took after that prefix (so we keep any token it chokes on).
+    #[inline]
+    #[allow(clippy::iter_on_empty_collections, clippy::iter_on_single_items)] // <-- We swap these out below
+    fn longest_prefix<In: Iterator<Item = I>>(
+        &self,
+        tokens: &mut In,
+    ) -> Result<(usize, String, Vec<I>), ParseError<I, C>> {
+        let mut run = None.into_iter().run(self);
+        let mut consumed: usize = 0;
+        let mut longest = None;
+        // Tokens the parser took after the last point at which it could have stopped.
+        let mut pending = vec![];
+        let error = loop {
+            if run.stack.is_empty()
+                && run
+                    .ctrl
+                    .view()
+                    .any(|i| get!(self.states, i).non_accepting.is_empty())
+            {
+                longest = Some((consumed, run.output_t.clone()));
+                pending.clear();
+            }
+            let Some(token) = tokens.next() else {
+                break run.ran_out();
+            };
+            run.input = Some(token.clone()).into_iter();
+            pending.push(token);
+            match run.next() {
+                Some(Ok(_)) => consumed = consumed.saturating_add(1),
+                Some(Err(e)) => break e,
+                None => break ParseError::BadInput(InputError::NotAccepting),
+            }
+        };
+        let (accepted, output) = longest.ok_or(error)?;
+        Ok((accepted, output, pending))
+    }
+}
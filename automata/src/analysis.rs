@@ -0,0 +1,198 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Which states input can reach with every region closed, modeling calls exactly:
+//! a call continues to its destination only if some input through its detour can actually return.
+
+use crate::{Ctrl, Curry, Graph, Input, Step, Transition};
+use core::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+
+/// An input, token by token, where `None` is a token we know exists but can't name
+/// (a wildcard in a parser that never scrutinizes any token, so we have nothing to build one from).
+pub(crate) type Word<I> = Vec<Option<I>>;
+
+/// Where a token (after any region it opens has closed again) leaves us.
+enum Landing {
+    /// In this state, with the stack exactly as it was.
+    Goto(usize),
+    /// Back in whatever region called us, having closed this one.
+    Pop,
+}
+
+/// Shortest inputs from state to state that leave the stack exactly as they found it,
+/// shared by every analysis that has to agree on what's reachable (e.g. `accepts_nothing` and `shortest_accepted`).
+pub(crate) struct Reach<'graph, I: Input, C: Ctrl<I>> {
+    /// Graph we're searching.
+    graph: &'graph Graph<I, C>,
+    /// Tokens to build inputs from (see `representative_tokens`), in order.
+    tokens: Vec<I>,
+    /// Shortest input from each state that closes the region it's in (ending with the token that returns), if any.
+    returns: Vec<Option<Word<I>>>,
+}
+
+impl<'graph, I: Step, C: Ctrl<I>> Reach<'graph, I, C> {
+    /// Figure out which states can close the region they're in, repeating until nothing gets any shorter
+    /// (since a state might only return after a call whose detour returns, and so on).
+    #[inline]
+    pub(crate) fn new(graph: &'graph Graph<I, C>) -> Self {
+        let mut reach = Self {
+            graph,
+            tokens: graph.representative_tokens().into_iter().collect(),
+            returns: vec![None; graph.states.len()],
+        };
+        // Without any calls, nothing could ever return with a region left to go back to.
+        if !graph
+            .states
+            .iter()
+            .flat_map(|state| state.transitions.values())
+            .any(|t| matches!(*t, Transition::Call { .. }))
+        {
+            return reach;
+        }
+        loop {
+            let mut changed = false;
+            for i in 0..graph.states.len() {
+                let (_, pop) = reach.search(Some(i));
+                if let Some(word) = pop {
+                    let slot = unwrap!(reach.returns.get_mut(i));
+                    if slot
+                        .as_ref()
+                        .is_none_or(|old| shortlex(&word) < shortlex(old))
+                    {
+                        *slot = Some(word);
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                return reach;
+            }
+        }
+    }
+
+    /// Shortest input (shortest first, then by token) from any of these states to each state we can reach
+    /// with the stack exactly as we started, along with a shortest input that closes the region we started in (if any).
+    #[inline]
+    pub(crate) fn search<It: IntoIterator<Item = usize>>(
+        &self,
+        from: It,
+    ) -> (BTreeMap<usize, Word<I>>, Option<Word<I>>) {
+        let mut shortest = BTreeMap::new();
+        let mut pop: Option<Word<I>> = None;
+        let mut heap: BinaryHeap<_> = from.into_iter().map(|i| Reverse((0, vec![], i))).collect();
+        while let Some(Reverse((_, word, i))) = heap.pop() {
+            if shortest.contains_key(&i) {
+                continue;
+            }
+            for (token, transition) in self.moves(i) {
+                let mut next = word.clone();
+                next.push(token);
+                let mut steps = vec![];
+                self.resolve(transition, next, &mut steps);
+                for (after, step) in steps {
+                    match step {
+                        Landing::Goto(dst) => {
+                            if !shortest.contains_key(&dst) {
+                                heap.push(Reverse((after.len(), after, dst)));
+                            }
+                        }
+                        Landing::Pop => {
+                            if pop
+                                .as_ref()
+                                .is_none_or(|old| shortlex(&after) < shortlex(old))
+                            {
+                                pop = Some(after);
+                            }
+                        }
+                    }
+                }
+            }
+            drop(shortest.insert(i, word));
+        }
+        (shortest, pop)
+    }
+
+    /// Each transition out of a state, with the least token (from `tokens`) that takes it.
+    #[inline]
+    fn moves(&self, i: usize) -> Vec<(Option<I>, &'graph Transition<I, C>)> {
+        let Some(state) = self.graph.states.get(i) else {
+            return vec![];
+        };
+        match state.transitions {
+            Curry::Wildcard(ref t) => vec![(self.tokens.first().cloned(), t)],
+            Curry::Scrutinize {
+                ref filter,
+                ref fallback,
+            } => filter
+                .0
+                .iter()
+                .map(|(range, t)| (Some(range.first.clone()), t))
+                .chain(fallback.iter().map(|t| {
+                    (
+                        self.tokens
+                            .iter()
+                            .find(|token| !filter.0.keys().any(|range| range.contains(token)))
+                            .cloned(),
+                        t,
+                    )
+                }))
+                .collect(),
+        }
+    }
+
+    /// Take a transition after reading `word`, skipping over any region it opens with a shortest input that closes it again.
+    #[inline]
+    fn resolve(
+        &self,
+        t: &Transition<I, C>,
+        mut word: Word<I>,
+        steps: &mut Vec<(Word<I>, Landing)>,
+    ) {
+        match *t {
+            Transition::Lateral { ref dst, .. } => {
+                steps.extend(dst.view().map(|i| (word.clone(), Landing::Goto(i))));
+            }
+            Transition::Return { .. } => steps.push((word, Landing::Pop)),
+            Transition::Call {
+                ref detour,
+                ref dst,
+                ..
+            } => {
+                let Some(detour_word) = detour
+                    .view()
+                    .filter_map(|i| self.returns.get(i)?.as_ref())
+                    .min_by_key(|w| shortlex(w))
+                else {
+                    return;
+                };
+                word.extend(detour_word.iter().cloned());
+                self.resolve(dst, word, steps);
+            }
+        }
+    }
+
+    /// Shortest input to each accepting state we can reach from the start with every region closed.
+    #[inline]
+    pub(crate) fn accepting(&self) -> BTreeMap<usize, Word<I>> {
+        self.search(self.graph.initial.view())
+            .0
+            .into_iter()
+            .filter(|&(i, _)| {
+                self.graph
+                    .states
+                    .get(i)
+                    .is_some_and(|state| state.non_accepting.is_empty())
+            })
+            .collect()
+    }
+}
+
+/// Order inputs shortest first, then by token.
+#[inline]
+const fn shortlex<I: Input>(word: &Word<I>) -> (usize, &Word<I>) {
+    (word.len(), word)
+}
@@ -0,0 +1,114 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Assert that a parser produces a particular value, explaining what went wrong if not.
+
+use crate::{Deterministic, Input, ParseError, Run, ToSrc};
+use core::fmt;
+
+impl<I: Input> Deterministic<I> {
+    /// Compute an output as in `accept` and check that it equals `expected`, for use in tests.
+    /// # Panics
+    /// If the parser rejects this input or its output doesn't equal `expected`,
+    /// with a line-by-line diff of the two (as `{:#?}`) and a trace of every token the parser read.
+    #[inline]
+    #[track_caller]
+    #[allow(clippy::needless_pass_by_value, clippy::panic)]
+    pub fn assert_parses_to<
+        O: 'static + fmt::Debug + PartialEq + ToSrc,
+        In: IntoIterator<Item = I>,
+    >(
+        &self,
+        input: In,
+        expected: O,
+    ) {
+        let tokens: Vec<I> = input.into_iter().collect();
+        let problem = match self
+            .clone()
+            .typed::<O>()
+            .map_err(ParseError::BadParser)
+            .and_then(|typed| typed.accept(tokens.iter().cloned()))
+        {
+            Ok(actual) if actual == expected => return,
+            Ok(actual) => format!(
+                "Parsed the wrong value (- expected, + actual):\n{}",
+                diff(&format!("{expected:#?}"), &format!("{actual:#?}")),
+            ),
+            Err(e) => format!("Expected {expected:?} but the parser rejected its input: {e}"),
+        };
+        panic!("{problem}\nTrace:\n{}", self.trace(tokens));
+    }
+
+    /// One line per token: its index, the token itself, and where the parser went from there.
+    #[inline]
+    fn trace(&self, tokens: Vec<I>) -> String {
+        let mut lines = vec![format!("      start  state {}", self.initial)];
+        let mut run = tokens.into_iter().run(self);
+        let mut index = 0_usize;
+        while let Some(step) = run.next() {
+            match step {
+                Ok(token) => lines.push(format!(
+                    "{index:>4}  {}  state {}, {} region(s) open",
+                    token.to_src(),
+                    run.ctrl,
+                    run.stack.len(),
+                )),
+                Err(e) => {
+                    lines.push(format!("{index:>4}  stopped: {e}"));
+                    break;
+                }
+            }
+            index = index.saturating_add(1);
+        }
+        lines.join("\n")
+    }
+}
+
+/// Mark each line only in `expected` with `-`, each line only in `actual` with `+`, and each shared line with neither.
+#[inline]
+fn diff(expected: &str, actual: &str) -> String {
+    let lhs: Vec<&str> = expected.lines().collect();
+    let rhs: Vec<&str> = actual.lines().collect();
+    // Longest common subsequence of every pair of suffixes.
+    let mut lcs = vec![vec![0_usize; rhs.len().saturating_add(1)]; lhs.len().saturating_add(1)];
+    for (i, l) in lhs.iter().enumerate().rev() {
+        for (j, r) in rhs.iter().enumerate().rev() {
+            let n = if l == r {
+                get!(get!(lcs, i.saturating_add(1)), j.saturating_add(1)).saturating_add(1)
+            } else {
+                *get!(get!(lcs, i.saturating_add(1)), j)
+                    .max(get!(get!(lcs, i), j.saturating_add(1)))
+            };
+            *unwrap!(unwrap!(lcs.get_mut(i)).get_mut(j)) = n;
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut lines = vec![];
+    while i < lhs.len() || j < rhs.len() {
+        let next = (lhs.get(i), rhs.get(j));
+        if let (Some(l), Some(r)) = next {
+            if l == r {
+                lines.push(format!("  {l}"));
+                (i, j) = (i.saturating_add(1), j.saturating_add(1));
+                continue;
+            }
+        }
+        let take_left = match next {
+            (Some(_), Some(_)) => {
+                get!(get!(lcs, i.saturating_add(1)), j) >= get!(get!(lcs, i), j.saturating_add(1))
+            }
+            (left, _) => left.is_some(),
+        };
+        if take_left {
+            lines.push(format!("- {}", get!(lhs, i)));
+            i = i.saturating_add(1);
+        } else {
+            lines.push(format!("+ {}", get!(rhs, j)));
+            j = j.saturating_add(1);
+        }
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,174 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Check on sampled inputs that a deterministic parser behaves like the parser it came from.
+
+use crate::{Ctrl, Deterministic, Graph, IllFormed, Input, ParseError, Step, ToSrc};
+use core::fmt;
+use std::collections::BTreeSet;
+
+/// Witness that determinizing a parser changed its behavior (or couldn't happen at all).
+#[non_exhaustive]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DeterminizationMismatch<I: Input, C: Ctrl<I>> {
+    /// Couldn't determinize the parser in the first place.
+    IllFormed(IllFormed<I, C>),
+    /// An input on which the two parsers disagree.
+    Counterexample {
+        /// The input itself.
+        input: Vec<I>,
+        /// What the original parser did with it.
+        original: Result<String, ParseError<I, C>>,
+        /// What the deterministic parser did with it.
+        deterministic: Result<String, ParseError<I, usize>>,
+    },
+}
+
+impl<I: Input, C: Ctrl<I>> fmt::Display for DeterminizationMismatch<I, C> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::IllFormed(ref e) => write!(f, "Couldn't determinize: {e}"),
+            Self::Counterexample {
+                ref input,
+                ref original,
+                ref deterministic,
+            } => write!(
+                f,
+                "On input {}, the original parser {} \
+                but the deterministic parser {}",
+                input.to_src(),
+                describe(original),
+                describe(deterministic),
+            ),
+        }
+    }
+}
+
+impl<I: Step, C: Ctrl<I>> Graph<I, C> {
+    /// Determinize this parser, then run both on `samples` inputs
+    /// (built from tokens at the edges of every range) and check that they agree:
+    /// half are every input up to some length, and half are longer pseudorandom inputs.
+    /// Meant for users' own test suites, to gain confidence in hand-built parsers.
+    /// # Errors
+    /// If this parser can't be determinized, or with the first input on which the two disagree.
+    #[inline]
+    #[allow(clippy::result_large_err)]
+    pub fn audit_determinization(
+        &self,
+        samples: usize,
+    ) -> Result<(), DeterminizationMismatch<I, C>> {
+        let deterministic = self
+            .determinize()
+            .map_err(DeterminizationMismatch::IllFormed)?;
+        self.audit_against(&deterministic, samples)
+    }
+
+    /// Run this parser and a supposedly equivalent deterministic parser on `samples` inputs
+    /// (built as in `audit_determinization`, but from the tokens in either parser) and check that they agree.
+    /// # Errors
+    /// With the first input on which the two disagree.
+    #[inline]
+    #[allow(clippy::result_large_err)]
+    pub fn audit_against(
+        &self,
+        deterministic: &Deterministic<I>,
+        samples: usize,
+    ) -> Result<(), DeterminizationMismatch<I, C>> {
+        let tokens: Vec<I> = self
+            .representative_tokens()
+            .into_iter()
+            .chain(deterministic.representative_tokens())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        if tokens.is_empty() {
+            return Ok(());
+        }
+        let max_len = self.states.len().saturating_mul(2).saturating_add(2);
+        let mut rng = XorShift(0x_2545_F491_4F6C_DD1D);
+        let exhaustive = samples.div_ceil(2);
+        for sample in 0..samples {
+            // Every short input first (shortest first), then longer pseudorandom inputs.
+            let input: Vec<I> = if sample < exhaustive {
+                nth_input(&tokens, sample)
+            } else {
+                let len = rng.below(max_len.saturating_add(1));
+                (0..len)
+                    .map(|_| get!(tokens, rng.below(tokens.len())).clone())
+                    .collect()
+            };
+            let original = self.accept(input.iter().cloned());
+            let det = deterministic.accept(input.iter().cloned());
+            let agree = match (&original, &det) {
+                (&Ok(ref lhs), &Ok(ref rhs)) => lhs == rhs,
+                (
+                    &Err(ParseError::BadInput(_) | ParseError::Incomplete { .. }),
+                    &Err(ParseError::BadInput(_) | ParseError::Incomplete { .. }),
+                ) => true,
+                _ => false,
+            };
+            if !agree {
+                return Err(DeterminizationMismatch::Counterexample {
+                    input,
+                    original,
+                    deterministic: det,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The `n`th input in order of length, then lexicographically (i.e. `n` in bijective base `tokens.len()`).
+#[inline]
+fn nth_input<I: Input>(tokens: &[I], mut n: usize) -> Vec<I> {
+    let mut input = vec![];
+    while let Some(rest) = n.checked_sub(1) {
+        input.push(get!(tokens, rest.checked_rem(tokens.len()).unwrap_or(0)).clone());
+        n = rest.checked_div(tokens.len()).unwrap_or(0);
+    }
+    input.reverse();
+    input
+}
+
+/// What a parser did with an input, in words.
+#[inline]
+fn describe<I: Input, C: Ctrl<I>>(result: &Result<String, ParseError<I, C>>) -> String {
+    match *result {
+        Ok(ref output_t) => format!("accepted it (returning a `{output_t}`)"),
+        Err(ParseError::BadInput(ref e)) => format!("rejected it ({e:?})"),
+        Err(ParseError::Incomplete { ref open, .. }) if open.is_empty() => {
+            "ran out of input".to_owned()
+        }
+        Err(ParseError::Incomplete { ref open, .. }) => {
+            format!("ran out of input with {open:?} still open")
+        }
+        Err(ParseError::BadParser(ref e)) => format!("broke ({e})"),
+    }
+}
+
+/// Tiny deterministic pseudorandom number generator (so audits are reproducible without any dependency).
+struct XorShift(u64);
+
+impl XorShift {
+    /// Pseudorandom number less than `n` (or zero if `n` is zero).
+    #[inline]
+    #[allow(
+        clippy::arithmetic_side_effects,
+        clippy::as_conversions,
+        clippy::cast_possible_truncation
+    )]
+    const fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13_u32;
+        self.0 ^= self.0 >> 7_u32;
+        self.0 ^= self.0 << 17_u32;
+        match self.0.checked_rem(n as u64) {
+            Some(r) => r as usize,
+            None => 0,
+        }
+    }
+}
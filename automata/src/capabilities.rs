@@ -0,0 +1,83 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Summarize what a parser ever does, so algorithms that can't handle something can check up front.
+
+use crate::{Ctrl, Curry, Graph, Input, Transition};
+use core::ops;
+
+/// What a parser ever does (anywhere, reachable or not), computed in one pass with `Graph::capabilities`.
+#[allow(clippy::exhaustive_structs, clippy::struct_excessive_bools)]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Capabilities {
+    /// Some state has a fallback transition.
+    pub fallback: bool,
+    /// Some transition opens or closes a region.
+    pub calls: bool,
+    /// Some state takes the same transition on every token.
+    pub wildcards: bool,
+    /// Some transition calls an update on the output.
+    pub updates: bool,
+}
+
+impl ops::BitOr for Capabilities {
+    type Output = Self;
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self {
+        Self {
+            fallback: self.fallback || rhs.fallback,
+            calls: self.calls || rhs.calls,
+            wildcards: self.wildcards || rhs.wildcards,
+            updates: self.updates || rhs.updates,
+        }
+    }
+}
+
+impl ops::BitOrAssign for Capabilities {
+    #[inline]
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = *self | rhs;
+    }
+}
+
+impl<I: Input, C: Ctrl<I>> Graph<I, C> {
+    /// Summarize what this parser ever does, in one pass over every state.
+    #[inline]
+    #[must_use]
+    pub fn capabilities(&self) -> Capabilities {
+        let mut capabilities = Capabilities::default();
+        for state in &self.states {
+            capabilities.fallback |= state.involves_any_fallback();
+            capabilities.wildcards |= matches!(state.transitions, Curry::Wildcard(..));
+            for t in state.transitions.values() {
+                capabilities.calls |= !matches!(*t, Transition::Lateral { .. });
+                capabilities.updates |= t.update().is_some();
+            }
+        }
+        capabilities
+    }
+
+    /// Check if this parser ever could, at any point, open or close a region.
+    #[inline]
+    #[must_use]
+    pub fn involves_calls(&self) -> bool {
+        self.capabilities().calls
+    }
+
+    /// Check if this parser ever could, at any point, take the same transition on every token.
+    #[inline]
+    #[must_use]
+    pub fn involves_wildcards(&self) -> bool {
+        self.capabilities().wildcards
+    }
+
+    /// Check if this parser ever could, at any point, call an update on its output.
+    #[inline]
+    #[must_use]
+    pub fn involves_updates(&self) -> bool {
+        self.capabilities().updates
+    }
+}
@@ -6,8 +6,8 @@
 
 //! Check well-formedness.
 
-use crate::{Ctrl, Curry, Input, Range, RangeMap, State, ToSrc, Transition, Update, FF};
-use core::{fmt, mem, num::NonZeroUsize};
+use crate::{Ctrl, Curry, Input, Range, RangeMap, State, Step, ToSrc, Transition, Update, FF};
+use core::{error, fmt, mem, num::NonZeroUsize};
 use std::collections::BTreeSet;
 
 /// Maximum size we're willing to tolerate in an `Err` variant (for performance reasons).
@@ -22,21 +22,26 @@ const _: () = [(); _MAX_ILL_FORMED_BYTES][mem::size_of::<IllFormed<(), usize>>()
 pub enum IllFormed<I: Input, C: Ctrl<I>> {
     /// An index points to a state greater than the total number of states.
     OutOfBounds(usize),
+    /// A parser has no initial state, so it could never accept anything (not even the empty input).
+    EmptyInitial,
+    /// An initial state index is greater than the total number of states.
+    InitialOutOfBounds(usize),
     /// A set of indices contains no elements (we should just delete the transition).
     ProlongingDeath,
     /// A `Range`'s `first` field measured greater than its `last` field.
     InvertedRange(I, I),
+    /// A state has a transition on a range whose `first` field measured greater than its `last` field,
+    /// so that transition could never match (usually two swapped bounds).
+    EmptyRange {
+        /// Index of the state with the offending transition.
+        state: usize,
+        /// The range with its endpoints flipped.
+        range: Range<I>,
+    },
     /// In a `RangeMap`, at least one key could be accepted by two existing ranges of keys.
     RangeMapOverlap(Range<I>),
-    /// In a `Curry`, a wildcard matches an input that a specific key also matches.
-    WildcardMask {
-        /// Input token (or range thereof) that could be ambiguous.
-        arg_token: Option<Range<I>>,
-        /// First output possibility.
-        possibility_1: Box<Transition<I, C>>,
-        /// Second output possibility.
-        possibility_2: Box<Transition<I, C>>,
-    },
+    /// In a `Curry`, the explicit ranges already cover every possible token, so the fallback could never run.
+    UnreachableFallback(Box<Transition<I, C>>),
     /// Can't go to two different (deterministic) states at the same time.
     Superposition(usize, usize),
     /// Can't call two different functions on half-constructed outputs at the same time.
@@ -55,8 +60,40 @@ pub enum IllFormed<I: Input, C: Ctrl<I>> {
     TypeMismatch(String, String),
     /// An accepting state returns the wrong type.
     WrongReturnType(String, String),
+    /// One parser's output runs into another that expects a different accumulator (e.g. across `>>`).
+    ComposeTypeMismatch {
+        /// Type coming out of the transition on the left.
+        lhs_out: String,
+        /// Type the state on the right expects.
+        rhs_in: String,
+    },
     /// Ambiguous regions: e.g. claiming to be opening both parentheses and brackets at the same time.
     AmbiguousRegions(&'static str, &'static str),
+    /// Reference to a region, but no transition ever opens that region.
+    RegionDNE(&'static str),
+    /// Tried to split a range of tokens just before a token that has no immediate predecessor.
+    Unsplittable(I),
+    /// Tried to take the product of parsers, but at least one of them opens or closes a region.
+    ProductOverRegion(&'static str),
+    /// Tried to call a function while interpreting a parser, but we only have its source code.
+    Uncallable(String),
+    /// A token (or range thereof) could be either skipped or significant
+    /// (with `None` if a parser to skip accepts anything whatsoever).
+    SkipOverlap(Option<Range<I>>),
+    /// Tried to write a parser as a `const` table, but it opens or closes a region.
+    ConstOverRegion(&'static str),
+    /// Tried to take the complement of a parser, but it calls an update (with this source code)
+    /// that would never run on any input the complement accepts.
+    ComplementOverUpdate(String),
+    /// A function (with this source code) reads each token's payload, but tokens don't come with payloads
+    /// (e.g. in `accept` instead of `accept_with_payload`, or without `CodegenOptions::payload`).
+    MissingPayload(String),
+    /// Tried to fuse a function (with this source code) that reads each token's payload with another function,
+    /// but only one function can take each payload.
+    FusedPayload(String),
+    /// Tried to collect the tokens a parser reads, but it accepts the empty input,
+    /// which reads no tokens and so couldn't produce a collection.
+    CollectOverEmpty,
 }
 
 impl<I: Input> IllFormed<I, usize> {
@@ -66,18 +103,15 @@ impl<I: Input> IllFormed<I, usize> {
     pub fn convert_ctrl<C: Ctrl<I>>(self) -> IllFormed<I, C> {
         match self {
             IllFormed::OutOfBounds(i) => IllFormed::OutOfBounds(i),
+            IllFormed::EmptyInitial => IllFormed::EmptyInitial,
+            IllFormed::InitialOutOfBounds(i) => IllFormed::InitialOutOfBounds(i),
             IllFormed::ProlongingDeath => IllFormed::ProlongingDeath,
             IllFormed::InvertedRange(a, b) => IllFormed::InvertedRange(a, b),
+            IllFormed::EmptyRange { state, range } => IllFormed::EmptyRange { state, range },
             IllFormed::RangeMapOverlap(range) => IllFormed::RangeMapOverlap(range),
-            IllFormed::WildcardMask {
-                arg_token,
-                possibility_1,
-                possibility_2,
-            } => IllFormed::WildcardMask {
-                arg_token,
-                possibility_1: Box::new(possibility_1.convert_ctrl()),
-                possibility_2: Box::new(possibility_2.convert_ctrl()),
-            },
+            IllFormed::UnreachableFallback(t) => {
+                IllFormed::UnreachableFallback(Box::new(t.convert_ctrl()))
+            }
             IllFormed::Superposition(a, b) => IllFormed::Superposition(a, b),
             IllFormed::IncompatibleCallbacks(a, b) => IllFormed::IncompatibleCallbacks(a, b),
             IllFormed::IncompatibleCombinators(a, b) => IllFormed::IncompatibleCombinators(a, b),
@@ -90,16 +124,32 @@ impl<I: Input> IllFormed<I, usize> {
             IllFormed::InitialNotUnit(s) => IllFormed::InitialNotUnit(s),
             IllFormed::TypeMismatch(a, b) => IllFormed::TypeMismatch(a, b),
             IllFormed::WrongReturnType(a, b) => IllFormed::WrongReturnType(a, b),
+            IllFormed::ComposeTypeMismatch { lhs_out, rhs_in } => {
+                IllFormed::ComposeTypeMismatch { lhs_out, rhs_in }
+            }
             IllFormed::AmbiguousRegions(a, b) => IllFormed::AmbiguousRegions(a, b),
+            IllFormed::RegionDNE(region) => IllFormed::RegionDNE(region),
+            IllFormed::Unsplittable(token) => IllFormed::Unsplittable(token),
+            IllFormed::ProductOverRegion(region) => IllFormed::ProductOverRegion(region),
+            IllFormed::Uncallable(src) => IllFormed::Uncallable(src),
+            IllFormed::SkipOverlap(range) => IllFormed::SkipOverlap(range),
+            IllFormed::ConstOverRegion(region) => IllFormed::ConstOverRegion(region),
+            IllFormed::ComplementOverUpdate(src) => IllFormed::ComplementOverUpdate(src),
+            IllFormed::MissingPayload(src) => IllFormed::MissingPayload(src),
+            IllFormed::FusedPayload(src) => IllFormed::FusedPayload(src),
+            IllFormed::CollectOverEmpty => IllFormed::CollectOverEmpty,
         }
     }
 }
 
 impl<I: Input, C: Ctrl<I>> fmt::Display for IllFormed<I, C> {
     #[inline]
+    #[allow(clippy::too_many_lines)]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
             Self::OutOfBounds(i) => write!(f, "State index out of bounds: {i}"),
+            Self::EmptyInitial => write!(f, "No initial state, so nothing could ever be accepted."),
+            Self::InitialOutOfBounds(i) => write!(f, "Initial state index out of bounds: {i}"),
             Self::ProlongingDeath => write!(
                 f,
                 "Transition to a state that will never accept. \
@@ -113,30 +163,21 @@ impl<I: Input, C: Ctrl<I>> fmt::Display for IllFormed<I, C> {
                     b.to_src(),
                 )
             }
+            Self::EmptyRange { state, ref range } => write!(
+                f,
+                "State {state} has a transition on {}..={}, which could never match. \
+                Did you swap its endpoints?",
+                range.first.to_src(),
+                range.last.to_src(),
+            ),
             Self::RangeMapOverlap(ref r) => {
                 write!(f, "Multiple ranges would accept {}", r.to_src())
             }
-            Self::WildcardMask {
-                ref arg_token,
-                ref possibility_1,
-                ref possibility_2,
-            } => {
-                write!(
-                    f,
-                    "On token {}, \
-                    a wildcard match succeeds (`{}`), \
-                    but so does a specific match (`{}`).",
-                    arg_token.as_ref().map_or("[end of input]".to_owned(), |r| {
-                        if r.first == r.last {
-                            r.first.to_src()
-                        } else {
-                            r.to_src()
-                        }
-                    }),
-                    possibility_1.to_src(),
-                    possibility_2.to_src(),
-                )
-            }
+            Self::UnreachableFallback(ref t) => write!(
+                f,
+                "Explicit ranges cover every possible token, so the fallback (`{}`) could never run.",
+                t.to_src(),
+            ),
             Self::Superposition(a, b) => write!(
                 f,
                 "Tried to visit two different deterministic states \
@@ -173,14 +214,78 @@ impl<I: Input, C: Ctrl<I>> fmt::Display for IllFormed<I, C> {
             ),
             Self::TypeMismatch(ref a, ref b) => write!(f, "Type mismatch: `{a}` =/= `{b}`."),
             Self::WrongReturnType(ref a, ref b) => write!(f, "Wrong output type: `{a}` =/= `{b}`"),
+            Self::ComposeTypeMismatch {
+                ref lhs_out,
+                ref rhs_in,
+            } => write!(
+                f,
+                "One parser outputs `{lhs_out}` into another that expects `{rhs_in}`: \
+                check the types where they're combined (e.g. with `>>`)."
+            ),
             Self::AmbiguousRegions(a, b) => write!(
                 f,
                 "Claiming to open two different regions (\"{a}\" and \"{b}\") simultaneously."
             ),
+            Self::RegionDNE(region) => write!(
+                f,
+                "Requested a region that no transition ever opens: \"{region}\"",
+            ),
+            Self::Unsplittable(ref token) => write!(
+                f,
+                "Tried to split a range of tokens just before {}, \
+                but nothing comes immediately before it.",
+                token.to_src(),
+            ),
+            Self::ProductOverRegion(region) => write!(
+                f,
+                "Products (intersection, difference, etc.) can't handle regions, \
+                but one of these parsers uses \"{region}\".",
+            ),
+            Self::Uncallable(ref src) => write!(
+                f,
+                "Can't call `{src}` without compiling it: \
+                construct functions with `update!` or `ff!` to call them in the interpreter.",
+            ),
+            Self::SkipOverlap(None) => write!(
+                f,
+                "Tried to skip any token whatsoever, \
+                so no token could ever be significant.",
+            ),
+            Self::SkipOverlap(Some(ref r)) => write!(
+                f,
+                "Tokens in {} could be either skipped or significant.",
+                r.to_src(),
+            ),
+            Self::ConstOverRegion(region) => write!(
+                f,
+                "`const` tables can only decide acceptance without a stack, \
+                but this parser uses \"{region}\".",
+            ),
+            Self::ComplementOverUpdate(ref src) => write!(
+                f,
+                "A complement accepts exactly what the original rejects, \
+                so it can't compute the original's output, but this parser calls `{src}`.",
+            ),
+            Self::MissingPayload(ref src) => write!(
+                f,
+                "`{src}` reads each token's payload, but tokens here don't come with payloads.",
+            ),
+            Self::FusedPayload(ref src) => write!(
+                f,
+                "Can't fuse `{src}` with another function, \
+                since it reads each token's payload and only one function can take it.",
+            ),
+            Self::CollectOverEmpty => write!(
+                f,
+                "Can't collect the tokens this parser reads, \
+                since it accepts the empty input, which has no tokens to collect.",
+            ),
         }
     }
 }
 
+impl<I: Input + fmt::Debug, C: Ctrl<I> + fmt::Debug> error::Error for IllFormed<I, C> {}
+
 /// Check well-formedness.
 pub trait Check<I: Input, C: Ctrl<I>> {
     /// Check well-formedness.
@@ -204,7 +309,7 @@ impl<I: Input> Check<I, BTreeSet<usize>> for BTreeSet<usize> {
     }
 }
 
-impl<I: Input, C: Ctrl<I>> Check<I, C> for Curry<I, C> {
+impl<I: Step, C: Ctrl<I>> Check<I, C> for Curry<I, C> {
     #[inline]
     fn check(&self, n_states: NonZeroUsize) -> Result<(), IllFormed<I, C>> {
         match *self {
@@ -215,6 +320,9 @@ impl<I: Input, C: Ctrl<I>> Check<I, C> for Curry<I, C> {
             } => {
                 if let &Some(ref f) = fallback {
                     f.check(n_states)?;
+                    if filter.covers_everything() {
+                        return Err(IllFormed::UnreachableFallback(Box::new(f.clone())));
+                    }
                 }
                 filter.check(n_states)
             }
@@ -253,7 +361,7 @@ impl<I: Input, C: Ctrl<I>> Check<I, C> for RangeMap<I, C> {
     }
 }
 
-impl<I: Input, C: Ctrl<I>> Check<I, C> for State<I, C> {
+impl<I: Step, C: Ctrl<I>> Check<I, C> for State<I, C> {
     #[inline]
     fn check(&self, n_states: NonZeroUsize) -> Result<(), IllFormed<I, C>> {
         self.transitions.check(n_states)
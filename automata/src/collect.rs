@@ -0,0 +1,86 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Replace a parser's output with the tokens it matched.
+
+use crate::{Deterministic, IllFormed, Input, State, Transition, Update};
+
+impl<I: Input> Deterministic<I> {
+    /// Throw away this parser's output and instead collect every token it reads into a `Vec`.
+    /// # Errors
+    /// If this parser opens or closes a region,
+    /// or if it accepts empty input (which would return `()` instead of a `Vec`).
+    #[inline]
+    pub fn collect_matched(&self) -> Result<Self, IllFormed<I, usize>>
+    where
+        I: 'static,
+    {
+        self.replace_updates(
+            &update!(|(), token| vec![token]),
+            &update!(|mut v: Vec<_>, token| {
+                v.push(token);
+                v
+            }),
+        )
+    }
+
+    /// Replace every update with one of two functions:
+    /// `first` on the very first token and `rest` on every token after that.
+    #[inline]
+    fn replace_updates(
+        &self,
+        first: &Update<I>,
+        rest: &Update<I>,
+    ) -> Result<Self, IllFormed<I, usize>> {
+        self.reject_regions()?;
+        let initial = get!(self.states, self.initial);
+        if initial.non_accepting.is_empty() {
+            return Err(IllFormed::CollectOverEmpty);
+        }
+        let replace = |mut state: State<I, usize>, update: &Update<I>| {
+            for t in state.transitions.values_mut() {
+                if let Transition::Lateral {
+                    update: ref mut u, ..
+                } = *t
+                {
+                    *u = Some(update.clone());
+                }
+            }
+            state
+        };
+        let mut states: Vec<_> = self
+            .states
+            .iter()
+            .map(|state| replace(state.clone(), rest))
+            .collect();
+        let fresh = states.len();
+        states.push(replace(initial.clone(), first));
+        let mut out = Self {
+            states,
+            initial: fresh,
+        }
+        .trim();
+        out.sort();
+        Ok(out)
+    }
+}
+
+impl Deterministic<char> {
+    /// Throw away this parser's output and instead collect every character it reads into a `String`.
+    /// # Errors
+    /// If this parser opens or closes a region,
+    /// or if it accepts empty input (which would return `()` instead of a `String`).
+    #[inline]
+    pub fn collect_matched_string(&self) -> Result<Self, IllFormed<char, usize>> {
+        self.replace_updates(
+            &update!(|(), c| String::from(c)),
+            &update!(|mut s: String, c| {
+                s.push(c);
+                s
+            }),
+        )
+    }
+}
@@ -8,21 +8,106 @@
 
 #![allow(clippy::manual_assert, clippy::match_wild_err_arm, clippy::panic)]
 
-use crate::{Ctrl, Curry, Deterministic, Graph, Input, Merge, RangeMap, State, Transition, FF};
-use core::{iter, mem, ops};
-use std::collections::BTreeSet;
+use crate::{
+    Ctrl, Curry, Deterministic, Graph, IllFormed, Input, Merge, Nondeterministic, RangeMap,
+    Rejection, State, Step, Transition, FF,
+};
+use core::{iter, ops};
+use std::collections::{BTreeMap, BTreeSet};
 
-impl<I: Input> ops::BitOr<Self> for Deterministic<I> {
+impl<I: Step, Rhs: Into<Self>> ops::BitOr<Rhs> for Deterministic<I> {
     type Output = Self;
     #[inline]
-    fn bitor(self, rhs: Self) -> Self {
+    fn bitor(self, rhs: Rhs) -> Self {
+        self.try_union(rhs.into()).unwrap_or_else(|e| panic!("{e}"))
+    }
+}
+
+impl<I: Step> Deterministic<I> {
+    /// Union exactly as `|`, but returning any conflict between the two parsers instead of panicking.
+    /// # Errors
+    /// If the two parsers would act differently on the same input (e.g. call different functions).
+    #[inline]
+    pub(crate) fn try_union(self, rhs: Self) -> Result<Self, IllFormed<I, BTreeSet<usize>>> {
+        self.disjoint_union(&rhs)
+            .map_or_else(|| self.determinized_union(rhs), Ok)
+    }
+
+    /// Concatenation exactly as `>>`, but returning any conflict between the two parsers instead of panicking.
+    /// # Errors
+    /// If the two parsers would act differently on the same input (e.g. call different functions).
+    #[inline]
+    pub(crate) fn try_concat(self, rhs: Self) -> Result<Self, IllFormed<I, BTreeSet<usize>>> {
+        let mut out = self.concat_nondeterministic(rhs);
+        out.sort();
+        out.determinize()
+    }
+
+    /// Union without determinizing, if the two parsers provably never compete for the same input:
+    /// i.e. if neither initial state has a fallback and no token starts an input in both.
+    /// Then the only new state is the merged initial state, since each later state belongs to exactly one parser.
+    /// `None` if we can't tell so cheaply (in which case `|` determinizes as usual).
+    #[inline]
+    #[must_use]
+    pub(crate) fn disjoint_union(&self, other: &Self) -> Option<Self> {
+        let lhs = self.states.get(self.initial)?;
+        let rhs = other.states.get(other.initial)?;
+        let (
+            &Curry::Scrutinize {
+                filter: ref lhs_filter,
+                fallback: None,
+            },
+            &Curry::Scrutinize {
+                filter: ref rhs_filter,
+                fallback: None,
+            },
+        ) = (&lhs.transitions, &rhs.transitions)
+        else {
+            return None;
+        };
+        lhs_filter.disjoint(rhs_filter).ok()?;
+        let size = self.states.len();
+        let shifted = other
+            .clone()
+            .map_indices(|i| i.checked_add(size).expect("Absurdly huge number of states"));
+        let initial = lhs
+            .clone()
+            .merge(shifted.states.get(other.initial)?.clone())
+            .ok()?;
+        let mut states = self.states.clone();
+        states.extend(shifted.states);
+        let out_initial = states.len();
+        states.push(initial);
+        let out = Graph {
+            states,
+            initial: out_initial,
+        };
+        let mut trimmed = out.trim();
+        // Coalesce exactly as determinizing would have.
+        for state in &mut trimmed.states {
+            if let Curry::Scrutinize { ref mut filter, .. } = state.transitions {
+                filter.coalesce();
+            }
+        }
+        trimmed.sort();
+        // Mismatched types are an error either way, so leave reporting it to the usual path.
+        trimmed.check_fragment().ok()?;
+        Some(trimmed)
+    }
+
+    /// Union by running both parsers side by side and determinizing, as `|` does unless `disjoint_union` applies.
+    /// # Errors
+    /// If the two parsers would act differently on the same input (e.g. call different functions).
+    #[inline]
+    pub(crate) fn determinized_union(
+        self,
+        rhs: Self,
+    ) -> Result<Self, IllFormed<I, BTreeSet<usize>>> {
         let mut s = self.generalize();
         let other = rhs.generalize();
         // Note that union on pushdown automata is undecidable;
         // we presumably reject a subset of automata that might possibly work.
-        if s.check().is_err() {
-            panic!("Internal error")
-        }
+        s.check_fragment()?;
         let size = s.states.len();
         let Graph {
             states: other_states,
@@ -31,18 +116,37 @@ impl<I: Input> ops::BitOr<Self> for Deterministic<I> {
         s.states.extend(other_states);
         s.initial.extend(other_initial);
         s.sort();
-        s.determinize().unwrap_or_else(|e| panic!("{e}"))
+        s.determinize()
+    }
+}
+
+impl<I: Step, Rhs: Into<Self>> ops::BitAnd<Rhs> for Deterministic<I> {
+    type Output = Self;
+    #[inline]
+    fn bitand(self, rhs: Rhs) -> Self {
+        self.product_with(&rhs.into(), |l, r| l && r)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+}
+
+impl<I: Step, Rhs: Into<Self>> ops::Add<Rhs> for Deterministic<I> {
+    type Output = Self;
+    #[inline]
+    fn add(self, rhs: Rhs) -> Self {
+        self.ordered_union(&rhs.into())
+            .unwrap_or_else(|e| panic!("{e}"))
     }
 }
 
-impl<I: Input> ops::Rem<Transition<I, usize>> for Deterministic<I> {
+impl<I: Step> ops::Rem<Transition<I, usize>> for Deterministic<I> {
     type Output = Self;
     #[inline]
     fn rem(mut self, rhs: Transition<I, usize>) -> Self::Output {
         for state in &mut self.states {
             if state.non_accepting.is_empty() {
                 if let Curry::Scrutinize {
-                    ref mut fallback, ..
+                    ref filter,
+                    ref mut fallback,
                 } = state.transitions
                 {
                     assert!(
@@ -50,7 +154,9 @@ impl<I: Input> ops::Rem<Transition<I, usize>> for Deterministic<I> {
                         "Tried to add a fallback transition, \
                         but a fallback already existed.",
                     );
-                    *fallback = Some(rhs.clone());
+                    if !filter.covers_everything() {
+                        *fallback = Some(rhs.clone());
+                    }
                 }
             }
         }
@@ -58,34 +164,66 @@ impl<I: Input> ops::Rem<Transition<I, usize>> for Deterministic<I> {
     }
 }
 
-impl<I: Input> ops::Shr<Self> for Deterministic<I> {
+impl<I: Step, Rhs: Into<Self>> ops::Shr<Rhs> for Deterministic<I> {
     type Output = Self;
     #[inline]
-    fn shr(mut self, other: Self) -> Self::Output {
-        let rhs_init = get!(other.states, other.initial)
-            .transitions
-            .clone()
-            .generalize();
+    fn shr(self, rhs: Rhs) -> Self::Output {
+        self.try_concat(rhs.into())
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+}
+
+impl<I: Step, Rhs: Into<Self>> ops::Shl<Rhs> for Deterministic<I> {
+    type Output = Self;
+    /// Concatenation that keeps only the left-hand output: `a << b` reads exactly what `a >> b` reads,
+    /// but throws away whatever `b` would compute (see `discard`).
+    /// # Panics
+    /// If `b` opens or closes a region, or if the combined parser would be ambiguous.
+    #[inline]
+    #[allow(clippy::arithmetic_side_effects, clippy::suspicious_arithmetic_impl)] // <-- Concatenation with `>>`
+    fn shl(self, rhs: Rhs) -> Self::Output {
+        self >> rhs.into().discard().unwrap_or_else(|e| panic!("{e}"))
+    }
+}
 
+impl<I: Step> Deterministic<I> {
+    /// Accept exactly the same input, but throw away whatever this parser would compute,
+    /// so it passes along its input unchanged.
+    /// # Errors
+    /// If any transition opens or closes a region, since we can't throw away what a region would combine.
+    #[inline]
+    pub fn discard(mut self) -> Result<Self, IllFormed<I, usize>> {
+        self.reject_regions()?;
+        for state in &mut self.states {
+            for t in state.transitions.values_mut() {
+                if let Transition::Lateral { ref mut update, .. } = *t {
+                    *update = None;
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    /// Concatenation before determinization:
+    /// states from `self` keep their indices, and states from `other` follow them.
+    #[inline]
+    #[allow(clippy::panic)]
+    fn concat_nondeterministic(mut self, other: Self) -> Nondeterministic<I> {
+        let lhs_accepts_empty = self.accepts_empty();
         let accepting_indices =
             self.states
                 .iter_mut()
                 .enumerate()
                 .fold(BTreeSet::new(), |mut acc_i, (i, st)| {
                     if st.non_accepting.is_empty() {
-                        st.non_accepting = iter::once(
-                            "Ran the first part of a two-parser concatenation \
-                            (with `>>`) but not the second one."
-                                .to_owned(),
-                        )
-                        .collect(); // <-- No longer accepting since we need to run the second parser
+                        st.non_accepting = iter::once(Rejection::UnfinishedConcatenation).collect(); // <-- No longer accepting since we need to run the second parser
                         let _ = acc_i.insert(i);
                     }
                     acc_i
                 });
 
         let mut s = self.generalize();
-        if s.check().is_err() {
+        if s.check_allowing_duplicates().is_err() {
             panic!("Internal error")
         }
         let size = s.states.len();
@@ -99,63 +237,236 @@ impl<I: Input> ops::Shr<Self> for Deterministic<I> {
 
         s.states.extend(other_states);
 
-        // For every transition that an empty stack can take from the initial state of the right-hand parser,
-        // add that transition (only on the empty stack) to each accepting state of the left-hand parser.
-        for state in &mut s.states {
-            state.transitions = mem::replace(
-                &mut state.transitions,
-                Curry::Wildcard(Transition::Return { region: "" }),
-            )
-            .merge(rhs_init.clone())
-            .unwrap_or_else(|e| panic!("{e}"));
-        }
+        // Any transition into an accepting state of the left-hand parser
+        // can also start the right-hand parser (see `add_tail_call_state` below).
+        // We never copy the right-hand initial state anywhere else, so no epsilon hubs pile up:
+        // long chains stay linear, and `(a >> b) >> c` matches `a >> (b >> c)` after sorting.
 
-        // If any initial states are immediately accepting, we need to start in the second parser, too.
-        if s.initial.iter().any(|i| accepting_indices.contains(i)) {
+        // If the left-hand parser accepts the empty input, we need to start in the second parser, too.
+        if lhs_accepts_empty {
             s.initial.extend(other_initial.iter().copied());
         }
 
-        let mut out = Graph {
+        Graph {
             states: s
                 .states
                 .into_iter()
                 .map(|st| add_tail_call_state(st, &other_initial, &accepting_indices))
                 .collect(),
             ..s
+        }
+    }
+
+    /// Like `self.star() >> follow`, but lazy: repeat `self` as few times as possible.
+    /// As soon as `follow` accepts, we drop (for good, without backtracking) every attempt still repeating `self`
+    /// along with any attempt at `follow` that has only just begun,
+    /// so e.g. `a` lazily repeated then `a` takes one `a`, whereas `a* >> a` takes as many as it can.
+    /// This only makes a difference when the end of the input isn't up to us, e.g. in `parse_slice`.
+    /// # Panics
+    /// If the combined parser would be ambiguous.
+    #[inline]
+    #[must_use]
+    #[allow(clippy::panic)]
+    pub fn lazy_star_then(self, follow: Self) -> Self {
+        let repeated = self.star();
+        let size = repeated.states.len();
+        let follow_initial = follow
+            .initial
+            .checked_add(size)
+            .expect("Absurdly huge number of states");
+        let out = repeated.concat_nondeterministic(follow);
+        let follow_accepting: BTreeSet<usize> = out
+            .states
+            .iter()
+            .enumerate()
+            .skip(size)
+            .filter(|&(_, state)| state.non_accepting.is_empty())
+            .map(|(i, _)| i)
+            .collect();
+        out.determinize_pruned(&|subset: &BTreeSet<usize>| {
+            if subset.is_disjoint(&follow_accepting) {
+                return subset.clone();
+            }
+            subset
+                .range(size..)
+                .copied()
+                .filter(|&i| i != follow_initial || follow_accepting.contains(&i))
+                .collect()
+        })
+        .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like `lazy_star_then`, but `self` has to match at least once.
+    /// # Panics
+    /// If the combined parser would be ambiguous.
+    #[inline]
+    #[must_use]
+    #[allow(clippy::arithmetic_side_effects)] // <-- Concatenation with `>>`
+    pub fn lazy_plus_then(self, follow: Self) -> Self {
+        self.clone() >> self.lazy_star_then(follow)
+    }
+}
+
+impl<I: Step> Deterministic<I> {
+    /// Concatenate every parser in order, exactly as folding `>>` over them would,
+    /// but shifting each parser's states only once and determinizing only once (instead of once per `>>`),
+    /// so long sequences build in roughly linear instead of quadratic time.
+    /// `None` if and only if there are no parsers to concatenate.
+    /// # Panics
+    /// If the combined parser would be ambiguous (or if there are absurdly many states in total).
+    #[inline]
+    #[must_use]
+    #[allow(clippy::panic, clippy::unwrap_in_result)]
+    pub fn concat_many(parsers: Vec<Self>) -> Option<Self> {
+        let last = parsers.len().checked_sub(1)?;
+        if last == 0 {
+            return parsers.into_iter().next();
+        }
+        let mut offsets = Vec::with_capacity(parsers.len());
+        let mut size = 0_usize;
+        for parser in &parsers {
+            offsets.push(size);
+            size = size
+                .checked_add(parser.states.len())
+                .expect("Absurdly huge number of states");
+        }
+        // Where to go as soon as each parser accepts:
+        // the next parser's initial state, and the one after that if the next accepts empty input, and so on.
+        let mut next_initials = vec![BTreeSet::new(); parsers.len()];
+        for i in (0..last).rev() {
+            let next = i.saturating_add(1);
+            let mut initials = get!(next_initials, next).clone();
+            if !get!(parsers, next).accepts_empty() {
+                initials.clear();
+            }
+            let _ = initials.insert(unwrap!(
+                get!(offsets, next).checked_add(get!(parsers, next).initial)
+            ));
+            *unwrap!(next_initials.get_mut(i)) = initials;
+        }
+        let mut initial: BTreeSet<usize> = iter::once(get!(parsers, 0).initial).collect();
+        if get!(parsers, 0).accepts_empty() {
+            initial.extend(get!(next_initials, 0).iter().copied());
+        }
+        let mut states = Vec::with_capacity(size);
+        for (i, ((parser, &offset), tail_calls)) in parsers
+            .into_iter()
+            .zip(&offsets)
+            .zip(&next_initials)
+            .enumerate()
+        {
+            let shifted = parser.map_indices(|j| unwrap!(j.checked_add(offset)));
+            let accepting: BTreeSet<usize> = shifted
+                .states
+                .iter()
+                .enumerate()
+                .filter(|&(_, state)| state.non_accepting.is_empty())
+                .map(|(j, _)| unwrap!(j.checked_add(offset)))
+                .collect();
+            for mut state in shifted.states {
+                if i != last && state.non_accepting.is_empty() {
+                    state.non_accepting = iter::once(Rejection::UnfinishedConcatenation).collect();
+                    // <-- No longer accepting since we need to run the next parser
+                }
+                states.push(add_tail_call_state(state, tail_calls, &accepting));
+            }
+        }
+        let mut out = Graph { states, initial };
+        out.sort();
+        Some(out.determinize().unwrap_or_else(|e| panic!("{e}")))
+    }
+
+    /// Accept exactly what this parser accepts, or the empty input (passing its input along unchanged).
+    /// # Panics
+    /// If the combined parser would be ambiguous.
+    #[inline]
+    #[must_use]
+    pub fn optional(self) -> Self {
+        self | Self::only_empty()
+    }
+
+    /// Accept exactly `n` copies of this parser's language in a row, threading the output through each,
+    /// as `self >> self >> ...` would (or only the empty input, passing its input along unchanged, if `n` is zero).
+    /// # Panics
+    /// If the combined parser would be ambiguous.
+    #[inline]
+    #[must_use]
+    pub fn repeat(self, n: usize) -> Self {
+        Self::concat_many(vec![self; n]).unwrap_or_else(Self::only_empty)
+    }
+
+    /// Accept only the empty input, passing it along unchanged.
+    #[inline]
+    fn only_empty() -> Self {
+        Graph {
+            states: vec![State {
+                transitions: Curry::Scrutinize {
+                    filter: RangeMap(BTreeMap::new()),
+                    fallback: None,
+                },
+                non_accepting: BTreeSet::new(),
+            }],
+            initial: 0,
+        }
+    }
+
+    /// Accept anything any of these parsers accepts, as folding `|` over them would,
+    /// but shifting each parser's states only once and determinizing only once (instead of once per `|`).
+    /// `None` if there are no parsers to choose from.
+    /// # Panics
+    /// If the combined parser would be ambiguous.
+    #[inline]
+    #[must_use]
+    #[allow(clippy::panic)]
+    pub fn union_many(parsers: Vec<Self>) -> Option<Self> {
+        if parsers.len() <= 1 {
+            return parsers.into_iter().next();
+        }
+        let size = parsers.iter().fold(0_usize, |acc, parser| {
+            acc.checked_add(parser.states.len())
+                .expect("Absurdly huge number of states")
+        });
+        let mut out = Graph {
+            states: Vec::with_capacity(size),
+            initial: BTreeSet::new(),
         };
+        for parser in parsers {
+            let offset = out.states.len();
+            let shifted = parser
+                .generalize()
+                .map_indices(|i| unwrap!(i.checked_add(offset)));
+            out.states.extend(shifted.states);
+            out.initial.extend(shifted.initial);
+        }
         out.sort();
-        out.determinize().unwrap_or_else(|e| panic!("{e}"))
+        Some(out.determinize().unwrap_or_else(|e| panic!("{e}")))
     }
 }
 
-impl<I: Input> ops::BitXor<(&'static str, Self, FF)> for Deterministic<I> {
+impl<I: Step> ops::BitXor<(&'static str, Self, FF)> for Deterministic<I> {
     type Output = Self;
     #[inline]
     fn bitxor(mut self, (region, other, combine): (&'static str, Self, FF)) -> Self::Output {
-        let rhs_init = get!(other.states, other.initial)
-            .transitions
-            .clone()
-            .generalize();
-
+        let lhs_accepts_empty = self.accepts_empty();
+        let initial = self.initial;
         let accepting_indices =
             self.states
                 .iter_mut()
                 .enumerate()
                 .fold(BTreeSet::new(), |mut acc_i, (i, st)| {
                     if st.non_accepting.is_empty() {
-                        st.non_accepting = iter::once(
-                            "Ran the first part of a two-parser call \
-                            (with `^`) but not the second one."
-                                .to_owned(),
-                        )
-                        .collect(); // <-- No longer accepting since we need to run the second parser
+                        // Every transition into this state will call the second parser first (see `add_call_state` below),
+                        // so it can keep accepting, except where we start (before we've called anything).
+                        if i == initial {
+                            st.non_accepting = iter::once(Rejection::UnfinishedCall).collect();
+                        }
                         let _ = acc_i.insert(i);
                     }
                     acc_i
                 });
 
         let mut s = self.generalize();
-        if s.check().is_err() {
+        if s.check_fragment().is_err() {
             panic!("Internal error")
         }
         let size = s.states.len();
@@ -169,19 +480,12 @@ impl<I: Input> ops::BitXor<(&'static str, Self, FF)> for Deterministic<I> {
 
         s.states.extend(other_states);
 
-        // For every transition that an empty stack can take from the initial state of the right-hand parser,
-        // add that transition (only on the empty stack) to each accepting state of the left-hand parser.
-        for state in &mut s.states {
-            state.transitions = mem::replace(
-                &mut state.transitions,
-                Curry::Wildcard(Transition::Return { region: "" }),
-            )
-            .merge(rhs_init.clone())
-            .unwrap_or_else(|e| panic!("{e}"));
-        }
+        // Any transition into an accepting state of the left-hand parser
+        // can also call the right-hand parser (see `add_call_state` below),
+        // so we never copy the right-hand initial state anywhere else.
 
-        // If any initial states are immediately accepting, we need to start in the second parser, too.
-        if s.initial.iter().any(|i| accepting_indices.contains(i)) {
+        // If the left-hand parser accepts the empty input, we need to start in the second parser, too.
+        if lhs_accepts_empty {
             s.initial.extend(other_initial.iter().copied());
         }
 
@@ -0,0 +1,111 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Echo a string parser's input without copying it unless some transition actually changes it.
+
+use crate::{walk::Visit, Deterministic, IllFormed, ParseError, Update, FF};
+use core::mem;
+use std::borrow::Cow;
+
+/// Echo the text read so far, borrowing it from the input for as long as nothing changes it.
+struct Echo<'input> {
+    /// The entire input.
+    input: &'input str,
+    /// Byte offset into `input` of the character we're reading.
+    offset: usize,
+    /// Text read so far.
+    output: Cow<'input, str>,
+}
+
+impl<'graph> Visit<'graph, char, usize> for Echo<'_> {
+    type Payload = usize;
+    type Saved = ();
+
+    #[inline]
+    fn read(&mut self, payload: usize) {
+        self.offset = payload;
+    }
+
+    #[inline]
+    fn lateral(
+        &mut self,
+        update: Option<&'graph Update<char>>,
+        &c: &char,
+        _: usize,
+        _: usize,
+    ) -> Result<(), ParseError<char, usize>> {
+        self.output = match (mem::take(&mut self.output), update) {
+            (Cow::Borrowed(_), None) => Cow::Borrowed(get!(
+                self.input,
+                ..unwrap!(self.offset.checked_add(c.len_utf8()))
+            )),
+            (Cow::Owned(mut s), None) => {
+                s.push(c);
+                Cow::Owned(s)
+            }
+            (so_far, Some(u)) => {
+                u.invoke::<usize>("String")
+                    .and_then(|t| {
+                        (t == "String")
+                            .then_some(())
+                            .ok_or(IllFormed::WrongReturnType("String".to_owned(), t))
+                    })
+                    .map_err(ParseError::BadParser)?;
+                let f = u.ptr.0.as_ref().ok_or_else(|| {
+                    ParseError::BadParser(IllFormed::Uncallable(u.src.to_string()))
+                })?;
+                let s = f(Box::new(so_far.into_owned()), c)
+                    .and_then(|out| out.downcast::<String>().ok())
+                    .ok_or_else(|| {
+                        ParseError::BadParser(IllFormed::Uncallable(u.src.to_string()))
+                    })?;
+                Cow::Owned(*s)
+            }
+        };
+        Ok(())
+    }
+
+    // Regions are rejected before we start, so neither of these ever happens.
+
+    #[inline]
+    fn open(&mut self) {}
+
+    #[inline]
+    fn close(
+        &mut self,
+        _: &'graph FF,
+        (): (),
+        _: usize,
+        _: usize,
+    ) -> Result<(), ParseError<char, usize>> {
+        Ok(())
+    }
+}
+
+impl Deterministic<char> {
+    /// Run this parser on a string and return the text it read, borrowed straight out of `input`
+    /// unless some transition had to change it (e.g. to unescape `\n`).
+    /// Here, transitions without an update echo their character, and transitions with an update
+    /// (which must take and return a `String`) get the text so far and their character instead,
+    /// at which point the output stops borrowing and becomes owned.
+    /// # Errors
+    /// If the parser rejects this input, if it opens or closes a region,
+    /// or if one of its updates doesn't take and return a `String`.
+    #[inline]
+    pub fn accept_cow<'input>(
+        &self,
+        input: &'input str,
+    ) -> Result<Cow<'input, str>, ParseError<char, usize>> {
+        self.reject_regions().map_err(ParseError::BadParser)?;
+        let mut echo = Echo {
+            input,
+            offset: 0,
+            output: Cow::Borrowed(""),
+        };
+        self.walk(input.char_indices().map(|(i, c)| (c, i)), &mut echo)?;
+        Ok(echo.output)
+    }
+}
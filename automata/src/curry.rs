@@ -6,8 +6,8 @@
 
 //! Read the next input symbol and decide an action.
 
-use crate::{Ctrl, IllFormed, Input, Range, RangeMap, Transition};
-use core::{cmp, iter};
+use crate::{Ctrl, IllFormed, Input, Range, RangeMap, Step, Transition};
+use core::{cmp, hash, iter, mem};
 use std::collections::BTreeMap;
 
 /// Read the next input symbol and decide an action.
@@ -17,8 +17,11 @@ pub enum Curry<I: Input, C: Ctrl<I>> {
     /// Throw away the input (without looking at it) and do this.
     Wildcard(Transition<I, C>),
     /// Map specific ranges of inputs to actions.
+    /// A token in any range of `filter` always takes that range's transition, never the fallback;
+    /// the fallback applies to exactly the tokens outside every range,
+    /// so a fallback next to ranges that cover every token is ill-formed (it could never run).
     Scrutinize {
-        /// Specific ranges to route to various transitions.
+        /// Specific ranges to route to various transitions (at most one per token).
         filter: RangeMap<I, C>,
         /// If no ranges match, take this transition (if any; otherwise, fail).
         fallback: Option<Transition<I, C>>,
@@ -64,6 +67,23 @@ impl<I: Input, C: Ctrl<I>> PartialEq for Curry<I, C> {
     }
 }
 
+impl<I: Input + hash::Hash, C: Ctrl<I> + hash::Hash> hash::Hash for Curry<I, C> {
+    #[inline]
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match *self {
+            Self::Wildcard(ref etc) => etc.hash(state),
+            Self::Scrutinize {
+                ref filter,
+                ref fallback,
+            } => {
+                filter.hash(state);
+                fallback.hash(state);
+            }
+        }
+    }
+}
+
 impl<I: Input, C: Ctrl<I>> Ord for Curry<I, C> {
     #[inline]
     fn cmp(&self, other: &Self) -> cmp::Ordering {
@@ -93,7 +113,8 @@ impl<I: Input, C: Ctrl<I>> PartialOrd for Curry<I, C> {
 }
 
 impl<I: Input, C: Ctrl<I>> Curry<I, C> {
-    /// Look up a transition based on an input token.
+    /// Look up a transition based on an input token:
+    /// a matching explicit range if there is one, otherwise the fallback (if any).
     /// # Errors
     /// If multiple ranges fit an argument.
     #[inline]
@@ -236,6 +257,24 @@ impl<I: Input, C: Ctrl<I>> Curry<I, C> {
     }
 }
 
+impl<I: Step, C: Ctrl<I>> Curry<I, C> {
+    /// Forget the fallback if the explicit ranges already cover every possible token, since it could never run.
+    #[inline]
+    #[must_use]
+    pub(crate) fn prune_fallback(self) -> Self {
+        match self {
+            Self::Scrutinize {
+                filter,
+                fallback: Some(_),
+            } if filter.covers_everything() => Self::Scrutinize {
+                filter,
+                fallback: None,
+            },
+            other @ (Self::Wildcard(..) | Self::Scrutinize { .. }) => other,
+        }
+    }
+}
+
 impl<I: Input> Curry<I, usize> {
     /// Convert the control parameter from `usize` to anything else.
     #[inline]
@@ -0,0 +1,423 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Describe the language a parser accepts in an EBNF-like notation, e.g. to document a format for people who don't read Rust.
+
+use crate::{to_src::range_pattern, Ctrl, Curry, Graph, Input, Range, Transition};
+use core::iter;
+use std::collections::BTreeMap;
+
+/// Regular expression over tokens, built up as we eliminate states one by one.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Expr<I: Input> {
+    /// Only the empty sequence.
+    Empty,
+    /// Any single token in this range.
+    Token(Range<I>),
+    /// Any single token at all.
+    Anything,
+    /// Any single token that no other transition out of the same state takes.
+    Otherwise,
+    /// Whatever the rule with this name accepts.
+    Rule(String),
+    /// Each in order.
+    Sequence(Vec<Expr<I>>),
+    /// Any one of these.
+    Alternation(Vec<Expr<I>>),
+    /// Any number of repetitions, including zero.
+    Star(Box<Expr<I>>),
+}
+
+impl<I: Input> Expr<I> {
+    /// This, then that.
+    #[inline]
+    fn then(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Empty, e) | (e, Self::Empty) => e,
+            (Self::Sequence(mut lhs), Self::Sequence(rhs)) => {
+                lhs.extend(rhs);
+                Self::Sequence(lhs)
+            }
+            (Self::Sequence(mut lhs), e) => {
+                lhs.push(e);
+                Self::Sequence(lhs)
+            }
+            (e, Self::Sequence(rhs)) => Self::Sequence(iter::once(e).chain(rhs).collect()),
+            (lhs, rhs) => Self::Sequence(vec![lhs, rhs]),
+        }
+    }
+
+    /// Either this or that.
+    #[inline]
+    fn or(self, other: Self) -> Self {
+        let mut v = if let Self::Alternation(v) = self {
+            v
+        } else {
+            vec![self]
+        };
+        for e in if let Self::Alternation(w) = other {
+            w
+        } else {
+            vec![other]
+        } {
+            if !v.contains(&e) {
+                v.push(e);
+            }
+        }
+        // A repetition already accepts the empty sequence.
+        if v.iter().any(|e| matches!(*e, Self::Star(..))) {
+            v.retain(|e| *e != Self::Empty);
+        }
+        if v.len() == 1 {
+            unwrap!(v.pop())
+        } else {
+            Self::Alternation(v)
+        }
+    }
+
+    /// Any number of repetitions of this, including zero.
+    #[inline]
+    fn star(self) -> Self {
+        match self {
+            Self::Empty => Self::Empty,
+            star @ Self::Star(..) => star,
+            Self::Alternation(v) if v.contains(&Self::Empty) => v
+                .into_iter()
+                .filter(|e| *e != Self::Empty)
+                .fold(None, |acc: Option<Self>, e| {
+                    Some(match acc {
+                        None => e,
+                        Some(a) => a.or(e),
+                    })
+                })
+                .map_or(Self::Empty, Self::star),
+            e @ (Self::Token(..)
+            | Self::Anything
+            | Self::Otherwise
+            | Self::Rule(..)
+            | Self::Sequence(..)
+            | Self::Alternation(..)) => Self::Star(Box::new(e)),
+        }
+    }
+
+    /// Render this expression so it could stand on its own between `=` and `;`.
+    #[inline]
+    fn render(&self) -> String {
+        match *self {
+            Self::Empty => "\"\"".to_owned(),
+            Self::Token(ref range) => range_pattern(range),
+            Self::Anything => "? any token ?".to_owned(),
+            Self::Otherwise => "? any other token ?".to_owned(),
+            Self::Rule(ref name) => name.clone(),
+            Self::Sequence(ref v) => render_sequence(v).join(" "),
+            Self::Alternation(ref v) => {
+                if v.contains(&Self::Empty) {
+                    let rest: Vec<_> = v.iter().filter(|e| **e != Self::Empty).cloned().collect();
+                    let inner = match rest.len() {
+                        1 => get!(rest, 0).atom(),
+                        _ => format!("({})", Self::Alternation(rest).render()),
+                    };
+                    format!("{inner}?")
+                } else {
+                    v.iter().map(Self::render).collect::<Vec<_>>().join(" | ")
+                }
+            }
+            Self::Star(ref e) => format!("{}*", e.atom()),
+        }
+    }
+
+    /// Render this expression so a postfix operator or juxtaposition applies to all of it.
+    #[inline]
+    fn atom(&self) -> String {
+        match *self {
+            Self::Sequence(ref v) => match render_sequence(v).as_slice() {
+                &[ref single] => single.clone(),
+                pieces => format!("({})", pieces.join(" ")),
+            },
+            Self::Alternation(ref v) if !v.contains(&Self::Empty) => {
+                format!("({})", self.render())
+            }
+            Self::Empty
+            | Self::Token(..)
+            | Self::Anything
+            | Self::Otherwise
+            | Self::Rule(..)
+            | Self::Alternation(..)
+            | Self::Star(..) => self.render(),
+        }
+    }
+}
+
+/// Render each item in a sequence, writing an item followed by any number of itself as one or more (e.g. `x+` instead of `x x*`)
+/// and runs of quoted characters as one string (e.g. `"yes"` instead of `'y' 'e' 's'`).
+#[inline]
+fn render_sequence<I: Input>(v: &[Expr<I>]) -> Vec<String> {
+    let mut items = vec![];
+    let mut iter = v.iter().peekable();
+    while let Some(e) = iter.next() {
+        let repeats = iter
+            .next_if(|next| matches!(**next, Expr::Star(ref s) if **s == *e))
+            .is_some();
+        items.push((e, repeats));
+    }
+    let mut pieces = vec![];
+    // Each quoted character in the current run: its prefix (e.g. `b` for bytes), how it looks inside a string, and how it looks alone.
+    let mut run: Vec<(&'static str, String, String)> = vec![];
+    for (e, repeats) in items {
+        let q = match *e {
+            Expr::Token(ref range) if !repeats && range.first == range.last => quoted(&range.first),
+            Expr::Empty
+            | Expr::Token(..)
+            | Expr::Anything
+            | Expr::Otherwise
+            | Expr::Rule(..)
+            | Expr::Sequence(..)
+            | Expr::Alternation(..)
+            | Expr::Star(..) => None,
+        };
+        if let Some((prefix, c)) = q {
+            if run.first().is_some_and(|&(p, _, _)| p != prefix) {
+                pieces.extend(end_run(&mut run));
+            }
+            run.push((prefix, c, e.render()));
+        } else {
+            pieces.extend(end_run(&mut run));
+            pieces.push(if repeats {
+                format!("{}+", e.atom())
+            } else {
+                e.atom()
+            });
+        }
+    }
+    pieces.extend(end_run(&mut run));
+    pieces
+}
+
+/// Render a run of quoted characters as one string (or as itself, if it's only one character), then clear it.
+#[inline]
+fn end_run(run: &mut Vec<(&'static str, String, String)>) -> Option<String> {
+    let rendered = match **run {
+        [] => None,
+        [(_, _, ref alone)] => Some(alone.clone()),
+        [(prefix, _, _), ..] => Some(format!(
+            "{prefix}\"{}\"",
+            run.iter()
+                .map(|&(_, ref c, _)| c.as_str())
+                .collect::<String>(),
+        )),
+    };
+    run.clear();
+    rendered
+}
+
+/// If a token renders as a quoted character (e.g. `'a'` or `b'a'`),
+/// its prefix (e.g. `b`) and how it would look inside a double-quoted string.
+#[inline]
+fn quoted<I: Input>(token: &I) -> Option<(&'static str, String)> {
+    let src = token.to_src();
+    let (prefix, rest) = src
+        .strip_prefix('b')
+        .map_or(("", src.as_str()), |rest| ("b", rest));
+    let inner = rest.strip_prefix('\'')?.strip_suffix('\'')?;
+    Some((
+        prefix,
+        match inner {
+            "\\'" => "'".to_owned(),
+            "\"" => "\\\"".to_owned(),
+            other => other.to_owned(),
+        },
+    ))
+}
+
+/// Turns a parser into rules, one for the parser itself and one for each region it opens.
+struct Describer<'graph, I: Input, C: Ctrl<I>> {
+    /// The parser we're describing.
+    graph: &'graph Graph<I, C>,
+    /// Name of the rule for each region and the states it starts in.
+    names: BTreeMap<(&'static str, &'graph C), String>,
+    /// Regions we've named but haven't described yet.
+    queue: Vec<(&'static str, &'graph C)>,
+}
+
+impl<'graph, I: Input, C: Ctrl<I>> Describer<'graph, I, C> {
+    /// Name of the rule for a region starting in these states, queueing it to be described if it's new.
+    #[inline]
+    fn name(&mut self, region: &'static str, detour: &'graph C) -> String {
+        if let Some(name) = self.names.get(&(region, detour)) {
+            return name.clone();
+        }
+        let mut name = region.to_owned();
+        let mut n: usize = 1;
+        while name == "start" || self.names.values().any(|v| *v == name) {
+            n = n.saturating_add(1);
+            name = format!("{region}_{n}");
+        }
+        drop(self.names.insert((region, detour), name.clone()));
+        self.queue.push((region, detour));
+        name
+    }
+
+    /// Describe everything accepted from these states: to the end of input if `in_region` is false,
+    /// or up to and including the token that closes the region if it's true.
+    #[inline]
+    fn rule(&mut self, initial: &'graph C, in_region: bool) -> Option<Expr<I>> {
+        let n = self.graph.states.len();
+        let (start, end) = (n, n.saturating_add(1));
+        let mut edges = BTreeMap::new();
+        for i in initial.view() {
+            add(&mut edges, start, i, Expr::Empty);
+        }
+        let reachable = self.graph.reachable_from(initial.view());
+        for &i in &reachable {
+            let state = get!(self.graph.states, i);
+            if !in_region && state.non_accepting.is_empty() {
+                add(&mut edges, i, end, Expr::Empty);
+            }
+            match state.transitions {
+                Curry::Wildcard(ref t) => {
+                    self.edge(&mut edges, i, Expr::Anything, t, in_region, end);
+                }
+                Curry::Scrutinize {
+                    ref filter,
+                    ref fallback,
+                } => {
+                    for (range, t) in &filter.0 {
+                        self.edge(&mut edges, i, Expr::Token(range.clone()), t, in_region, end);
+                    }
+                    if let Some(ref t) = *fallback {
+                        self.edge(&mut edges, i, Expr::Otherwise, t, in_region, end);
+                    }
+                }
+            }
+        }
+        // Eliminate states one at a time, starting with whichever adds the fewest new edges.
+        let mut remaining: Vec<usize> = reachable.into_iter().collect();
+        while !remaining.is_empty() {
+            let degree = |q: usize| {
+                let ins = edges.keys().filter(|&&(p, r)| r == q && p != q).count();
+                let outs = edges.keys().filter(|&&(p, r)| p == q && r != q).count();
+                ins.saturating_mul(outs)
+            };
+            let index = unwrap!(remaining
+                .iter()
+                .enumerate()
+                .min_by_key(|&(_, &q)| degree(q))
+                .map(|(index, _)| index));
+            let q = remaining.swap_remove(index);
+            let repeat = edges.remove(&(q, q)).map_or(Expr::Empty, Expr::star);
+            let ins: Vec<_> = edges
+                .iter()
+                .filter(|&(&(_, r), _)| r == q)
+                .map(|(&(p, _), e)| (p, e.clone()))
+                .collect();
+            let outs: Vec<_> = edges
+                .iter()
+                .filter(|&(&(p, _), _)| p == q)
+                .map(|(&(_, r), e)| (r, e.clone()))
+                .collect();
+            edges.retain(|&(p, r), _| p != q && r != q);
+            for &(p, ref into) in &ins {
+                for &(r, ref out) in &outs {
+                    add(
+                        &mut edges,
+                        p,
+                        r,
+                        into.clone().then(repeat.clone()).then(out.clone()),
+                    );
+                }
+            }
+        }
+        edges.remove(&(start, end))
+    }
+
+    /// Add an edge for a transition out of state `src` on the tokens `label` describes.
+    #[inline]
+    fn edge(
+        &mut self,
+        edges: &mut BTreeMap<(usize, usize), Expr<I>>,
+        src: usize,
+        label: Expr<I>,
+        transition: &'graph Transition<I, C>,
+        in_region: bool,
+        end: usize,
+    ) {
+        match *transition {
+            Transition::Lateral { ref dst, .. } => {
+                for i in dst.view() {
+                    add(edges, src, i, label.clone());
+                }
+            }
+            Transition::Call {
+                region,
+                ref detour,
+                ref dst,
+                ..
+            } => {
+                let name = self.name(region, detour);
+                self.edge(
+                    edges,
+                    src,
+                    label.then(Expr::Rule(name)),
+                    dst,
+                    in_region,
+                    end,
+                );
+            }
+            Transition::Return { .. } => {
+                if in_region {
+                    add(edges, src, end, label);
+                }
+            }
+        }
+    }
+}
+
+/// Add an alternative to the edge between two states.
+#[inline]
+fn add<I: Input>(
+    edges: &mut BTreeMap<(usize, usize), Expr<I>>,
+    src: usize,
+    dst: usize,
+    e: Expr<I>,
+) {
+    let merged = match edges.remove(&(src, dst)) {
+        None => e,
+        Some(existing) => existing.or(e),
+    };
+    drop(edges.insert((src, dst), merged));
+}
+
+impl<I: Input, C: Ctrl<I>> Graph<I, C> {
+    /// Describe the language this parser accepts in an EBNF-like notation
+    /// (the one `from_ebnf` reads, plus ranges like `'0'..='9'` and `? any token ?`),
+    /// with one rule for the parser itself (`start`) and one for each region it can open.
+    /// Loops become `*`, alternatives `|`, and chains concatenation.
+    /// Best-effort: the language is exact, but rarely written as briefly as a person would.
+    #[inline]
+    #[must_use]
+    pub fn describe(&self) -> String {
+        let mut describer = Describer {
+            graph: self,
+            names: BTreeMap::new(),
+            queue: vec![],
+        };
+        let mut out = String::new();
+        let mut push = |name: &str, rule: Option<Expr<I>>| {
+            out.push_str(name);
+            out.push_str(" = ");
+            out.push_str(&rule.map_or_else(|| "? nothing ?".to_owned(), |e| e.render()));
+            out.push_str(" ;\n");
+        };
+        push("start", describer.rule(&self.initial, false));
+        let mut described = 0_usize;
+        while let Some(&(region, detour)) = describer.queue.get(described) {
+            described = described.saturating_add(1);
+            let rule = describer.rule(detour, true);
+            push(&describer.name(region, detour), rule);
+        }
+        out
+    }
+}
@@ -0,0 +1,929 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Import and export a parser as a small data file (a subset of TOML), for anyone who'd rather not write Rust.
+//!
+//! ```text
+//! initial = "start"
+//!
+//! [[state]]
+//! name = "start"
+//! errors = ["Expected a digit or `(`"]
+//! transitions = [
+//!     { on = "0-9", to = "done", update = "digit" },
+//!     { on = "(", call = "parentheses", detour = "start", to = "done", combine = "nested" },
+//! ]
+//!
+//! [[state]]
+//! name = "done"
+//! accepting = true
+//! transitions = [{ on = ")", return = "parentheses" }]
+//! ```
+//!
+//! Each `[[state]]` has a unique `name` and either says `accepting = true` or lists the `errors` to report if input ends there.
+//! Each transition matches a single character (`on = "x"`) or an inclusive range (`on = "0-9"`);
+//! a transition without `on` takes any token the rest of its state doesn't.
+//! A transition either goes `to` a state, opens a region with `call` (running `detour` until the region closes, then going `to` a state),
+//! or closes a region with `return`. `update` and `combine` name functions from lists passed alongside the description.
+
+use crate::{
+    Curry, Deterministic, Graph, IllFormed, Range, RangeMap, Rejection, State, Transition, Update,
+    FF,
+};
+use core::{fmt, iter};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Line and column in a data file, both counting from 1.
+#[allow(clippy::exhaustive_structs)]
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Position {
+    /// Line, counting from 1.
+    pub line: usize,
+    /// Column (in characters, not bytes), counting from 1.
+    pub column: usize,
+}
+
+impl fmt::Display for Position {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// Why a description couldn't be imported or exported.
+#[non_exhaustive]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DescriptionError {
+    /// Unexpected character (or end of input).
+    Syntax {
+        /// Where we gave up.
+        at: Position,
+        /// What we would have accepted instead.
+        expected: &'static str,
+    },
+    /// The same key twice in one table.
+    DuplicateKey {
+        /// Where the second one starts.
+        at: Position,
+        /// The key itself.
+        key: String,
+    },
+    /// A key that means nothing here.
+    UnknownKey {
+        /// Where the key starts.
+        at: Position,
+        /// The key itself.
+        key: String,
+    },
+    /// A table without a key it needs.
+    MissingKey {
+        /// Where the table starts.
+        at: Position,
+        /// The key we needed.
+        key: &'static str,
+    },
+    /// A value of the wrong kind (e.g. a string where we need `true` or `false`).
+    WrongType {
+        /// Where the value starts.
+        at: Position,
+        /// What we needed instead.
+        expected: &'static str,
+    },
+    /// Keys that can't go together (e.g. `to` and `return` in the same transition).
+    Conflict {
+        /// Where the offending table starts.
+        at: Position,
+        /// What went wrong, in English.
+        problem: &'static str,
+    },
+    /// Two states with the same name.
+    DuplicateState {
+        /// Where the second one's name starts.
+        at: Position,
+        /// The name itself.
+        name: String,
+    },
+    /// Reference to a state that was never defined.
+    UndefinedState {
+        /// Where the reference starts.
+        at: Position,
+        /// The name itself.
+        name: String,
+    },
+    /// Reference to a function that isn't in the list passed alongside the description.
+    UndefinedFunction {
+        /// Where the reference starts.
+        at: Position,
+        /// The name itself.
+        name: String,
+    },
+    /// Neither a single character nor two characters separated by `-` (in order).
+    BadRange {
+        /// Where the range starts.
+        at: Position,
+        /// The range as written.
+        range: String,
+    },
+    /// Two transitions in the same state on overlapping ranges.
+    Overlap {
+        /// Where the second range starts.
+        at: Position,
+        /// Characters both ranges accept.
+        overlap: Range<char>,
+    },
+    /// Every name resolved, but the parser itself is ill-formed.
+    IllFormed(IllFormed<char, usize>),
+    /// Exporting a function that isn't in the list passed alongside the parser (with its source code).
+    Unnamed(String),
+    /// Exporting a parser that a description can't express.
+    Unrepresentable(&'static str),
+}
+
+impl fmt::Display for DescriptionError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Syntax { at, expected } => write!(f, "Syntax error at {at}: expected {expected}"),
+            Self::DuplicateKey { at, ref key } => write!(f, "Key `{key}` repeated at {at}"),
+            Self::UnknownKey { at, ref key } => write!(f, "Unknown key `{key}` at {at}"),
+            Self::MissingKey { at, key } => {
+                write!(f, "Table starting at {at} needs a key named `{key}`")
+            }
+            Self::WrongType { at, expected } => write!(f, "Expected {expected} at {at}"),
+            Self::Conflict { at, problem } => write!(f, "In the table starting at {at}: {problem}"),
+            Self::DuplicateState { at, ref name } => {
+                write!(f, "State `{name}` defined more than once (again at {at})")
+            }
+            Self::UndefinedState { at, ref name } => {
+                write!(f, "Reference to undefined state `{name}` at {at}")
+            }
+            Self::UndefinedFunction { at, ref name } => write!(
+                f,
+                "Reference to a function named `{name}` at {at}, \
+                but no function with that name was passed alongside the description",
+            ),
+            Self::BadRange { at, ref range } => write!(
+                f,
+                "Expected a single character or a range like `0-9` at {at}, but got \"{range}\"",
+            ),
+            Self::Overlap { at, ref overlap } => write!(
+                f,
+                "Range at {at} overlaps another transition in the same state on {}..={}",
+                overlap.first, overlap.last,
+            ),
+            Self::IllFormed(ref e) => write!(f, "{e}"),
+            Self::Unnamed(ref src) => write!(
+                f,
+                "No name for `{src}`: pass it alongside the parser with a name to refer to it by",
+            ),
+            Self::Unrepresentable(problem) => write!(f, "Can't describe this parser: {problem}"),
+        }
+    }
+}
+
+impl Deterministic<char> {
+    /// Import a parser from a data file (see this module's documentation),
+    /// resolving each `update` and `combine` by name in the lists passed alongside it.
+    /// States keep the order they're listed in (so the first one listed is state 0), and the result is checked.
+    /// # Errors
+    /// If the description is malformed (with its line and column) or the parser it describes is ill-formed.
+    #[inline]
+    pub fn from_description(
+        src: &str,
+        updates: &[(&str, Update<char>)],
+        combinators: &[(&str, FF)],
+    ) -> Result<Self, DescriptionError> {
+        let mut root = Syntax {
+            chars: src.chars().collect(),
+            index: 0,
+        }
+        .document()?;
+        let (initial_at, initial) = root.required_string("initial")?;
+        let states = root.array_of_tables("state")?;
+        root.finish()?;
+
+        let mut names = BTreeMap::new();
+        let mut tables = vec![];
+        for mut table in states {
+            let (at, name) = table.required_string("name")?;
+            if names.insert(name.clone(), names.len()).is_some() {
+                return Err(DescriptionError::DuplicateState { at, name });
+            }
+            tables.push(table);
+        }
+        let mut compiler = Compiler {
+            names: &names,
+            updates,
+            combinators,
+            regions: BTreeMap::new(),
+        };
+        let graph = Graph {
+            states: tables
+                .into_iter()
+                .map(|table| compiler.state(table))
+                .collect::<Result<_, _>>()?,
+            initial: compiler.state_index(initial_at, &initial)?,
+        };
+        graph
+            .check()
+            .map(|()| graph)
+            .map_err(DescriptionError::IllFormed)
+    }
+
+    /// Export this parser as a data file (see this module's documentation) that `from_description` reads back,
+    /// naming states with `breadcrumb_names` and functions by the lists passed alongside it.
+    /// # Errors
+    /// If a function isn't in those lists or a region's continuation does anything but go to a state.
+    #[inline]
+    pub fn to_description(
+        &self,
+        updates: &[(&str, Update<char>)],
+        combinators: &[(&str, FF)],
+    ) -> Result<String, DescriptionError> {
+        let names = self.breadcrumb_names();
+        let mut out = format!("initial = {}\n", quote(get!(names, self.initial)));
+        for (state, name) in self.states.iter().zip(&names) {
+            out.push_str("\n[[state]]\nname = ");
+            out.push_str(&quote(name));
+            out.push('\n');
+            if state.non_accepting.is_empty() {
+                out.push_str("accepting = true\n");
+            } else {
+                out.push_str("errors = [");
+                out.push_str(
+                    &state
+                        .non_accepting
+                        .iter()
+                        .map(|e| quote(&e.to_string()))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                );
+                out.push_str("]\n");
+            }
+            #[allow(clippy::type_complexity)]
+            let transitions: Vec<(Option<&Range<char>>, &Transition<char, usize>)> =
+                match state.transitions {
+                    Curry::Wildcard(ref t) => vec![(None, t)],
+                    Curry::Scrutinize {
+                        ref filter,
+                        ref fallback,
+                    } => filter
+                        .0
+                        .iter()
+                        .map(|(k, v)| (Some(k), v))
+                        .chain(fallback.iter().map(|f| (None, f)))
+                        .collect(),
+                };
+            out.push_str("transitions = [\n");
+            for (range, transition) in transitions {
+                let mut fields = vec![];
+                if let Some(r) = range {
+                    fields.push(format!(
+                        "on = {}",
+                        quote(&if r.first == r.last {
+                            r.first.to_string()
+                        } else {
+                            format!("{}-{}", r.first, r.last)
+                        }),
+                    ));
+                }
+                let lateral = |acc: &mut Vec<String>, t: &Transition<char, usize>| match *t {
+                    Transition::Lateral { dst, ref update } => {
+                        acc.push(format!("to = {}", quote(get!(names, dst))));
+                        if let Some(ref u) = *update {
+                            acc.push(format!("update = {}", quote(name_of(updates, u)?)));
+                        }
+                        Ok(())
+                    }
+                    Transition::Call { .. } | Transition::Return { .. } => {
+                        Err(DescriptionError::Unrepresentable(
+                            "after a region closes, we can only go to a state",
+                        ))
+                    }
+                };
+                match *transition {
+                    Transition::Lateral { .. } => lateral(&mut fields, transition)?,
+                    Transition::Call {
+                        region,
+                        detour,
+                        ref dst,
+                        ref combine,
+                    } => {
+                        fields.push(format!("call = {}", quote(region)));
+                        fields.push(format!("detour = {}", quote(get!(names, detour))));
+                        lateral(&mut fields, dst)?;
+                        fields.push(format!(
+                            "combine = {}",
+                            quote(name_of(combinators, combine)?)
+                        ));
+                    }
+                    Transition::Return { region } => {
+                        fields.push(format!("return = {}", quote(region)));
+                    }
+                }
+                out.push_str("    { ");
+                out.push_str(&fields.join(", "));
+                out.push_str(" },\n");
+            }
+            out.push_str("]\n");
+        }
+        Ok(out)
+    }
+}
+
+/// The name a function was passed alongside a parser with.
+#[inline]
+fn name_of<'names, T: Eq + fmt::Display>(
+    names: &[(&'names str, T)],
+    f: &T,
+) -> Result<&'names str, DescriptionError> {
+    names
+        .iter()
+        .find(|&&(_, ref g)| g == f)
+        .map(|&(name, _)| name)
+        .ok_or_else(|| DescriptionError::Unnamed(f.to_string()))
+}
+
+/// Quote a string so `Syntax::string` reads it back exactly.
+#[inline]
+fn quote(s: &str) -> String {
+    let mut out = "\"".to_owned();
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            control if control.is_control() => out.extend(control.escape_unicode()),
+            other => out.push(other),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Any value in a data file.
+#[derive(Clone, Debug)]
+enum Value {
+    /// Quoted string.
+    String(String),
+    /// `true` or `false`.
+    Bool(bool),
+    /// Values in square brackets (each with where it starts).
+    Array(Vec<(Position, Value)>),
+    /// Keys and values in curly braces (or under a `[[header]]`).
+    Table(Table),
+}
+
+/// Keys and values, each with where it starts.
+#[derive(Clone, Debug)]
+struct Table {
+    /// Where the table itself starts.
+    at: Position,
+    /// Each key (with where it starts) and its value (with where that starts).
+    entries: Vec<((Position, String), (Position, Value))>,
+}
+
+impl Table {
+    /// Add a key, unless it's already there.
+    #[inline]
+    fn insert(
+        &mut self,
+        (at, key): (Position, String),
+        value: (Position, Value),
+    ) -> Result<(), DescriptionError> {
+        if self.entries.iter().any(|&((_, ref k), _)| *k == key) {
+            return Err(DescriptionError::DuplicateKey { at, key });
+        }
+        self.entries.push(((at, key), value));
+        Ok(())
+    }
+
+    /// Add a table to an array of tables, starting the array if it isn't there.
+    #[inline]
+    fn append(
+        &mut self,
+        (at, key): (Position, String),
+        table: Self,
+    ) -> Result<(), DescriptionError> {
+        let value = (table.at, Value::Table(table));
+        match self
+            .entries
+            .iter_mut()
+            .find(|&&mut ((_, ref k), _)| *k == key)
+        {
+            None => self.insert((at, key), (at, Value::Array(vec![value]))),
+            Some(&mut (_, (_, Value::Array(ref mut v)))) => {
+                v.push(value);
+                Ok(())
+            }
+            Some(_) => Err(DescriptionError::DuplicateKey { at, key }),
+        }
+    }
+
+    /// Remove a key and return its value, if it's there.
+    #[inline]
+    fn take(&mut self, key: &str) -> Option<(Position, Value)> {
+        let i = self.entries.iter().position(|&((_, ref k), _)| k == key)?;
+        Some(self.entries.remove(i).1)
+    }
+
+    /// Remove a key whose value has to be a string.
+    #[inline]
+    fn string(&mut self, key: &str) -> Result<Option<(Position, String)>, DescriptionError> {
+        match self.take(key) {
+            None => Ok(None),
+            Some((at, Value::String(s))) => Ok(Some((at, s))),
+            Some((at, _)) => Err(DescriptionError::WrongType {
+                at,
+                expected: "a quoted string",
+            }),
+        }
+    }
+
+    /// Remove a key that has to be there and whose value has to be a string.
+    #[inline]
+    fn required_string(
+        &mut self,
+        key: &'static str,
+    ) -> Result<(Position, String), DescriptionError> {
+        self.string(key)?
+            .ok_or(DescriptionError::MissingKey { at: self.at, key })
+    }
+
+    /// Remove a key whose value has to be `true` or `false`.
+    #[inline]
+    fn bool(&mut self, key: &str) -> Result<Option<bool>, DescriptionError> {
+        match self.take(key) {
+            None => Ok(None),
+            Some((_, Value::Bool(b))) => Ok(Some(b)),
+            Some((at, _)) => Err(DescriptionError::WrongType {
+                at,
+                expected: "`true` or `false`",
+            }),
+        }
+    }
+
+    /// Remove a key whose value has to be an array (empty if the key isn't there).
+    #[inline]
+    fn array(&mut self, key: &str) -> Result<Vec<(Position, Value)>, DescriptionError> {
+        match self.take(key) {
+            None => Ok(vec![]),
+            Some((_, Value::Array(v))) => Ok(v),
+            Some((at, _)) => Err(DescriptionError::WrongType {
+                at,
+                expected: "an array in square brackets",
+            }),
+        }
+    }
+
+    /// Remove a key whose value has to be an array of tables (empty if the key isn't there).
+    #[inline]
+    fn array_of_tables(&mut self, key: &str) -> Result<Vec<Self>, DescriptionError> {
+        self.array(key)?
+            .into_iter()
+            .map(|(at, v)| match v {
+                Value::Table(t) => Ok(t),
+                Value::String(..) | Value::Bool(..) | Value::Array(..) => {
+                    Err(DescriptionError::WrongType {
+                        at,
+                        expected: "a table in curly braces",
+                    })
+                }
+            })
+            .collect()
+    }
+
+    /// Make sure we've used every key.
+    #[inline]
+    fn finish(self) -> Result<(), DescriptionError> {
+        self.entries
+            .into_iter()
+            .next()
+            .map_or(Ok(()), |((at, key), _)| {
+                Err(DescriptionError::UnknownKey { at, key })
+            })
+    }
+}
+
+/// Recursive-descent parser over a data file.
+struct Syntax {
+    /// Source, split into characters.
+    chars: Vec<char>,
+    /// Index of the next unread character.
+    index: usize,
+}
+
+impl Syntax {
+    /// Parse top-level keys and `[[header]]` tables until the end of the input.
+    #[inline]
+    fn document(&mut self) -> Result<Table, DescriptionError> {
+        let mut root = Table {
+            at: self.position(),
+            entries: vec![],
+        };
+        // Array-of-tables key and the table under its latest `[[header]]`, if we've seen one.
+        let mut open: Option<((Position, String), Table)> = None;
+        while self.peek().is_some() {
+            let at = self.position();
+            if self.eat("[[") {
+                let key = (self.position(), self.key()?);
+                if !self.eat("]]") {
+                    return Err(self.expected("`]]`"));
+                }
+                if let Some((prev, table)) = open.replace((
+                    key,
+                    Table {
+                        at,
+                        entries: vec![],
+                    },
+                )) {
+                    root.append(prev, table)?;
+                }
+                continue;
+            }
+            let (key, value) = self.key_value()?;
+            open.as_mut()
+                .map_or(&mut root, |&mut (_, ref mut table)| table)
+                .insert(key, value)?;
+        }
+        if let Some((key, table)) = open {
+            root.append(key, table)?;
+        }
+        Ok(root)
+    }
+
+    /// Parse `key = value`.
+    #[inline]
+    #[allow(clippy::type_complexity)]
+    fn key_value(&mut self) -> Result<((Position, String), (Position, Value)), DescriptionError> {
+        let _ = self.peek();
+        let key = (self.position(), self.key()?);
+        if !self.eat("=") {
+            return Err(self.expected("`=`"));
+        }
+        let _ = self.peek();
+        let at = self.position();
+        Ok((key, (at, self.value()?)))
+    }
+
+    /// Parse a string, `true`, `false`, an array, or an inline table.
+    #[inline]
+    fn value(&mut self) -> Result<Value, DescriptionError> {
+        match self.peek() {
+            Some('"') => self.string().map(Value::String),
+            Some('[') => {
+                self.index = self.index.saturating_add(1);
+                let mut v = vec![];
+                while !self.eat("]") {
+                    let _ = self.peek();
+                    let at = self.position();
+                    v.push((at, self.value()?));
+                    if !self.eat(",") && self.peek() != Some(']') {
+                        return Err(self.expected("`,` or `]`"));
+                    }
+                }
+                Ok(Value::Array(v))
+            }
+            Some('{') => {
+                let mut table = Table {
+                    at: self.position(),
+                    entries: vec![],
+                };
+                self.index = self.index.saturating_add(1);
+                while !self.eat("}") {
+                    let (key, value) = self.key_value()?;
+                    table.insert(key, value)?;
+                    if !self.eat(",") && self.peek() != Some('}') {
+                        return Err(self.expected("`,` or `}`"));
+                    }
+                }
+                Ok(Value::Table(table))
+            }
+            _ => {
+                if self.eat("true") {
+                    Ok(Value::Bool(true))
+                } else if self.eat("false") {
+                    Ok(Value::Bool(false))
+                } else {
+                    Err(self.expected("a quoted string, `true`, `false`, `[`, or `{`"))
+                }
+            }
+        }
+    }
+
+    /// Parse a quoted string (with the usual backslash escapes).
+    #[inline]
+    fn string(&mut self) -> Result<String, DescriptionError> {
+        if !self.eat("\"") {
+            return Err(self.expected("`\"`"));
+        }
+        let mut s = String::new();
+        loop {
+            match self.next() {
+                None | Some('\n') => return Err(self.expected("a closing quote")),
+                Some('"') => return Ok(s),
+                Some('\\') => s.push(match self.next() {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('r') => '\r',
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('u') => {
+                        let hex: Option<String> = (self.next() == Some('{')).then(|| {
+                            iter::from_fn(|| self.next())
+                                .take_while(|&c| c != '}')
+                                .collect()
+                        });
+                        hex.and_then(|h| u32::from_str_radix(&h, 16).ok())
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| {
+                                self.expected("a hexadecimal code point like `\\u{7F}`")
+                            })?
+                    }
+                    _ => return Err(self.expected("`n`, `t`, `r`, `\"`, `\\`, or `u` after `\\`")),
+                }),
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    /// Parse a bare key.
+    #[inline]
+    fn key(&mut self) -> Result<String, DescriptionError> {
+        let _ = self.peek();
+        let mut s = String::new();
+        while let Some(&c) = self.chars.get(self.index) {
+            if !(c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                break;
+            }
+            s.push(c);
+            self.index = self.index.saturating_add(1);
+        }
+        if s.is_empty() {
+            Err(self.expected("a key"))
+        } else {
+            Ok(s)
+        }
+    }
+
+    /// Skip whitespace and comments, then consume this exact string if it's next.
+    #[inline]
+    fn eat(&mut self, s: &str) -> bool {
+        let _ = self.peek();
+        let n = s.chars().count();
+        let matches = self
+            .chars
+            .get(self.index..)
+            .is_some_and(|rest| rest.iter().copied().take(n).eq(s.chars()));
+        if matches {
+            self.index = self.index.saturating_add(n);
+        }
+        matches
+    }
+
+    /// Skip whitespace and comments, then look at the next character without consuming it.
+    #[inline]
+    fn peek(&mut self) -> Option<char> {
+        loop {
+            match self.chars.get(self.index) {
+                Some(&'#') => {
+                    while self.chars.get(self.index).is_some_and(|&c| c != '\n') {
+                        self.index = self.index.saturating_add(1);
+                    }
+                }
+                Some(c) if c.is_whitespace() => self.index = self.index.saturating_add(1),
+                other => return other.copied(),
+            }
+        }
+    }
+
+    /// Consume the next character, whitespace or not.
+    #[inline]
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.get(self.index).copied();
+        self.index = self.index.saturating_add(1);
+        c
+    }
+
+    /// Line and column of the next unread character.
+    #[inline]
+    fn position(&self) -> Position {
+        let before = get!(self.chars, ..self.index.min(self.chars.len()));
+        let line_start = before
+            .iter()
+            .rposition(|&c| c == '\n')
+            .map_or(0, |i| i.saturating_add(1));
+        Position {
+            line: before
+                .iter()
+                .filter(|&&c| c == '\n')
+                .count()
+                .saturating_add(1),
+            column: self.index.saturating_sub(line_start).saturating_add(1),
+        }
+    }
+
+    /// Syntax error right here.
+    #[inline]
+    fn expected(&self, expected: &'static str) -> DescriptionError {
+        DescriptionError::Syntax {
+            at: self.position(),
+            expected,
+        }
+    }
+}
+
+/// Resolve names in tables into states.
+struct Compiler<'names, 'functions> {
+    /// Index of each state by name.
+    names: &'names BTreeMap<String, usize>,
+    /// Functions to call on each token, by name.
+    updates: &'functions [(&'functions str, Update<char>)],
+    /// Functions to combine a region's output with what came before it, by name.
+    combinators: &'functions [(&'functions str, FF)],
+    /// Each region's name, allocated once (since transitions refer to regions by `&'static str`).
+    regions: BTreeMap<String, &'static str>,
+}
+
+impl Compiler<'_, '_> {
+    /// Compile a `[[state]]` table (without its `name`, which we've already read).
+    #[inline]
+    fn state(&mut self, mut table: Table) -> Result<State<char, usize>, DescriptionError> {
+        let at = table.at;
+        let accepting = table.bool("accepting")?.unwrap_or(false);
+        let errors = table
+            .array("errors")?
+            .into_iter()
+            .map(|(value_at, v)| match v {
+                Value::String(s) => Ok(s),
+                Value::Bool(..) | Value::Array(..) | Value::Table(..) => {
+                    Err(DescriptionError::WrongType {
+                        at: value_at,
+                        expected: "a quoted string",
+                    })
+                }
+            })
+            .collect::<Result<BTreeSet<_>, _>>()?;
+        let transitions = table.array_of_tables("transitions")?;
+        table.finish()?;
+        let non_accepting = match (accepting, errors.is_empty()) {
+            (true, true) => BTreeSet::new(),
+            (true, false) => {
+                return Err(DescriptionError::Conflict {
+                    at,
+                    problem: "an accepting state can't list errors",
+                })
+            }
+            (false, true) => iter::once("Unexpected end of input".into()).collect(),
+            (false, false) => errors.into_iter().map(Rejection::from).collect(),
+        };
+        let mut filter: BTreeMap<Range<char>, Transition<char, usize>> = BTreeMap::new();
+        let mut fallback = None;
+        for mut t in transitions {
+            let table_at = t.at;
+            let on = t.string("on")?;
+            let transition = self.transition(t)?;
+            match on {
+                None => {
+                    if fallback.replace(transition).is_some() {
+                        return Err(DescriptionError::Conflict {
+                            at: table_at,
+                            problem: "only one transition per state can leave out `on`",
+                        });
+                    }
+                }
+                Some((range_at, range)) => {
+                    let parsed = parse_range(&range).ok_or(DescriptionError::BadRange {
+                        at: range_at,
+                        range,
+                    })?;
+                    if let Some(overlap) = filter.keys().find_map(|&k| k.intersection(parsed)) {
+                        return Err(DescriptionError::Overlap {
+                            at: range_at,
+                            overlap,
+                        });
+                    }
+                    drop(filter.insert(parsed, transition));
+                }
+            }
+        }
+        Ok(State {
+            transitions: match (filter.is_empty(), fallback) {
+                (true, Some(etc)) => Curry::Wildcard(etc),
+                (_, etc) => Curry::Scrutinize {
+                    filter: RangeMap(filter),
+                    fallback: etc,
+                },
+            },
+            non_accepting,
+        })
+    }
+
+    /// Compile a transition table (without its `on`, which we've already read).
+    #[inline]
+    fn transition(
+        &mut self,
+        mut table: Table,
+    ) -> Result<Transition<char, usize>, DescriptionError> {
+        let at = table.at;
+        let to = table.string("to")?;
+        let update = table.string("update")?;
+        let call = table.string("call")?;
+        let detour = table.string("detour")?;
+        let combine = table.string("combine")?;
+        let ret = table.string("return")?;
+        table.finish()?;
+        if let Some((_, region)) = ret {
+            if to.is_some()
+                || update.is_some()
+                || call.is_some()
+                || detour.is_some()
+                || combine.is_some()
+            {
+                return Err(DescriptionError::Conflict {
+                    at,
+                    problem: "a transition with `return` can't have any other key but `on`",
+                });
+            }
+            return Ok(Transition::Return {
+                region: self.region(region),
+            });
+        }
+        let (to_at, to_name) = to.ok_or(DescriptionError::MissingKey { at, key: "to" })?;
+        let lateral = Transition::Lateral {
+            dst: self.state_index(to_at, &to_name)?,
+            update: update
+                .map(|(update_at, name)| lookup(self.updates, update_at, name))
+                .transpose()?,
+        };
+        let Some((_, region)) = call else {
+            if detour.is_some() || combine.is_some() {
+                return Err(DescriptionError::Conflict {
+                    at,
+                    problem: "`detour` and `combine` only make sense with `call`",
+                });
+            }
+            return Ok(lateral);
+        };
+        let (detour_at, detour_name) =
+            detour.ok_or(DescriptionError::MissingKey { at, key: "detour" })?;
+        let (combine_at, combine_name) =
+            combine.ok_or(DescriptionError::MissingKey { at, key: "combine" })?;
+        Ok(Transition::Call {
+            region: self.region(region),
+            detour: self.state_index(detour_at, &detour_name)?,
+            dst: Box::new(lateral),
+            combine: lookup(self.combinators, combine_at, combine_name)?,
+        })
+    }
+
+    /// Index of a state by name.
+    #[inline]
+    fn state_index(&self, at: Position, name: &str) -> Result<usize, DescriptionError> {
+        self.names
+            .get(name)
+            .copied()
+            .ok_or_else(|| DescriptionError::UndefinedState {
+                at,
+                name: name.to_owned(),
+            })
+    }
+
+    /// A region's name, allocated only the first time we see it.
+    #[inline]
+    fn region(&mut self, name: String) -> &'static str {
+        self.regions
+            .entry(name)
+            .or_insert_with_key(|key| Box::leak(key.clone().into_boxed_str()))
+    }
+}
+
+/// Find a function by name.
+#[inline]
+fn lookup<T: Clone>(
+    functions: &[(&str, T)],
+    at: Position,
+    name: String,
+) -> Result<T, DescriptionError> {
+    functions
+        .iter()
+        .find(|&&(n, _)| n == name)
+        .map(|&(_, ref f)| f.clone())
+        .ok_or(DescriptionError::UndefinedFunction { at, name })
+}
+
+/// Parse a single character (`x`) or an inclusive range (`0-9`).
+#[inline]
+fn parse_range(s: &str) -> Option<Range<char>> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next(), chars.next(), chars.next()) {
+        (Some(c), None, None, None) => Some(Range::unit(c)),
+        (Some(first), Some('-'), Some(last), None) if first <= last => Some(Range { first, last }),
+        _ => None,
+    }
+}
@@ -0,0 +1,170 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Report errors with `miette`, pointing at the input that caused them.
+
+use crate::{
+    to_src::range_pattern, Ctrl, Curry, Graph, IllFormed, Input, InputError, ParseError, Range, Run,
+};
+use core::{error, fmt, iter};
+use miette::{Diagnostic, LabeledSpan, SourceCode};
+use std::collections::BTreeSet;
+
+impl<I: Input + fmt::Debug, C: Ctrl<I> + fmt::Debug> Diagnostic for IllFormed<I, C> {
+    #[inline]
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new("inator::ill_formed"))
+    }
+
+    #[inline]
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new(
+            "The parser itself is broken, not its input: check how it was built.",
+        ))
+    }
+}
+
+impl<I: Input + fmt::Debug, C: Ctrl<I> + fmt::Debug> Diagnostic for ParseError<I, C> {
+    #[inline]
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new(match *self {
+            Self::BadInput(InputError::NotAccepting) => "inator::not_accepting",
+            Self::BadInput(InputError::Unclosed) => "inator::unclosed",
+            Self::BadInput(InputError::Unopened) => "inator::unopened",
+            Self::BadInput(InputError::Absurd) => "inator::absurd",
+            Self::Incomplete { .. } => "inator::incomplete",
+            Self::BadParser(..) => "inator::ill_formed",
+        }))
+    }
+
+    #[inline]
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        match *self {
+            Self::Incomplete { ref open, .. } if !open.is_empty() => Some(Box::new(format!(
+                "Still inside {}",
+                open.iter()
+                    .map(|region| format!("`{region}`"))
+                    .collect::<Vec<_>>()
+                    .join(" inside "),
+            ))),
+            Self::BadParser(ref e) => e.help(),
+            Self::BadInput(..) | Self::Incomplete { .. } => None,
+        }
+    }
+}
+
+/// A `ParseError` along with the input that caused it, so `miette` can point at the offending token.
+#[non_exhaustive]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseReport<C: Ctrl<char>> {
+    /// What went wrong.
+    pub error: ParseError<char, C>,
+    /// The entire input.
+    pub input: String,
+    /// Byte offset into `input` of the token that caused the error (or `input.len()` if input ran out).
+    pub offset: usize,
+    /// Tokens the parser could have taken there instead (empty if we can't say or if any token would have done).
+    pub expected: Vec<Range<char>>,
+}
+
+impl<C: Ctrl<char>> fmt::Display for ParseReport<C> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl<C: Ctrl<char> + fmt::Debug> error::Error for ParseReport<C> {}
+
+impl<C: Ctrl<char> + fmt::Debug> Diagnostic for ParseReport<C> {
+    #[inline]
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.error.code()
+    }
+
+    #[inline]
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.error.help()
+    }
+
+    #[inline]
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        Some(&self.input)
+    }
+
+    #[inline]
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        let len = self
+            .input
+            .get(self.offset..)
+            .and_then(|rest| rest.chars().next())
+            .map_or(0, char::len_utf8);
+        let label = match self.error {
+            ParseError::BadInput(InputError::Absurd) => "unexpected token",
+            ParseError::BadInput(InputError::Unopened) => "closes a region that was never opened",
+            ParseError::BadInput(InputError::NotAccepting | InputError::Unclosed)
+            | ParseError::Incomplete { .. } => "input ended here",
+            ParseError::BadParser(..) => "parser broke here",
+        };
+        let span = self.offset..self.offset.saturating_add(len);
+        let expected = (!self.expected.is_empty()).then(|| {
+            LabeledSpan::at(
+                span.clone(),
+                format!(
+                    "expected {}",
+                    self.expected
+                        .iter()
+                        .map(range_pattern)
+                        .collect::<Vec<_>>()
+                        .join(" or "),
+                ),
+            )
+        });
+        Some(Box::new(
+            iter::once(LabeledSpan::at(span, label)).chain(expected),
+        ))
+    }
+}
+
+impl<C: Ctrl<char>> Graph<char, C> {
+    /// Run this parser to completion as in `accept`, but on failure,
+    /// keep the input and where in it the parser stopped, to render with `miette`.
+    /// # Errors
+    /// If the parser determines there should be an error.
+    #[inline]
+    pub fn accept_reporting(&self, input: &str) -> Result<String, ParseReport<C>> {
+        let mut run = input.chars().run(self);
+        let error = match run.finish() {
+            Ok(output_t) => return Ok(output_t),
+            Err(e) => e,
+        };
+        let expected = match error {
+            ParseError::Incomplete { ref expected, .. } => expected.clone(),
+            ParseError::BadInput(InputError::Absurd) => run
+                .ctrl
+                .view()
+                .filter_map(|i| match self.states.get(i)?.transitions {
+                    Curry::Scrutinize { ref filter, .. } => Some(filter.0.keys()),
+                    Curry::Wildcard(..) => None,
+                })
+                .flatten()
+                .copied()
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect(),
+            ParseError::BadInput(..) | ParseError::BadParser(..) => vec![],
+        };
+        Err(ParseReport {
+            error,
+            expected,
+            input: input.to_owned(),
+            offset: input
+                .char_indices()
+                .nth(run.consumed)
+                .map_or(input.len(), |(offset, _)| offset),
+        })
+    }
+}
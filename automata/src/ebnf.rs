@@ -0,0 +1,620 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Import a grammar from a (very) minimal EBNF.
+//!
+//! ```text
+//! digit = '0' | '1' | '2' ;
+//! atom = digit | '(' expr ')' ;
+//! expr = atom ('+' atom)* ;
+//! ```
+//!
+//! Each rule is `name = ... ;` (or `::=`). Terminals are quoted with `'` or `"`,
+//! items are concatenated by juxtaposition (commas optional), and `|`, `*`, `+`, `?`, and parentheses mean what you'd expect.
+//! A rule may refer to itself (directly or not) only inside a group that opens and closes with single characters,
+//! e.g. `'(' expr ')'`, which becomes a region (i.e. a `Call` on the opening character and a `Return` on the closing one).
+
+#![allow(clippy::panic)]
+
+use crate::{Curry, Deterministic, Graph, Range, RangeMap, State, Transition};
+use core::{fmt, iter};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Why a grammar couldn't be imported.
+#[non_exhaustive]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EbnfError {
+    /// Unexpected character (or end of input) at this index (in characters, not bytes).
+    Syntax {
+        /// Index (in characters) where we gave up.
+        position: usize,
+        /// What we would have accepted instead.
+        expected: &'static str,
+    },
+    /// Two rules with the same name.
+    DuplicateRule(String),
+    /// Reference to a rule that was never defined.
+    UndefinedRule(String),
+    /// A rule refers to itself without an enclosing pair of delimiters to open and close a region.
+    UndelimitedRecursion(String),
+    /// Inside this region, the closing delimiter could either close the region or continue inside it.
+    AmbiguousClose(&'static str),
+}
+
+impl fmt::Display for EbnfError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Syntax { position, expected } => {
+                write!(f, "Syntax error at character #{position}: expected {expected}")
+            }
+            Self::DuplicateRule(ref name) => write!(f, "Rule `{name}` defined more than once"),
+            Self::UndefinedRule(ref name) => write!(f, "Reference to undefined rule `{name}`"),
+            Self::UndelimitedRecursion(ref name) => write!(
+                f,
+                "Rule `{name}` refers to itself outside any pair of single-character delimiters \
+                (e.g. `'(' {name} ')'`), so it can't be parsed with a finite stack.",
+            ),
+            Self::AmbiguousClose(region) => write!(
+                f,
+                "Inside region `{region}`, the closing delimiter could also continue inside the region",
+            ),
+        }
+    }
+}
+
+/// Syntax tree of the right-hand side of a rule.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Expr {
+    /// Exactly this string.
+    Terminal(String),
+    /// Whatever the rule with this name accepts.
+    Rule(String),
+    /// Each in order.
+    Sequence(Vec<Expr>),
+    /// Any one of these.
+    Alternation(Vec<Expr>),
+    /// Any number of repetitions, including zero.
+    Star(Box<Expr>),
+    /// Any number of repetitions, but at least one.
+    Plus(Box<Expr>),
+    /// Zero or one repetitions.
+    Optional(Box<Expr>),
+}
+
+impl fmt::Display for Expr {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Terminal(ref s) => write!(f, "'{s}'"),
+            Self::Rule(ref name) => write!(f, "{name}"),
+            Self::Sequence(ref v) => {
+                write!(f, "(")?;
+                for (i, e) in v.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{e}")?;
+                }
+                write!(f, ")")
+            }
+            Self::Alternation(ref v) => {
+                write!(f, "(")?;
+                for (i, e) in v.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, " | ")?;
+                    }
+                    write!(f, "{e}")?;
+                }
+                write!(f, ")")
+            }
+            Self::Star(ref e) => write!(f, "{e}*"),
+            Self::Plus(ref e) => write!(f, "{e}+"),
+            Self::Optional(ref e) => write!(f, "{e}?"),
+        }
+    }
+}
+
+impl Deterministic<char> {
+    /// Import every rule in a grammar written in a minimal EBNF (see this module's documentation),
+    /// e.g. in a build script, then e.g. `to_file` whichever rules you need.
+    /// Parsers built this way compute no output (i.e. they return `()`).
+    /// # Errors
+    /// If the grammar is malformed or refers to itself without delimiters.
+    /// # Panics
+    /// If two alternatives conflict in a way a deterministic parser can't resolve
+    /// (e.g. one opens a region on a character that the other simply consumes).
+    #[inline]
+    pub fn from_ebnf(src: &str) -> Result<BTreeMap<String, Self>, EbnfError> {
+        let rules = Syntax {
+            chars: src.chars().collect(),
+            index: 0,
+        }
+        .grammar()?;
+        let mut compiler = Compiler {
+            rules: &rules,
+            names: BTreeMap::new(),
+            regions: BTreeMap::new(),
+        };
+        // Compile every rule first so every region any rule could open has its detour ready.
+        let compiled = rules
+            .keys()
+            .map(|name| Ok((name, compiler.rule(name, &mut vec![])?)))
+            .collect::<Result<Vec<_>, _>>()?;
+        compiled
+            .into_iter()
+            .map(|(name, parser)| Ok((name.clone(), compiler.splice_regions(parser))))
+            .collect()
+    }
+}
+
+/// Recursive-descent parser over a grammar's source.
+struct Syntax {
+    /// Source, split into characters.
+    chars: Vec<char>,
+    /// Index of the next unread character.
+    index: usize,
+}
+
+impl Syntax {
+    /// Parse every rule until the end of the input.
+    #[inline]
+    fn grammar(&mut self) -> Result<BTreeMap<String, Expr>, EbnfError> {
+        let mut rules = BTreeMap::new();
+        while self.peek().is_some() {
+            let name = self.identifier()?;
+            if !self.eat("::=") && !self.eat("=") {
+                return Err(self.expected("`=` or `::=`"));
+            }
+            let body = self.alternation()?;
+            if !self.eat(";") {
+                return Err(self.expected("`;`"));
+            }
+            if rules.insert(name.clone(), body).is_some() {
+                return Err(EbnfError::DuplicateRule(name));
+            }
+        }
+        Ok(rules)
+    }
+
+    /// Parse sequences separated by `|`.
+    #[inline]
+    fn alternation(&mut self) -> Result<Expr, EbnfError> {
+        let mut v = vec![self.sequence()?];
+        while self.eat("|") {
+            v.push(self.sequence()?);
+        }
+        Ok(if v.len() == 1 {
+            unwrap!(v.pop())
+        } else {
+            Expr::Alternation(v)
+        })
+    }
+
+    /// Parse items one after another (optionally separated by commas).
+    #[inline]
+    fn sequence(&mut self) -> Result<Expr, EbnfError> {
+        let mut v = vec![];
+        loop {
+            let _ = self.eat(",");
+            match self.peek() {
+                None | Some(';' | '|' | ')') => break,
+                Some(_) => v.push(self.postfix()?),
+            }
+        }
+        Ok(if v.len() == 1 {
+            unwrap!(v.pop())
+        } else {
+            Expr::Sequence(v)
+        })
+    }
+
+    /// Parse an atom followed by any number of `*`, `+`, or `?`.
+    #[inline]
+    fn postfix(&mut self) -> Result<Expr, EbnfError> {
+        let mut e = self.atom()?;
+        loop {
+            e = if self.eat("*") {
+                Expr::Star(Box::new(e))
+            } else if self.eat("+") {
+                Expr::Plus(Box::new(e))
+            } else if self.eat("?") {
+                Expr::Optional(Box::new(e))
+            } else {
+                return Ok(e);
+            };
+        }
+    }
+
+    /// Parse a terminal, a reference to a rule, or a parenthesized alternation.
+    #[inline]
+    fn atom(&mut self) -> Result<Expr, EbnfError> {
+        match self.peek() {
+            Some(quote @ ('\'' | '"')) => {
+                self.index = self.index.saturating_add(1);
+                let mut s = String::new();
+                loop {
+                    let c = self
+                        .next()
+                        .ok_or_else(|| self.expected("a closing quote"))?;
+                    if c == quote {
+                        return Ok(Expr::Terminal(s));
+                    }
+                    s.push(if c == '\\' {
+                        match self.next() {
+                            Some('n') => '\n',
+                            Some('t') => '\t',
+                            Some('r') => '\r',
+                            Some('0') => '\0',
+                            Some(escaped) => escaped,
+                            None => return Err(self.expected("an escaped character")),
+                        }
+                    } else {
+                        c
+                    });
+                }
+            }
+            Some('(') => {
+                self.index = self.index.saturating_add(1);
+                let e = self.alternation()?;
+                if self.eat(")") {
+                    Ok(e)
+                } else {
+                    Err(self.expected("`)`"))
+                }
+            }
+            _ => self.identifier().map(Expr::Rule),
+        }
+    }
+
+    /// Parse the name of a rule.
+    #[inline]
+    fn identifier(&mut self) -> Result<String, EbnfError> {
+        let _ = self.peek();
+        let mut s = String::new();
+        while let Some(&c) = self.chars.get(self.index) {
+            if !(c.is_alphanumeric() || c == '_' || c == '-') {
+                break;
+            }
+            s.push(c);
+            self.index = self.index.saturating_add(1);
+        }
+        if s.is_empty() {
+            Err(self.expected("a rule name, a quoted terminal, or `(`"))
+        } else {
+            Ok(s)
+        }
+    }
+
+    /// Skip whitespace, then consume this exact string if it's next.
+    #[inline]
+    fn eat(&mut self, s: &str) -> bool {
+        let _ = self.peek();
+        let n = s.chars().count();
+        let matches = self
+            .chars
+            .get(self.index..)
+            .is_some_and(|rest| rest.iter().copied().take(n).eq(s.chars()));
+        if matches {
+            self.index = self.index.saturating_add(n);
+        }
+        matches
+    }
+
+    /// Skip whitespace, then look at the next character without consuming it.
+    #[inline]
+    fn peek(&mut self) -> Option<char> {
+        while self
+            .chars
+            .get(self.index)
+            .is_some_and(|c| c.is_whitespace())
+        {
+            self.index = self.index.saturating_add(1);
+        }
+        self.chars.get(self.index).copied()
+    }
+
+    /// Consume the next character, whitespace or not.
+    #[inline]
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.get(self.index).copied();
+        self.index = self.index.saturating_add(1);
+        c
+    }
+
+    /// Syntax error right here.
+    #[inline]
+    const fn expected(&self, expected: &'static str) -> EbnfError {
+        EbnfError::Syntax {
+            position: self.index,
+            expected,
+        }
+    }
+}
+
+/// Turn syntax trees into parsers, keeping track of regions along the way.
+struct Compiler<'rules> {
+    /// Every rule in the grammar.
+    rules: &'rules BTreeMap<String, Expr>,
+    /// Region names, leaked once each, keyed by their (owned) name.
+    names: BTreeMap<String, &'static str>,
+    /// Parser to run inside each region (or `None` while we're still compiling it).
+    regions: BTreeMap<&'static str, Option<Deterministic<char>>>,
+}
+
+/// Reason a state exists only to be pointed at before we know where a region should detour.
+const PLACEHOLDER: &str = "Placeholder for a region that hasn't been compiled yet";
+
+impl<'rules> Compiler<'rules> {
+    /// Compile a rule, knowing which rules we're already in the middle of (without any region in between).
+    #[inline]
+    fn rule(
+        &mut self,
+        name: &str,
+        stack: &mut Vec<String>,
+    ) -> Result<Deterministic<char>, EbnfError> {
+        if stack.iter().any(|s| s == name) {
+            return Err(EbnfError::UndelimitedRecursion(name.to_owned()));
+        }
+        let rules = self.rules;
+        let body = rules
+            .get(name)
+            .ok_or_else(|| EbnfError::UndefinedRule(name.to_owned()))?;
+        stack.push(name.to_owned());
+        let out = self.expr(body, stack);
+        drop(stack.pop());
+        out
+    }
+
+    /// Compile any expression.
+    #[inline]
+    #[allow(clippy::arithmetic_side_effects)]
+    fn expr(
+        &mut self,
+        e: &Expr,
+        stack: &mut Vec<String>,
+    ) -> Result<Deterministic<char>, EbnfError> {
+        Ok(match *e {
+            Expr::Terminal(ref s) => terminal(s),
+            Expr::Rule(ref name) => self.rule(name, stack)?,
+            Expr::Sequence(ref v) => {
+                if let Some((open, close)) = self.delimited(v, stack) {
+                    return self.region(v, open, close);
+                }
+                let mut acc = terminal("");
+                for each in v {
+                    acc = acc >> self.expr(each, stack)?;
+                }
+                acc
+            }
+            Expr::Alternation(ref v) => {
+                let mut acc: Option<Deterministic<char>> = None;
+                for each in v {
+                    let parser = self.expr(each, stack)?;
+                    acc = Some(match acc {
+                        None => parser,
+                        Some(extant) => extant | parser,
+                    });
+                }
+                acc.unwrap_or_else(|| terminal(""))
+            }
+            Expr::Star(ref inner) => self.expr(inner, stack)?.star(),
+            Expr::Plus(ref inner) => {
+                let once = self.expr(inner, stack)?;
+                once.clone() >> once.star()
+            }
+            Expr::Optional(ref inner) => terminal("") | self.expr(inner, stack)?,
+        })
+    }
+
+    /// If this sequence opens and closes with single characters and recurses in between,
+    /// return those characters.
+    #[inline]
+    fn delimited(&self, v: &[Expr], stack: &[String]) -> Option<(char, char)> {
+        let (Some(&Expr::Terminal(ref first)), Some(&Expr::Terminal(ref last))) =
+            (v.first(), v.last())
+        else {
+            return None;
+        };
+        let (mut first_chars, mut last_chars) = (first.chars(), last.chars());
+        let (Some(open), None, Some(close), None) = (
+            first_chars.next(),
+            first_chars.next(),
+            last_chars.next(),
+            last_chars.next(),
+        ) else {
+            return None;
+        };
+        let inner = v.get(1..v.len().checked_sub(1)?)?;
+        let mut visited = BTreeSet::new();
+        inner
+            .iter()
+            .any(|e| self.mentions(e, stack, &mut visited))
+            .then_some((open, close))
+    }
+
+    /// Whether this expression could (transitively) refer to any of these rules.
+    #[inline]
+    fn mentions<'e>(&self, e: &'e Expr, targets: &[String], visited: &mut BTreeSet<&'e str>) -> bool
+    where
+        'rules: 'e,
+    {
+        match *e {
+            Expr::Terminal(_) => false,
+            Expr::Rule(ref name) => {
+                targets.contains(name)
+                    || (visited.insert(name)
+                        && self
+                            .rules
+                            .get(name)
+                            .is_some_and(|body| self.mentions(body, targets, visited)))
+            }
+            Expr::Sequence(ref v) | Expr::Alternation(ref v) => {
+                v.iter().any(|each| self.mentions(each, targets, visited))
+            }
+            Expr::Star(ref each) | Expr::Plus(ref each) | Expr::Optional(ref each) => {
+                self.mentions(each, targets, visited)
+            }
+        }
+    }
+
+    /// Open a region on the first character, run what's in between, and close it on the last character.
+    /// What runs inside is compiled once per region (with a fresh stack, since the region itself keeps us finite).
+    #[inline]
+    fn region(
+        &mut self,
+        v: &[Expr],
+        open: char,
+        close: char,
+    ) -> Result<Deterministic<char>, EbnfError> {
+        let owned = Expr::Sequence(v.to_vec()).to_string();
+        let region = *self
+            .names
+            .entry(owned)
+            .or_insert_with_key(|name| Box::leak(name.clone().into_boxed_str()));
+        if !self.regions.contains_key(region) {
+            drop(self.regions.insert(region, None));
+            let inner = Expr::Sequence(
+                v.get(1..v.len().saturating_sub(1))
+                    .map_or_else(Vec::new, <[Expr]>::to_vec),
+            );
+            let detour = close_on(self.expr(&inner, &mut vec![])?, region, close)?;
+            drop(self.regions.insert(region, Some(detour)));
+        }
+        Ok(Graph {
+            states: vec![
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(
+                            iter::once((
+                                Range::unit(open),
+                                Transition::Call {
+                                    region,
+                                    detour: 2,
+                                    dst: Box::new(Transition::Lateral {
+                                        dst: 1,
+                                        update: None,
+                                    }),
+                                    combine: ff!(|(), ()| ()),
+                                },
+                            ))
+                            .collect(),
+                        ),
+                        fallback: None,
+                    },
+                    non_accepting: iter::once(format!("Expected `{open}`").into()).collect(),
+                },
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(BTreeMap::new()),
+                        fallback: None,
+                    },
+                    non_accepting: BTreeSet::new(),
+                },
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(BTreeMap::new()),
+                        fallback: None,
+                    },
+                    non_accepting: iter::once(PLACEHOLDER.into()).collect(),
+                },
+            ],
+            initial: 0,
+        })
+    }
+
+    /// Append what runs inside every region and point each call at it instead of at a placeholder.
+    #[inline]
+    fn splice_regions(&self, mut parser: Deterministic<char>) -> Deterministic<char> {
+        let mut detours = BTreeMap::new();
+        for (&region, detour) in &self.regions {
+            let Some(compiled) = detour.clone() else {
+                panic!("Internal error: region `{region}` never finished compiling")
+            };
+            let size = parser.states.len();
+            let Graph { states, initial } = compiled
+                .map_indices(|i| i.checked_add(size).expect("Absurdly huge number of states"));
+            parser.states.extend(states);
+            let _ = detours.insert(region, initial);
+        }
+        for state in &mut parser.states {
+            for t in state.transitions.values_mut() {
+                for (&region, detour) in &detours {
+                    t.redirect_region(region, detour);
+                }
+            }
+        }
+        let mut out = parser.trim();
+        out.sort();
+        out
+    }
+}
+
+/// Accept exactly this string.
+#[inline]
+fn terminal(s: &str) -> Deterministic<char> {
+    Graph {
+        states: s
+            .chars()
+            .enumerate()
+            .map(|(i, c)| State {
+                transitions: Curry::Scrutinize {
+                    filter: RangeMap(
+                        iter::once((
+                            Range::unit(c),
+                            Transition::Lateral {
+                                dst: i.saturating_add(1),
+                                update: None,
+                            },
+                        ))
+                        .collect(),
+                    ),
+                    fallback: None,
+                },
+                non_accepting: iter::once(format!("Expected `{c}`").into()).collect(),
+            })
+            .chain(iter::once(State {
+                transitions: Curry::Scrutinize {
+                    filter: RangeMap(BTreeMap::new()),
+                    fallback: None,
+                },
+                non_accepting: BTreeSet::new(),
+            }))
+            .collect(),
+        initial: 0,
+    }
+}
+
+/// Close a region on this character from every accepting state (which then no longer accepts on its own).
+#[inline]
+fn close_on(
+    mut parser: Deterministic<char>,
+    region: &'static str,
+    close: char,
+) -> Result<Deterministic<char>, EbnfError> {
+    for state in &mut parser.states {
+        if !state.non_accepting.is_empty() {
+            continue;
+        }
+        let Curry::Scrutinize {
+            ref mut filter,
+            ref fallback,
+        } = state.transitions
+        else {
+            return Err(EbnfError::AmbiguousClose(region));
+        };
+        if fallback.is_some() || filter.0.keys().any(|range| range.contains(&close)) {
+            return Err(EbnfError::AmbiguousClose(region));
+        }
+        drop(
+            filter
+                .0
+                .insert(Range::unit(close), Transition::Return { region }),
+        );
+        state.non_accepting = iter::once(format!("Expected `{close}`").into()).collect();
+    }
+    Ok(parser)
+}
@@ -0,0 +1,143 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Mark the end of input with a sentinel token.
+
+use crate::{
+    Curry, Deterministic, Graph, IllFormed, Merge, ParseError, Range, RangeMap, Rejection, State,
+    Step, Transition,
+};
+use core::iter;
+use std::collections::{BTreeMap, BTreeSet};
+
+impl<I: Step> Deterministic<I> {
+    /// Accept only once `eof` follows anything this parser would have accepted, and nothing after it.
+    /// The sentinel doesn't touch the output, so this accepts with the same output as before.
+    /// Run the result with `accept_with_eof` to add the sentinel automatically once input runs out.
+    /// # Errors
+    /// If any state that used to accept already does something on `eof`.
+    #[inline]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn with_eof(self, eof: I) -> Result<Self, IllFormed<I, usize>> {
+        let done = self.states.len();
+        let expected = Rejection::ExpectedEof(eof.clone());
+        let on_eof = Curry::Scrutinize {
+            filter: RangeMap(
+                iter::once((
+                    Range::unit(eof),
+                    Transition::Lateral {
+                        dst: done,
+                        update: None,
+                    },
+                ))
+                .collect(),
+            ),
+            fallback: None,
+        };
+        let mut states = self
+            .states
+            .into_iter()
+            .map(|state| {
+                if !state.non_accepting.is_empty() {
+                    return Ok(state);
+                }
+                Ok(State {
+                    transitions: state.transitions.merge(on_eof.clone())?,
+                    non_accepting: iter::once(expected.clone()).collect(),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        states.push(State {
+            transitions: Curry::Scrutinize {
+                filter: RangeMap(BTreeMap::new()),
+                fallback: None,
+            },
+            non_accepting: BTreeSet::new(),
+        });
+        let mut out = Graph {
+            states,
+            initial: self.initial,
+        };
+        out.sort();
+        Ok(out)
+    }
+
+    /// Run this parser to completion as in `accept`, but add `eof` once input runs out (e.g. for a parser from `with_eof`).
+    /// # Errors
+    /// If the parser determines there should be an error.
+    #[inline]
+    pub fn accept_with_eof<In: IntoIterator<Item = I>>(
+        &self,
+        input: In,
+        eof: I,
+    ) -> Result<String, ParseError<I, usize>> {
+        self.accept(input.into_iter().chain(iter::once(eof)))
+    }
+
+    /// Treat `terminator` like the end of input: wherever this parser would have accepted,
+    /// `terminator` finishes the parse (with the same output) and everything after it is ignored,
+    /// and anywhere else, it rejects the input.
+    /// `terminator` never does anything else, so grammars don't have to leave room for it.
+    /// # Errors
+    /// If we need to split a range around `terminator` but it has no immediate predecessor.
+    /// # Panics
+    /// If this parser has (almost) `usize::MAX` states.
+    #[inline]
+    pub fn with_terminator(self, terminator: I) -> Result<Self, IllFormed<I, usize>> {
+        let done = self.states.len();
+        let dead = done.checked_add(1).expect("Absurdly huge number of states");
+        let mut states = self
+            .states
+            .into_iter()
+            .map(|mut state| {
+                let dst = if state.non_accepting.is_empty() {
+                    done
+                } else {
+                    dead
+                };
+                let (mut filter, fallback) = match state.transitions {
+                    Curry::Wildcard(t) => (RangeMap(BTreeMap::new()), Some(t)),
+                    Curry::Scrutinize { filter, fallback } => (filter, fallback),
+                };
+                filter.split_at(&terminator)?;
+                if let Some(next) = terminator.succ() {
+                    filter.split_at(&next)?;
+                }
+                let only = Range::unit(terminator.clone());
+                filter.remove(&only);
+                drop(
+                    filter
+                        .0
+                        .insert(only, Transition::Lateral { dst, update: None }),
+                );
+                // If the terminator was the last token the fallback could catch, it can't catch anything now.
+                state.transitions = Curry::Scrutinize { filter, fallback }.prune_fallback();
+                Ok(state)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        // Ignore anything after the terminator.
+        states.push(State {
+            transitions: Curry::Wildcard(Transition::Lateral {
+                dst: done,
+                update: None,
+            }),
+            non_accepting: BTreeSet::new(),
+        });
+        states.push(State {
+            transitions: Curry::Scrutinize {
+                filter: RangeMap(BTreeMap::new()),
+                fallback: None,
+            },
+            non_accepting: iter::once(Rejection::Terminated(terminator)).collect(),
+        });
+        let mut out = Graph {
+            states,
+            initial: self.initial,
+        };
+        out.sort();
+        Ok(out)
+    }
+}
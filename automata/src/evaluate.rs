@@ -0,0 +1,231 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Interpret a parser all the way through, computing an actual output value instead of only its type.
+
+use crate::{
+    f::Dynamic, walk::Visit, Deterministic, IllFormed, Input, ParseError, ToSrc, Typed, Update, FF,
+};
+use core::mem;
+
+/// Compute an actual output value along a walk.
+struct Evaluate<'check, F: Fn(&Dynamic, usize), G: FnMut(&Dynamic)> {
+    /// Output so far.
+    acc: Dynamic,
+    /// Payload of the token we're reading, until a function takes it.
+    payload: Option<Dynamic>,
+    /// Called on the output (and the index of the token) after every update.
+    check: &'check F,
+    /// Called on the output after every token that leaves the parser accepting.
+    on_accept: &'check mut G,
+}
+
+impl<'graph, I: Input, F: Fn(&Dynamic, usize), G: FnMut(&Dynamic)> Visit<'graph, I, usize>
+    for Evaluate<'_, F, G>
+{
+    type Payload = Option<Dynamic>;
+    /// Output from before the region opened, and the payload of the token that opened it.
+    type Saved = (Dynamic, Option<Dynamic>);
+
+    #[inline]
+    fn read(&mut self, payload: Option<Dynamic>) {
+        self.payload = payload;
+    }
+
+    #[inline]
+    fn lateral(
+        &mut self,
+        update: Option<&'graph Update<I>>,
+        token: &I,
+        _: usize,
+        index: usize,
+    ) -> Result<(), ParseError<I, usize>> {
+        let Some(called) = update else {
+            return Ok(());
+        };
+        let acc = mem::replace(&mut self.acc, Box::new(()));
+        let out = if called.payload_t.is_some() {
+            let f = called.with_payload.0.as_ref().ok_or_else(|| {
+                ParseError::BadParser(IllFormed::Uncallable(called.src.to_string()))
+            })?;
+            let p = self.payload.take().ok_or_else(|| {
+                ParseError::BadParser(IllFormed::MissingPayload(called.src.to_string()))
+            })?;
+            f(acc, token.clone(), p)
+        } else {
+            let f = called.ptr.0.as_ref().ok_or_else(|| {
+                ParseError::BadParser(IllFormed::Uncallable(called.src.to_string()))
+            })?;
+            f(acc, token.clone())
+        }
+        .ok_or_else(|| {
+            ParseError::BadParser(IllFormed::TypeMismatch(
+                called.input_t.to_string(),
+                called.output_t.to_string(),
+            ))
+        })?;
+        (self.check)(&out, index);
+        self.acc = out;
+        Ok(())
+    }
+
+    #[inline]
+    fn open(&mut self) -> Self::Saved {
+        (
+            mem::replace(&mut self.acc, Box::new(())),
+            self.payload.take(),
+        )
+    }
+
+    #[inline]
+    fn close(
+        &mut self,
+        combine: &'graph FF,
+        (saved, payload): Self::Saved,
+        _: usize,
+        _: usize,
+    ) -> Result<(), ParseError<I, usize>> {
+        let f =
+            combine.ptr.0.as_ref().ok_or_else(|| {
+                ParseError::BadParser(IllFormed::Uncallable(combine.src.to_string()))
+            })?;
+        let inside = mem::replace(&mut self.acc, Box::new(()));
+        self.acc = f(saved, inside).ok_or_else(|| {
+            ParseError::BadParser(IllFormed::TypeMismatch(
+                combine.lhs_t.to_string(),
+                combine.rhs_t.to_string(),
+            ))
+        })?;
+        // The token that opened the region takes the next transition, so its payload comes back too.
+        self.payload = payload;
+        Ok(())
+    }
+
+    #[inline]
+    fn accepted(&mut self) {
+        (self.on_accept)(&self.acc);
+    }
+}
+
+impl<I: Input, O: 'static + ToSrc> Typed<Deterministic<I>, O> {
+    /// Run this parser to completion and compute its output as an actual value,
+    /// calling each function exactly as the generated source code would.
+    /// Only for parsers that promise what they compute (see `Graph::typed`).
+    /// # Errors
+    /// If the parser rejects this input or if any function along the way was written only as source code
+    /// (i.e. not with `update!` or `ff!`).
+    #[inline]
+    pub fn accept<In: IntoIterator<Item = I>>(&self, input: In) -> Result<O, ParseError<I, usize>> {
+        self.evaluate(without_payloads(input), &|_, _| {}, &mut |_| {})
+    }
+
+    /// Compute an output exactly as in `accept`, but on tokens that each come with a payload
+    /// (e.g. `(kind, text)` pairs from a lexer): the parser branches only on each token,
+    /// and functions from `update_with_payload!` receive each token paired with its payload.
+    /// Functions from `update!` still receive only the token.
+    /// # Errors
+    /// Exactly as in `accept`, or if a payload isn't a `P`.
+    #[inline]
+    pub fn accept_with_payload<P: 'static, In: IntoIterator<Item = (I, P)>>(
+        &self,
+        input: In,
+    ) -> Result<O, ParseError<I, usize>> {
+        self.evaluate(
+            input.into_iter().map(|(token, payload)| {
+                let erased: Dynamic = Box::new(payload);
+                (token, Some(erased))
+            }),
+            &|_, _| {},
+            &mut |_| {},
+        )
+    }
+
+    /// Compute an output exactly as in `accept`, but also call `on_accept` with the output so far
+    /// every time the input so far would be accepted (i.e. in an accepting state with no region open),
+    /// e.g. to handle each record in a stream of records as soon as it's complete instead of at the end.
+    /// # Errors
+    /// Exactly as in `accept` (after calling `on_accept` for every accepted prefix before the error).
+    #[inline]
+    pub fn run_emitting<In: IntoIterator<Item = I>, F: FnMut(&O)>(
+        &self,
+        input: In,
+        mut on_accept: F,
+    ) -> Result<O, ParseError<I, usize>> {
+        self.evaluate(without_payloads(input), &|_, _| {}, &mut |acc: &Dynamic| {
+            if let Some(o) = acc.downcast_ref::<O>() {
+                on_accept(o);
+            }
+        })
+    }
+
+    /// Compute an output exactly as in `accept`, but (in debug builds only) check `invariant`
+    /// against the output after every update that produces an `O`, e.g. to catch a buggy `update!` during development.
+    /// Compiles to plain `accept` in release builds.
+    /// # Errors
+    /// Exactly as in `accept`.
+    /// # Panics
+    /// In debug builds, if `invariant` returns `false`, with the index of the token whose update broke it.
+    #[inline]
+    pub fn accept_with_invariant<In: IntoIterator<Item = I>, F: Fn(&O) -> bool>(
+        &self,
+        input: In,
+        invariant: F,
+    ) -> Result<O, ParseError<I, usize>> {
+        self.evaluate(
+            without_payloads(input),
+            &|acc: &Dynamic, index| {
+                if let Some(o) = acc.downcast_ref::<O>() {
+                    debug_assert!(
+                        invariant(o),
+                        "Invariant violated by the update on the token at index {index}",
+                    );
+                }
+            },
+            &mut |_| {},
+        )
+    }
+
+    /// Compute an output as in `accept`, calling `check` on the output (and the index of the token)
+    /// after every update and `on_accept` on the output after every token that leaves the parser accepting.
+    #[inline]
+    fn evaluate<
+        In: IntoIterator<Item = (I, Option<Dynamic>)>,
+        F: Fn(&Dynamic, usize),
+        G: FnMut(&Dynamic),
+    >(
+        &self,
+        input: In,
+        check: &F,
+        on_accept: &mut G,
+    ) -> Result<O, ParseError<I, usize>> {
+        let mut evaluate = Evaluate {
+            acc: Box::new(()),
+            payload: None,
+            check,
+            on_accept,
+        };
+        self.walk(input, &mut evaluate)?;
+        // Only reachable if this parser broke its promise (e.g. if it was built by hand instead of with `typed`).
+        evaluate.acc.downcast().map_or_else(
+            |_| {
+                let output_t = self.output_type().map_err(ParseError::BadParser)?;
+                Err(ParseError::BadParser(IllFormed::WrongReturnType(
+                    output_t.unwrap_or("()").to_owned(),
+                    O::src_type(),
+                )))
+            },
+            |o| Ok(*o),
+        )
+    }
+}
+
+/// Pair each token with no payload.
+#[inline]
+fn without_payloads<I: Input, In: IntoIterator<Item = I>>(
+    input: In,
+) -> impl Iterator<Item = (I, Option<Dynamic>)> {
+    input.into_iter().map(|token| (token, None))
+}
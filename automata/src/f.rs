@@ -8,32 +8,106 @@
 
 #![allow(clippy::module_name_repetitions)]
 
-use crate::ToSrc;
+use crate::{Ctrl, IllFormed, Input, ToSrc};
+use core::{any::Any, cmp, fmt, hash, panic::RefUnwindSafe};
+use std::sync::Arc;
+
+/// A value whose type we only know at runtime (checked against the source-code representation of its type).
+pub type Dynamic = Box<dyn Any>;
+
+/// Function we can call on an output-so-far and an input token while interpreting a parser.
+pub type UpdateFn<I> = dyn Fn(Dynamic, I) -> Option<Dynamic> + RefUnwindSafe + Send + Sync;
+
+/// Function we can call on an output-so-far, an input token, and that token's payload while interpreting a parser.
+pub type PayloadFn<I> =
+    dyn Fn(Dynamic, I, Dynamic) -> Option<Dynamic> + RefUnwindSafe + Send + Sync;
+
+/// Function we can call on two outputs while interpreting a parser.
+pub type CombineFn = dyn Fn(Dynamic, Dynamic) -> Option<Dynamic> + RefUnwindSafe + Send + Sync;
+
+/// A function we can call while interpreting a parser, not just in generated source code.
+/// Ignored by every comparison, since functions are compared by their source code instead.
+#[allow(clippy::exhaustive_structs)]
+pub struct Erased<T: ?Sized>(
+    /// The function itself, if we have it (e.g. not if a function was only ever written as a string).
+    pub Option<Arc<T>>,
+);
+
+impl<T: ?Sized> Clone for Erased<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: ?Sized> Default for Erased<T> {
+    #[inline]
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+impl<T: ?Sized> PartialEq for Erased<T> {
+    #[inline]
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl<T: ?Sized> Eq for Erased<T> {}
+
+impl<T: ?Sized> PartialOrd for Erased<T> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: ?Sized> Ord for Erased<T> {
+    #[inline]
+    fn cmp(&self, _: &Self) -> cmp::Ordering {
+        cmp::Ordering::Equal
+    }
+}
+
+impl<T: ?Sized> hash::Hash for Erased<T> {
+    #[inline]
+    fn hash<H: hash::Hasher>(&self, _: &mut H) {}
+}
+
+impl<T: ?Sized> fmt::Debug for Erased<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", if self.0.is_some() { "fn" } else { "_" })
+    }
+}
 
 /// One-argument function.
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct F {
     /// Source-code representation of this function.
-    pub src: String,
+    pub src: Arc<str>,
     /// Argument type.
-    pub arg_t: String,
+    pub arg_t: Arc<str>,
     /// Output type.
-    pub output_t: String,
+    pub output_t: Arc<str>,
 }
 
 /// Two-argument function.
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct FF {
     /// Source-code representation of this function.
-    pub src: String,
+    pub src: Arc<str>,
     /// Type of the first argument.
-    pub lhs_t: String,
+    pub lhs_t: Arc<str>,
     /// Type of the second argument.
-    pub rhs_t: String,
+    pub rhs_t: Arc<str>,
     /// Output type.
-    pub output_t: String,
+    pub output_t: Arc<str>,
+    /// The function itself, to call while interpreting a parser.
+    pub ptr: Erased<CombineFn>,
 }
 
 impl F {
@@ -42,9 +116,9 @@ impl F {
     #[must_use]
     pub fn _from_macro<Arg: ToSrc, Output: ToSrc>(src: String, _: fn(Arg) -> Output) -> Self {
         Self {
-            src,
-            arg_t: Arg::src_type(),
-            output_t: Output::src_type(),
+            src: src.into(),
+            arg_t: Arg::src_type().into(),
+            output_t: Output::src_type().into(),
         }
     }
 }
@@ -53,15 +127,62 @@ impl FF {
     /// Internals of the `ff!(...)` macro.
     #[inline]
     #[must_use]
-    pub fn _from_macro<Lhs: ToSrc, Rhs: ToSrc, Output: ToSrc>(
+    pub fn _from_macro<Lhs: 'static + ToSrc, Rhs: 'static + ToSrc, Output: 'static + ToSrc>(
         src: String,
-        _: fn(Lhs, Rhs) -> Output,
+        f: fn(Lhs, Rhs) -> Output,
     ) -> Self {
         Self {
-            src,
-            lhs_t: Lhs::src_type(),
-            rhs_t: Rhs::src_type(),
-            output_t: Output::src_type(),
+            src: src.into(),
+            lhs_t: Lhs::src_type().into(),
+            rhs_t: Rhs::src_type().into(),
+            output_t: Output::src_type().into(),
+            ptr: Erased(Some(Arc::new(move |lhs: Dynamic, rhs: Dynamic| {
+                let out: Dynamic = Box::new(f(*lhs.downcast().ok()?, *rhs.downcast().ok()?));
+                Some(out)
+            }))),
         }
     }
+
+    /// Check types of the right-hand argument (output from a region) and compute the output type.
+    /// # Errors
+    /// If the argument type doesn't match the function's expected right-hand type.
+    #[inline]
+    pub fn invoke<I: Input, C: Ctrl<I>>(&self, rhs_t: &str) -> Result<String, IllFormed<I, C>> {
+        rhs_t
+            .eq(&*self.rhs_t)
+            .then(|| self.output_t.to_string())
+            .ok_or_else(|| IllFormed::TypeMismatch(rhs_t.to_owned(), self.rhs_t.to_string()))
+    }
+}
+
+impl fmt::Debug for F {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for F {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "fn({}) : {} -> {}", self.src, self.arg_t, self.output_t)
+    }
+}
+
+impl fmt::Debug for FF {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for FF {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "fn({}) : {}, {} -> {}",
+            self.src, self.lhs_t, self.rhs_t, self.output_t,
+        )
+    }
 }
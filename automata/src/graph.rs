@@ -7,12 +7,12 @@
 //! Automaton loosely based on visibly pushdown automata.
 
 use crate::{
-    try_merge, Check, Ctrl, Curry, IllFormed, Input, InputError, Merge, ParseError, RangeMap,
-    State, Transition,
+    analysis::Reach, try_merge, Check, Ctrl, Curry, IllFormed, Input, InputError, Merge,
+    ParseError, RangeMap, Rejection, State, Step, ToSrc, Transition,
 };
-use core::{iter, num::NonZeroUsize};
+use core::{hash, iter, num::NonZeroUsize};
 use std::{
-    collections::{btree_map, BTreeMap, BTreeSet},
+    collections::{btree_map, BTreeMap, BTreeSet, VecDeque},
     ffi::OsStr,
     fs, io,
     path::Path,
@@ -57,7 +57,395 @@ impl<I: Input, C: Ctrl<I>> PartialEq for Graph<I, C> {
     }
 }
 
+/// Structural, exactly as `==`: two parsers that accept the same language can still hash differently.
+impl<I: Input + hash::Hash, C: Ctrl<I> + hash::Hash> hash::Hash for Graph<I, C> {
+    #[inline]
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.initial.hash(state);
+        self.states.hash(state);
+    }
+}
+
 impl<I: Input, C: Ctrl<I>> Graph<I, C> {
+    /// Compute the output type of any successful run.
+    /// # Errors
+    /// If multiple accepting states attempt to return different types.
+    #[inline]
+    pub fn output_type(&self) -> Result<Option<&str>, IllFormed<I, C>> {
+        self.accumulator_types()?
+            .into_iter()
+            .zip(&self.states)
+            .filter(|&(_, state)| state.non_accepting.is_empty())
+            .try_fold(None, |acc: Option<&str>, (input_t, _)| {
+                match (acc, input_t) {
+                    (Some(t), Some(u)) if t != u => {
+                        Err(IllFormed::WrongReturnType(t.to_owned(), u.to_owned()))
+                    }
+                    _ => Ok(acc.or(input_t)),
+                }
+            })
+    }
+
+    /// The accumulator type each state expects, if we can tell,
+    /// following each type from wherever we know it (a state with an update that says what it takes)
+    /// through updates (which change it), transitions without one (which pass it along),
+    /// and regions (which start from `()` and then combine their output with whatever came before).
+    /// # Errors
+    /// If any transition's output doesn't match what its destination expects (or what it gets from elsewhere).
+    #[inline]
+    pub(crate) fn accumulator_types(&self) -> Result<Vec<Option<&str>>, IllFormed<I, C>> {
+        let mut known = self
+            .states
+            .iter()
+            .map(State::input_type)
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut queue: Vec<usize> = (0..known.len()).collect();
+        while let Some(i) = queue.pop() {
+            let (Some(state), Some(&Some(passed))) = (self.states.get(i), known.get(i)) else {
+                continue;
+            };
+            let mut flows = vec![];
+            for transition in state.transitions.values() {
+                flow(transition, passed, &mut flows);
+            }
+            for (dst, lhs_out) in flows {
+                for j in dst.view() {
+                    match known.get_mut(j) {
+                        None => {}
+                        Some(&mut Some(rhs_in)) => {
+                            if rhs_in != lhs_out {
+                                return Err(IllFormed::ComposeTypeMismatch {
+                                    lhs_out: lhs_out.to_owned(),
+                                    rhs_in: rhs_in.to_owned(),
+                                });
+                            }
+                        }
+                        Some(slot @ &mut None) => {
+                            *slot = Some(lhs_out);
+                            queue.push(j);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(known)
+    }
+
+    /// Indices of every state reachable from these states (including the states themselves).
+    /// Calls count as reaching both their detour and their continuation.
+    #[inline]
+    #[must_use]
+    pub fn reachable_from<It: IntoIterator<Item = usize>>(&self, from: It) -> BTreeSet<usize> {
+        let mut reached = BTreeSet::new();
+        let mut frontier: Vec<usize> = from.into_iter().collect();
+        while let Some(i) = frontier.pop() {
+            if !reached.insert(i) {
+                continue;
+            }
+            if let Some(state) = self.states.get(i) {
+                for t in state.transitions.values() {
+                    frontier.extend(t.dsts().into_iter().flat_map(Ctrl::view));
+                }
+            }
+        }
+        reached
+    }
+}
+
+/// Everywhere this transition hands an accumulator directly, and the type of each,
+/// given that the accumulator so far is a `passed`.
+#[inline]
+fn flow<'graph, I: Input, C: Ctrl<I>>(
+    transition: &'graph Transition<I, C>,
+    passed: &'graph str,
+    flows: &mut Vec<(&'graph C, &'graph str)>,
+) {
+    match *transition {
+        Transition::Lateral {
+            ref dst,
+            ref update,
+        } => flows.push((dst, update.as_ref().map_or(passed, |u| &u.output_t))),
+        Transition::Call {
+            ref detour,
+            ref dst,
+            ref combine,
+            ..
+        } => {
+            flows.push((detour, "()"));
+            flow(dst, &combine.output_t, flows);
+        }
+        Transition::Return { .. } => {}
+    }
+}
+
+impl<I: Input, C: Ctrl<I>> Graph<I, C> {
+    /// Run this parser to completion and check types along the way.
+    /// # Errors
+    /// If the parser determines there should be an error.
+    #[inline]
+    #[allow(unsafe_code)]
+    pub fn accept<In: IntoIterator<Item = I>>(
+        &self,
+        input: In,
+    ) -> Result<String, ParseError<I, C>> {
+        use crate::Run;
+        debug_assert!(
+            self.initial.view().next().is_some(),
+            "{}",
+            IllFormed::<I, C>::EmptyInitial,
+        );
+        input.run(self).finish()
+    }
+
+    /// Run this parser to completion on a borrowed sequence of tokens (e.g. `&[I]`, `Vec<I>`, or, for bytes, a `String`),
+    /// cloning each token as we go, so callers don't have to pick the right iterator adapter.
+    /// ```rust
+    /// use inator_automata::*;
+    /// use std::collections::BTreeSet;
+    /// // Accept any sequence of bytes whatsoever.
+    /// let anything: Deterministic<u8> = Graph {
+    ///     states: vec![State {
+    ///         transitions: Curry::Wildcard(Transition::Lateral { dst: 0, update: None }),
+    ///         non_accepting: BTreeSet::new(),
+    ///     }],
+    ///     initial: 0,
+    /// };
+    /// assert!(anything.accept_ref("Hello!".to_owned()).is_ok());
+    /// assert!(anything.accept_ref(vec![0_u8, 255]).is_ok());
+    /// ```
+    /// # Errors
+    /// If the parser determines there should be an error.
+    #[inline]
+    pub fn accept_ref<T: AsRef<[I]>>(&self, input: T) -> Result<String, ParseError<I, C>> {
+        self.accept(input.as_ref().iter().cloned())
+    }
+
+    /// Run this parser to completion as in `accept`, but also hand each token it consumes to `step`
+    /// along with a caller-provided sink (e.g. to copy matched tokens somewhere without an intermediate buffer).
+    /// # Errors
+    /// If the parser determines there should be an error (after handing `step` every token before it).
+    #[inline]
+    pub fn run_into<In: IntoIterator<Item = I>, S, F: FnMut(&mut S, &I)>(
+        &self,
+        input: In,
+        sink: &mut S,
+        mut step: F,
+    ) -> Result<String, ParseError<I, C>> {
+        use crate::Run;
+        debug_assert!(
+            self.initial.view().next().is_some(),
+            "{}",
+            IllFormed::<I, C>::EmptyInitial,
+        );
+        let mut run = input.run(self);
+        for r in &mut run {
+            step(sink, &r?);
+        }
+        for i in run.ctrl.view() {
+            if get!(self.states, i).non_accepting.is_empty() {
+                return Ok(run.output_t);
+            }
+        }
+        Err(ParseError::BadInput(InputError::NotAccepting))
+    }
+
+    /// Run this parser on the longest prefix of a slice that it accepts,
+    /// returning the output from that prefix and the rest of the slice (without copying it).
+    /// If no prefix is accepted, return the error that stopped the parser and the whole slice.
+    #[inline]
+    #[allow(clippy::missing_panics_doc, clippy::type_complexity)]
+    pub fn parse_slice<'input>(
+        &self,
+        input: &'input [I],
+    ) -> (Result<String, ParseError<I, C>>, &'input [I]) {
+        use crate::Run;
+        let mut run = input.iter().cloned().run(self);
+        let mut consumed: usize = 0;
+        let mut longest = None;
+        let error = loop {
+            if run.stack.is_empty()
+                && run
+                    .ctrl
+                    .view()
+                    .any(|i| get!(self.states, i).non_accepting.is_empty())
+            {
+                longest = Some((consumed, run.output_t.clone()));
+            }
+            match run.next() {
+                Some(Ok(_)) => consumed = unwrap!(consumed.checked_add(1)),
+                Some(Err(e)) => break e,
+                None => break ParseError::BadInput(InputError::NotAccepting),
+            }
+        };
+        longest.map_or((Err(error), input), |(n, output)| {
+            (Ok(output), get!(input, n..))
+        })
+    }
+
+    /// Compute the input type of any successful run.
+    /// # Errors
+    /// If multiple accepting states attempt to return different types.
+    #[inline]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn input_type(&self) -> Result<Option<&str>, IllFormed<I, C>> {
+        self.initial
+            .view()
+            .map(|i| get!(self.states, i))
+            .try_fold(None, |acc, state| {
+                let shit = acc.merge(state.transitions.values().try_fold(None, |accc, t| {
+                    accc.merge(t.input_type()).map_or_else(
+                        |(a, b)| {
+                            if a == b {
+                                Ok(Some(a))
+                            } else {
+                                Err(IllFormed::TypeMismatch(a.to_owned(), b.to_owned()))
+                            }
+                        },
+                        Ok,
+                    )
+                })?);
+                shit.map_or_else(
+                    |(a, b)| {
+                        if a == b {
+                            Ok(Some(a))
+                        } else {
+                            Err(IllFormed::TypeMismatch(a.to_owned(), b.to_owned()))
+                        }
+                    },
+                    Ok,
+                )
+            })
+    }
+
+    /// Change nothing about the semantics but merge identical states and renumber the rest in a canonical order:
+    /// each state reachable from the initial state is numbered in the order a breadth-first search first reaches it,
+    /// following transitions in order of their ranges (then any fallback) and each call's detour before its continuation.
+    /// For deterministic parsers, equivalent constructions (e.g. `a | b` and `b | a`) come out equal,
+    /// however their states happened to be numbered along the way.
+    #[inline]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn sort(&mut self) {
+        self.dedup_states();
+        self.renumber_by_search();
+    }
+
+    /// Sort the internal vector of states and merge identical states.
+    #[inline]
+    #[allow(clippy::missing_panics_doc)]
+    fn dedup_states(&mut self) {
+        // Merging duplicates can make other states identical, so repeat until nothing merges.
+        loop {
+            // Associate each original index with a concrete state instead of just an index,
+            // since we're going to be swapping the indices around.
+            let index_map: BTreeMap<usize, State<_, _>> =
+                self.states.iter().cloned().enumerate().collect();
+            self.states.sort_unstable();
+            self.states.dedup(); // <-- Cool that we can do this!
+            self.initial = self
+                .initial
+                .clone()
+                .map_indices(|i| unwrap!(self.states.binary_search(unwrap!(index_map.get(&i)))));
+            // Can't do this in-place since the entire state array is required as an argument.
+            self.states = self
+                .states
+                .iter()
+                .map(|s| s.reindex(&self.states, &index_map))
+                .collect();
+            if self.states.len() == index_map.len() {
+                return;
+            }
+        }
+    }
+
+    /// Number states in the order a breadth-first search from the initial state first reaches them,
+    /// leaving any unreachable states at the end in their original order.
+    #[inline]
+    fn renumber_by_search(&mut self) {
+        let mut order = vec![];
+        let mut seen = BTreeSet::new();
+        let mut queue: VecDeque<usize> = self.initial.view().collect();
+        while let Some(i) = queue.pop_front() {
+            if !seen.insert(i) {
+                continue;
+            }
+            order.push(i);
+            if let Some(state) = self.states.get(i) {
+                for t in state.transitions.values() {
+                    t.search_order(&mut queue);
+                }
+            }
+        }
+        order.extend((0..self.states.len()).filter(|i| !seen.contains(i)));
+        let mut renumbered = vec![0; self.states.len()];
+        for (new, &old) in order.iter().enumerate() {
+            if let Some(slot) = renumbered.get_mut(old) {
+                *slot = new;
+            }
+        }
+        let states = order
+            .iter()
+            .map(|&i| {
+                get!(self.states, i)
+                    .clone()
+                    .map_indices(|j| *get!(renumbered, j))
+            })
+            .collect();
+        self.states = states;
+        self.initial = self.initial.clone().map_indices(|j| *get!(renumbered, j));
+    }
+
+    /// Check if this parser ever could, at any point, involve a fallback transition.
+    #[inline]
+    #[must_use]
+    pub fn involves_any_fallback(&self) -> bool {
+        self.states.iter().any(State::involves_any_fallback)
+    }
+
+    /// Remove every state that no input could ever reach.
+    #[inline]
+    #[must_use]
+    pub fn trim(self) -> Self {
+        let reachable = self.reachable_from(self.initial.view());
+        self.restrict(&reachable, self.initial.clone())
+    }
+
+    /// Check whether this parser accepts the empty input (often a sign of a stray `.optional()` or `.star()`),
+    /// i.e. whether any initial state is accepting. No transition (not even a `Call`) can happen without consuming a token,
+    /// so nothing else could be reached without input.
+    #[inline]
+    #[must_use]
+    pub fn accepts_empty(&self) -> bool {
+        self.initial
+            .view()
+            .any(|i| get!(self.states, i).non_accepting.is_empty())
+    }
+
+    /// Keep only these states (which must be closed under transitions), renumbered in order.
+    #[inline]
+    #[must_use]
+    pub(crate) fn restrict(&self, keep: &BTreeSet<usize>, initial: C) -> Self {
+        let ordering: Vec<usize> = keep.iter().copied().collect();
+        let renumber = |i| unwrap!(ordering.binary_search(&i));
+        Self {
+            states: ordering
+                .iter()
+                .map(|&i| get!(self.states, i).clone().map_indices(renumber))
+                .collect(),
+            initial: initial.map_indices(renumber),
+        }
+    }
+}
+
+impl<I: Step, C: Ctrl<I>> Graph<I, C> {
+    /// Check whether no accepting state is reachable with every region closed, i.e. whether this parser rejects every input.
+    /// A call only leads on to its destination if some input through its detour can actually return,
+    /// exactly as in `shortest_accepted` and `acceptance_examples`.
+    #[inline]
+    #[must_use]
+    pub fn accepts_nothing(&self) -> bool {
+        Reach::new(self).accepting().is_empty()
+    }
+
     /// Check a subset of well-formedness.
     /// Note that this can't check if determinization will succeed in less time than actually trying;
     /// if you want to see if there can be any runtime errors, just try to determinize it.
@@ -65,11 +453,9 @@ impl<I: Input, C: Ctrl<I>> Graph<I, C> {
     /// When ill-formed (with a witness).
     #[inline]
     pub fn check(&self) -> Result<(), IllFormed<I, C>> {
-        let n_states = self.states.len();
+        self.check_fragment()?;
         for i in self.initial.view() {
-            let Some(state) = self.states.get(i) else {
-                return Err(IllFormed::OutOfBounds(i));
-            };
+            let state = get!(self.states, i);
             if let Some(t) = state.input_type()? {
                 if t != "()" {
                     return Err(IllFormed::InitialNotUnit(t.to_owned()));
@@ -84,62 +470,101 @@ impl<I: Input, C: Ctrl<I>> Graph<I, C> {
                 }
             }
         }
-        let _ = self.output_type()?;
+        Ok(())
+    }
+
+    /// Check everything `check` does except that the initial state takes a unit-type input (`()`),
+    /// since a piece of a larger parser picks up whatever the pieces before it computed.
+    /// # Errors
+    /// When ill-formed (with a witness).
+    #[inline]
+    pub(crate) fn check_fragment(&self) -> Result<(), IllFormed<I, C>> {
+        self.check_allowing_duplicates()?;
         for (i, state) in self.states.iter().enumerate() {
             if get!(self.states, ..i).contains(state) {
                 return Err(IllFormed::DuplicateState(Box::new(state.clone())));
             }
         }
-        NonZeroUsize::new(n_states).map_or(Ok(()), |nz| {
-            self.states.iter().try_fold((), |(), state| state.check(nz))
-        })
+        Ok(())
     }
 
-    /// Run this parser to completion and check types along the way.
+    /// Check everything `check_fragment` does except that no two states are identical,
+    /// since a graph on its way to determinization (e.g. after `>>` stops a few states from accepting)
+    /// can pick up identical states that determinization merges anyway.
     /// # Errors
-    /// If the parser determines there should be an error.
+    /// When ill-formed (with a witness).
     #[inline]
-    #[allow(unsafe_code)]
-    pub fn accept<In: IntoIterator<Item = I>>(
-        &self,
-        input: In,
-    ) -> Result<String, ParseError<I, C>> {
-        use crate::Run;
-        let mut run = input.run(self);
-        for r in &mut run {
-            drop(r?);
+    pub(crate) fn check_allowing_duplicates(&self) -> Result<(), IllFormed<I, C>> {
+        let n_states = self.states.len();
+        if self.initial.view().next().is_none() {
+            return Err(IllFormed::EmptyInitial);
         }
-        for i in run.ctrl.view() {
-            if get!(self.states, i).non_accepting.is_empty() {
-                return Ok(run.output_t);
+        if let Some(i) = self.initial.view().find(|&i| i >= n_states) {
+            return Err(IllFormed::InitialOutOfBounds(i));
+        }
+        let _ = self.output_type()?;
+        self.check_composition()?;
+        for (i, state) in self.states.iter().enumerate() {
+            if let Curry::Scrutinize { ref filter, .. } = state.transitions {
+                if let Some(range) = filter.0.keys().find(|range| range.first > range.last) {
+                    return Err(IllFormed::EmptyRange {
+                        state: i,
+                        range: range.clone(),
+                    });
+                }
             }
         }
-        Err(ParseError::BadInput(InputError::NotAccepting))
+        NonZeroUsize::new(n_states).map_or(Ok(()), |nz| {
+            self.states.iter().try_fold((), |(), state| state.check(nz))
+        })
+    }
+
+    /// Check that every lateral transition hands its destination the accumulator type that state expects.
+    /// This is exactly where two parsers meet after `>>`, so a mismatch there shows up here instead of in generated code.
+    /// # Errors
+    /// If any transition's output doesn't match what its destination expects (or what it gets from elsewhere).
+    #[inline]
+    fn check_composition(&self) -> Result<(), IllFormed<I, C>> {
+        self.accumulator_types().map(drop)
     }
 
     /// Subset construction algorithm for determinizing nondeterministic automata.
     /// # Errors
     /// If there's an ambiguity (which would have crashed the nondeterministic automaton anyway).
     #[inline]
+    pub fn determinize(&self) -> Result<Deterministic<I>, IllFormed<I, C>> {
+        self.determinize_pruned(&C::clone)
+    }
+
+    /// Subset construction, but replacing each subset of states with `prune(subset)` before we ever use it,
+    /// e.g. to drop threads we'd rather not follow any longer.
+    /// `prune` should return a nonempty subset of its argument.
+    /// # Errors
+    /// If there's an ambiguity (which would have crashed the nondeterministic automaton anyway).
+    #[inline]
     #[allow(
         clippy::missing_panics_doc,
         clippy::type_complexity,
         clippy::unwrap_in_result
     )]
-    pub fn determinize(&self) -> Result<Deterministic<I>, IllFormed<I, C>> {
+    pub(crate) fn determinize_pruned<P: Fn(&C) -> C>(
+        &self,
+        prune: &P,
+    ) -> Result<Deterministic<I>, IllFormed<I, C>> {
         // Check that the source graph is well-formed
-        self.check()?;
+        self.check_allowing_duplicates()?;
 
         // Associate each subset of states with a merged state
         let mut subsets_as_states = BTreeMap::new();
-        self.explore(&mut subsets_as_states, &self.initial)?;
+        let initial = prune(&self.initial);
+        self.explore(&mut subsets_as_states, &initial, prune)?;
 
         // Fix an ordering on those subsets
         let ordering: Vec<C> = subsets_as_states.keys().cloned().collect();
         // Don't need to sort--that's guaranteed in `BTreeMap::keys`
 
         let mut output = Deterministic {
-            initial: unwrap!(ordering.binary_search(&self.initial)),
+            initial: unwrap!(ordering.binary_search(&initial)),
             states: ordering
                 .iter()
                 .map(|set| {
@@ -154,19 +579,26 @@ impl<I: Input, C: Ctrl<I>> Graph<I, C> {
                 })
                 .collect(),
         };
+        // Subsets can split one range into adjacent pieces that all end up doing the same thing.
+        for state in &mut output.states {
+            if let Curry::Scrutinize { ref mut filter, .. } = state.transitions {
+                filter.coalesce();
+            }
+        }
         output.sort();
         output
-            .check()
+            .check_fragment()
             .map(|()| output)
             .map_err(IllFormed::convert_ctrl)
     }
 
     /// Associate each subset of states with a merged state.
     #[inline]
-    fn explore(
+    fn explore<P: Fn(&C) -> C>(
         &self,
         subsets_as_states: &mut BTreeMap<C, State<I, C>>,
         subset: &C,
+        prune: &P,
     ) -> Result<(), IllFormed<I, C>> {
         // Check if we've seen this subset already
         let btree_map::Entry::Vacant(entry) = subsets_as_states.entry(subset.clone()) else {
@@ -174,20 +606,26 @@ impl<I: Input, C: Ctrl<I>> Graph<I, C> {
         };
 
         // Merge this subset of states into one (most of the heavy lifting)
-        let mega_state = match try_merge(subset.view().map(|i| Ok(get!(self.states, i).clone()))) {
-            // If no state follows, reject immediately.
-            None => State {
-                transitions: Curry::Scrutinize {
-                    filter: RangeMap(BTreeMap::new()),
-                    fallback: None,
+        let mut mega_state =
+            match try_merge(subset.view().map(|i| Ok(get!(self.states, i).clone()))) {
+                // If no state follows, reject immediately.
+                None => State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(BTreeMap::new()),
+                        fallback: None,
+                    },
+                    non_accepting: iter::once(Rejection::UnexpectedToken).collect(),
                 },
-                non_accepting: iter::once("Unexpected token".to_owned()).collect(),
-            },
-            // If they successfully merged, return the merged state
-            Some(Ok(ok)) => ok,
-            // If they didn't successfully merge, something's wrong with the original automaton
-            Some(Err(e)) => return Err(e),
-        };
+                // If they successfully merged, return the merged state
+                Some(Ok(ok)) => ok,
+                // If they didn't successfully merge, something's wrong with the original automaton
+                Some(Err(e)) => return Err(e),
+            };
+
+        // Refer only to pruned subsets from here on
+        for transition in mega_state.transitions.values_mut() {
+            prune_transition(transition, prune);
+        }
 
         // Necessary before we move `mega_state`
         let all_dsts: BTreeSet<C> = mega_state
@@ -202,100 +640,7 @@ impl<I: Input, C: Ctrl<I>> Graph<I, C> {
         // Recurse on all possible next states
         all_dsts
             .into_iter()
-            .try_fold((), |(), dst| self.explore(subsets_as_states, &dst))
-    }
-
-    /// Compute the output type of any successful run.
-    /// # Errors
-    /// If multiple accepting states attempt to return different types.
-    #[inline]
-    pub fn output_type(&self) -> Result<Option<&str>, IllFormed<I, C>> {
-        self.states
-            .iter()
-            .try_fold(None, |acc: Option<&str>, state| {
-                if state.non_accepting.is_empty() {
-                    acc.map_or_else(
-                        || state.input_type(),
-                        |t| {
-                            if let Some(input_t) = state.input_type()? {
-                                if input_t != t {
-                                    return Err(IllFormed::WrongReturnType(
-                                        t.to_owned(),
-                                        input_t.to_owned(),
-                                    ));
-                                }
-                            }
-                            Ok(Some(t))
-                        },
-                    )
-                } else {
-                    Ok(acc)
-                }
-            })
-    }
-
-    /// Compute the input type of any successful run.
-    /// # Errors
-    /// If multiple accepting states attempt to return different types.
-    #[inline]
-    #[allow(clippy::missing_panics_doc)]
-    pub fn input_type(&self) -> Result<Option<&str>, IllFormed<I, C>> {
-        self.initial
-            .view()
-            .map(|i| get!(self.states, i))
-            .try_fold(None, |acc, state| {
-                let shit = acc.merge(state.transitions.values().try_fold(None, |accc, t| {
-                    accc.merge(t.input_type()).map_or_else(
-                        |(a, b)| {
-                            if a == b {
-                                Ok(Some(a))
-                            } else {
-                                Err(IllFormed::TypeMismatch(a.to_owned(), b.to_owned()))
-                            }
-                        },
-                        Ok,
-                    )
-                })?);
-                shit.map_or_else(
-                    |(a, b)| {
-                        if a == b {
-                            Ok(Some(a))
-                        } else {
-                            Err(IllFormed::TypeMismatch(a.to_owned(), b.to_owned()))
-                        }
-                    },
-                    Ok,
-                )
-            })
-    }
-
-    /// Change nothing about the semantics but sort the internal vector of states.
-    #[inline]
-    #[allow(clippy::missing_panics_doc)]
-    pub fn sort(&mut self) {
-        // Associate each original index with a concrete state instead of just an index,
-        // since we're going to be swapping the indices around.
-        let index_map: BTreeMap<usize, State<_, _>> =
-            self.states.iter().cloned().enumerate().collect();
-        self.states.sort_unstable();
-        self.states.dedup(); // <-- Cool that we can do this!
-        self.initial = self
-            .initial
-            .clone()
-            .map_indices(|i| unwrap!(self.states.binary_search(unwrap!(index_map.get(&i)))));
-        // Can't do this in-place since the entire state array is required as an argument.
-        self.states = self
-            .states
-            .iter()
-            .map(|s| s.reindex(&self.states, &index_map))
-            .collect();
-    }
-
-    /// Check if this parser ever could, at any point, involve a fallback transition.
-    #[inline]
-    #[must_use]
-    pub fn involves_any_fallback(&self) -> bool {
-        self.states.iter().any(State::involves_any_fallback)
+            .try_fold((), |(), dst| self.explore(subsets_as_states, &dst, prune))
     }
 
     /// Kleene-star operation: accept any number (including zero!) of repetitions of this parser.
@@ -303,6 +648,14 @@ impl<I: Input, C: Ctrl<I>> Graph<I, C> {
     #[must_use]
     #[allow(clippy::panic, clippy::missing_panics_doc)]
     pub fn star(self) -> Deterministic<I> {
+        self.try_star().unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Kleene star exactly as `star`, but returning any conflict between repetitions instead of panicking.
+    /// # Errors
+    /// If one repetition would act differently from the next on the same input (e.g. call different functions).
+    #[inline]
+    pub(crate) fn try_star(self) -> Result<Deterministic<I>, IllFormed<I, BTreeSet<usize>>> {
         let mut s = self.generalize();
         let accepting: BTreeSet<usize> = s
             .states
@@ -335,7 +688,29 @@ impl<I: Input, C: Ctrl<I>> Graph<I, C> {
             }],
             initial: 0,
         };
-        empty | s.determinize().unwrap_or_else(|e| panic!("{e}"))
+        let repeated = s.determinize()?;
+        if repeated.accepts_empty() {
+            Ok(repeated)
+        } else {
+            empty.try_union(repeated)
+        }
+    }
+}
+
+/// Replace each subset of states this transition could visit with `prune(subset)`.
+#[inline]
+fn prune_transition<I: Input, C: Ctrl<I>, P: Fn(&C) -> C>(value: &mut Transition<I, C>, prune: &P) {
+    match *value {
+        Transition::Lateral { ref mut dst, .. } => *dst = prune(dst),
+        Transition::Call {
+            ref mut detour,
+            ref mut dst,
+            ..
+        } => {
+            *detour = prune(detour);
+            prune_transition(dst, prune);
+        }
+        Transition::Return { .. } => {}
     }
 }
 
@@ -395,7 +770,38 @@ fn fix_indices_transition<I: Input, C: Ctrl<I>>(
     }
 }
 
-impl<I: Input> Graph<I, usize> {
+impl<I: Step> Graph<I, usize> {
+    /// Instead of stopping on a token without any transition, move to a single absorbing state that never accepts,
+    /// so a run that's already doomed to reject still has a state to point to.
+    /// Doesn't change any existing transition (so updates and output types are untouched).
+    #[inline]
+    #[must_use]
+    pub fn sink_on_reject(mut self) -> Self {
+        let sink = self.states.len();
+        let to_sink = Transition::Lateral {
+            dst: sink,
+            update: None,
+        };
+        for state in &mut self.states {
+            if let Curry::Scrutinize {
+                ref filter,
+                ref mut fallback,
+            } = state.transitions
+            {
+                if fallback.is_none() && !filter.covers_everything() {
+                    *fallback = Some(to_sink.clone());
+                }
+            }
+        }
+        self.states.push(State {
+            transitions: Curry::Wildcard(to_sink),
+            non_accepting: iter::once(Rejection::UnexpectedToken).collect(),
+        });
+        let mut out = self.trim();
+        out.sort();
+        out
+    }
+
     /// Write this parser as a Rust source file.
     /// # Errors
     /// If file creation or formatting fails.
@@ -409,4 +815,97 @@ impl<I: Input> Graph<I, usize> {
             Command::new("rustfmt").arg(path).output().map(|_| {})
         })
     }
+
+    /// Write a minimal standalone crate around this parser (e.g. to reproduce a bug):
+    /// `Cargo.toml`, `src/parser.rs` (as in `to_file`), and `src/main.rs`
+    /// with one test per accepting state asserting that the generated parser accepts its example from `acceptance_examples`.
+    /// Generated parsers don't read any configuration, so there's no `src/config.rs` to write alongside them.
+    /// # Errors
+    /// If this automaton is ill-formed or if file creation or formatting fails.
+    #[inline]
+    pub fn write_example_crate(
+        &self,
+        dir: &Path,
+        name: &str,
+    ) -> Result<io::Result<()>, IllFormed<I, usize>> {
+        let examples = self.acceptance_examples();
+        let test = if examples.is_empty() {
+            String::new()
+        } else {
+            let cases = examples.into_iter().fold(String::new(), |acc, (i, input)| {
+                format!(
+                    "{acc}
+
+    #[test]
+    fn accepts_state_{i}() {{
+        assert!(parser::parse({}).is_ok());
+    }}",
+                    input.to_src(),
+                )
+            });
+            format!(
+                "
+
+#[cfg(test)]
+mod test {{
+    use super::*;{cases}
+}}"
+            )
+        };
+        let src = dir.join("src");
+        if let Err(e) = fs::create_dir_all(&src) {
+            return Ok(Err(e));
+        }
+        Ok(self.to_file(src.join("parser.rs"))?.and_then(|()| {
+            fs::write(
+                dir.join("Cargo.toml"),
+                format!(
+                    r#"[package]
+name = "{name}"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+"#,
+                ),
+            )?;
+            fs::write(
+                src.join("main.rs"),
+                format!(
+                    "//! Automatically generated with [inator](https://crates.io/crates/inator).
+
+#![allow(unreachable_code, unused_variables)]
+
+mod parser;
+
+fn main() {{}}{test}
+",
+                ),
+            )
+        }))
+    }
+}
+
+impl<C: Ctrl<char>> Graph<char, C> {
+    /// Run this parser to completion on the characters of a string.
+    /// # Errors
+    /// If the parser determines there should be an error.
+    #[inline]
+    pub fn accept_str(&self, input: &str) -> Result<String, ParseError<char, C>> {
+        self.accept(input.chars())
+    }
+
+    /// Run this parser to completion, folding each input character to lowercase before matching,
+    /// so a parser written only for lowercase input accepts any case without growing any larger.
+    /// Folding is ASCII-only (`char::to_ascii_lowercase`): non-ASCII characters pass through untouched,
+    /// since full Unicode lowercasing can turn one character into several.
+    /// # Errors
+    /// If the parser determines there should be an error.
+    #[inline]
+    pub fn accept_case_insensitive<In: IntoIterator<Item = char>>(
+        &self,
+        input: In,
+    ) -> Result<String, ParseError<char, C>> {
+        self.accept(input.into_iter().map(|c| c.to_ascii_lowercase()))
+    }
 }
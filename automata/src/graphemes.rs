@@ -0,0 +1,26 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Treat each grapheme cluster (i.e. each user-perceived character) as a single token.
+
+use crate::{Ctrl, Graph, ParseError};
+use unicode_segmentation::UnicodeSegmentation;
+
+impl<C: Ctrl<char>> Graph<char, C> {
+    /// Run this parser to completion on the grapheme clusters of a string instead of its characters,
+    /// feeding the parser only the first character of each cluster (e.g. `e` for `e` followed by a combining acute accent),
+    /// so combining marks, emoji modifiers, and the like never show up as tokens of their own.
+    /// # Errors
+    /// If the parser determines there should be an error.
+    #[inline]
+    pub fn accept_graphemes(&self, input: &str) -> Result<String, ParseError<char, C>> {
+        self.accept(
+            input
+                .graphemes(true)
+                .filter_map(|cluster| cluster.chars().next()),
+        )
+    }
+}
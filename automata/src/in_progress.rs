@@ -6,8 +6,9 @@
 
 //! Execute an automaton on an input sequence.
 
-use crate::{try_merge, Ctrl, Graph, IllFormed, Input, ToSrc, Transition};
-use core::fmt;
+use crate::{try_merge, Ctrl, Curry, Graph, IllFormed, Input, Range, ToSrc, Transition};
+use core::{error, fmt};
+use std::collections::BTreeSet;
 
 /// Execute an automaton on an input sequence.
 #[non_exhaustive]
@@ -22,6 +23,8 @@ pub struct InProgress<'graph, I: Input, C: Ctrl<I>, In: Iterator<Item = I>> {
     pub ctrl: C,
     /// Output type as we go.
     pub output_t: String,
+    /// Number of tokens consumed so far.
+    pub consumed: usize,
 }
 
 impl<I: Input, C: Ctrl<I>, In: Iterator<Item = I>> fmt::Debug for InProgress<'_, I, C, In> {
@@ -38,6 +41,55 @@ impl<I: Input, C: Ctrl<I>, In: Iterator<Item = I>> fmt::Debug for InProgress<'_,
     }
 }
 
+/// How far a budgeted run got (see `InProgress::step_budget`).
+#[allow(clippy::exhaustive_enums)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Progress<I: Input, C: Ctrl<I>> {
+    /// Input ran out (or the parser stopped) within the budget: exactly what `accept` would have returned.
+    Done(Result<String, ParseError<I, C>>),
+    /// Used up the budget with input left over: call again to pick up where we left off.
+    Pending,
+}
+
+impl<I: Input, C: Ctrl<I>, In: Iterator<Item = I>> InProgress<'_, I, C, In> {
+    /// Consume at most `budget` tokens, then pause (e.g. to let a cooperative scheduler interleave other work).
+    /// Running a parse in any number of budgeted steps gives the same result as one call to `accept`.
+    #[inline]
+    pub fn step_budget(&mut self, budget: usize) -> Progress<I, C> {
+        for _ in 0..budget {
+            match self.next() {
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Progress::Done(Err(e)),
+                None => return Progress::Done(Ok(self.output_t.clone())),
+            }
+        }
+        Progress::Pending
+    }
+
+    /// Consume the rest of the input, returning exactly what `accept` would have returned.
+    /// # Errors
+    /// If the parser determines there should be an error (leaving it where it stopped).
+    #[inline]
+    pub(crate) fn finish(&mut self) -> Result<String, ParseError<I, C>> {
+        for token in &mut *self {
+            drop(token?);
+        }
+        // Input only runs out without an error once the parser accepts.
+        Ok(self.output_t.clone())
+    }
+
+    /// The error we'd report if input ended right now without the parser accepting.
+    #[inline]
+    pub(crate) fn ran_out(&self) -> ParseError<I, C> {
+        ran_out(
+            self.graph,
+            &self.ctrl,
+            &open_regions(&self.stack),
+            self.consumed,
+        )
+    }
+}
+
 /// Input intentionally rejected by a parser without anything going wrong internally.
 #[non_exhaustive]
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -58,10 +110,133 @@ pub enum InputError {
 pub enum ParseError<I: Input, C: Ctrl<I>> {
     /// Input intentionally rejected by a parser without anything going wrong internally.
     BadInput(InputError),
+    /// Input ended before the parser could accept it, but more input could still fix that
+    /// (e.g. a streaming caller should wait for more data instead of giving up).
+    Incomplete {
+        /// Tokens that could come next (empty if any token at all would do).
+        expected: Vec<Range<I>>,
+        /// Number of tokens consumed before input ran out.
+        consumed: usize,
+        /// Regions still open, outermost first.
+        open: Vec<&'static str>,
+    },
     /// Parser was broken.
     BadParser(IllFormed<I, C>),
 }
 
+impl fmt::Display for InputError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::NotAccepting => write!(f, "Input ended in a state that doesn't accept"),
+            Self::Unclosed => write!(f, "Input ended with a region still open"),
+            Self::Unopened => write!(f, "Tried to close a region that was never opened"),
+            Self::Absurd => write!(f, "Unexpected token"),
+        }
+    }
+}
+
+impl error::Error for InputError {}
+
+impl<I: Input, C: Ctrl<I>> fmt::Display for ParseError<I, C> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::BadInput(ref e) => write!(f, "{e}"),
+            Self::Incomplete {
+                ref expected,
+                consumed,
+                ..
+            } => {
+                write!(f, "Input ended after {consumed} tokens")?;
+                if !expected.is_empty() {
+                    write!(
+                        f,
+                        ", but expected a token on {}",
+                        expected
+                            .iter()
+                            .map(ToSrc::to_src)
+                            .collect::<Vec<_>>()
+                            .join(" or "),
+                    )?;
+                }
+                Ok(())
+            }
+            Self::BadParser(ref e) => write!(f, "Parser was broken: {e}"),
+        }
+    }
+}
+
+impl<I: Input + fmt::Debug, C: Ctrl<I> + fmt::Debug> error::Error for ParseError<I, C> {}
+
+/// The error to report when input ends in a configuration that doesn't accept:
+/// `Incomplete` if more input could still lead to an accepting state, or `BadInput` if no input ever could.
+/// Takes each open region (outermost first) with the transition to take once it closes.
+#[inline]
+pub(crate) fn ran_out<I: Input, C: Ctrl<I>>(
+    graph: &Graph<I, C>,
+    ctrl: &C,
+    open: &[(&'static str, &Transition<I, C>)],
+    consumed: usize,
+) -> ParseError<I, C> {
+    let resume = open
+        .iter()
+        .flat_map(|&(_, dst)| dst.dsts())
+        .flat_map(Ctrl::view);
+    let mut expected = BTreeSet::new();
+    let mut anything = false;
+    for state in ctrl.view().filter_map(|i| graph.states.get(i)) {
+        match state.transitions {
+            Curry::Wildcard(..) => anything = true,
+            Curry::Scrutinize {
+                ref filter,
+                ref fallback,
+            } => {
+                anything |= fallback.is_some();
+                expected.extend(filter.0.keys().cloned());
+            }
+        }
+    }
+    if (!anything && expected.is_empty())
+        || !graph
+            .reachable_from(ctrl.view().chain(resume))
+            .into_iter()
+            .any(|i| {
+                graph
+                    .states
+                    .get(i)
+                    .is_some_and(|s| s.non_accepting.is_empty())
+            })
+    {
+        return ParseError::BadInput(if open.is_empty() {
+            InputError::NotAccepting
+        } else {
+            InputError::Unclosed
+        });
+    }
+    if anything {
+        expected.clear();
+    }
+    ParseError::Incomplete {
+        // Merge ranges that overlap, e.g. `'0'..='5'` and `'4'..='9'`.
+        expected: expected
+            .into_iter()
+            .fold(vec![], |mut acc: Vec<Range<I>>, range| {
+                match acc.last_mut() {
+                    Some(last) if range.first <= last.last => {
+                        if range.last > last.last {
+                            last.last = range.last;
+                        }
+                    }
+                    _ => acc.push(range),
+                }
+                acc
+            }),
+        consumed,
+        open: open.iter().map(|&(region, _)| region).collect(),
+    }
+}
+
 impl<I: Input, C: Ctrl<I>, In: Iterator<Item = I>> Iterator for InProgress<'_, I, C, In> {
     type Item = Result<I, ParseError<I, C>>;
     #[inline]
@@ -73,25 +248,44 @@ impl<I: Input, C: Ctrl<I>, In: Iterator<Item = I>> Iterator for InProgress<'_, I
             maybe_token.clone(),
             &mut self.stack,
             &self.output_t,
+            self.consumed,
         ) {
             Ok(ok) => ok,
             Err(e) => return Some(Err(e)),
         };
         self.output_t = o;
         self.ctrl = c?;
+        self.consumed = self.consumed.saturating_add(1);
         maybe_token.map(Ok) // <-- Propagate the iterator's input
     }
 }
 
+/// Each region a stack of calls has open (outermost first) with the transition to take once it closes.
+#[inline]
+fn open_regions<I: Input, C: Ctrl<I>>(
+    stack: &[Transition<I, C>],
+) -> Vec<(&'static str, &Transition<I, C>)> {
+    stack
+        .iter()
+        .filter_map(|t| match *t {
+            Transition::Call {
+                region, ref dst, ..
+            } => Some((region, &**dst)),
+            Transition::Lateral { .. } | Transition::Return { .. } => None,
+        })
+        .collect()
+}
+
 /// Act on the automaton graph in response to one input token.
 #[inline]
 #[allow(clippy::type_complexity)]
-fn step<I: Input, C: Ctrl<I>>(
+pub(crate) fn step<I: Input, C: Ctrl<I>>(
     graph: &Graph<I, C>,
     ctrl: &C,
     maybe_token: Option<I>,
     stack: &mut Vec<Transition<I, C>>,
     output_t: &str,
+    consumed: usize,
 ) -> Result<(Option<C>, String), ParseError<I, C>> {
     ctrl.view().try_fold((), |(), i| {
         if graph.states.get(i).is_none() {
@@ -102,14 +296,10 @@ fn step<I: Input, C: Ctrl<I>>(
     })?;
     let mut states = ctrl.view().map(|i| get!(graph.states, i));
     let Some(token) = maybe_token else {
-        return if stack.is_empty() {
-            if states.any(|s| s.non_accepting.is_empty()) {
-                Ok((None, output_t.to_owned()))
-            } else {
-                Err(ParseError::BadInput(InputError::NotAccepting))
-            }
+        return if stack.is_empty() && states.any(|s| s.non_accepting.is_empty()) {
+            Ok((None, output_t.to_owned()))
         } else {
-            Err(ParseError::BadInput(InputError::Unclosed))
+            Err(ran_out(graph, ctrl, &open_regions(stack), consumed))
         };
     };
 
@@ -4,11 +4,160 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-//! Typing convenience: trait satisfying everything required for an input token.
+//! Typing convenience: trait satisfying everything required for an input token,
+//! plus (opt-in) how to step from one token to the next.
 
 use crate::ToSrc;
+use std::collections::BTreeSet;
 
 /// Typing convenience: trait satisfying everything required for an input token.
 pub trait Input: Clone + Ord + ToSrc {}
 
 impl<I: Clone + Ord + ToSrc> Input for I {}
+
+/// How to step from one token to the next, e.g. to split a range of tokens
+/// (to combine or intersect two parsers that partition tokens differently)
+/// or to tell whether a set of ranges covers every token.
+///
+/// Implement this for your own token type with an empty `impl Step for MyToken {}` if you can't step between tokens:
+/// we then never split a range of your tokens and never assume a set of ranges covers every token.
+pub trait Step: Input {
+    /// The value immediately after this one, if there is one and we know it
+    /// (i.e. `None` if this is the greatest value, if infinitely many values lie just above it, or if we can't tell).
+    /// Used to split ranges of tokens.
+    #[inline(always)]
+    #[must_use]
+    fn succ(&self) -> Option<Self> {
+        None
+    }
+    /// The value immediately before this one, if there is one and we know it
+    /// (i.e. `None` if this is the least value, if infinitely many values lie just below it, or if we can't tell).
+    /// Used to split ranges of tokens.
+    #[inline(always)]
+    #[must_use]
+    fn pred(&self) -> Option<Self> {
+        None
+    }
+    /// The least value of this type, if there is one and we know it.
+    /// Used to build example inputs for parsers that accept any token whatsoever.
+    #[inline(always)]
+    #[must_use]
+    fn least() -> Option<Self> {
+        None
+    }
+}
+
+impl Step for () {
+    #[inline(always)]
+    fn least() -> Option<Self> {
+        Some(())
+    }
+}
+
+impl Step for u8 {
+    #[inline(always)]
+    fn succ(&self) -> Option<Self> {
+        self.checked_add(1)
+    }
+    #[inline(always)]
+    fn pred(&self) -> Option<Self> {
+        self.checked_sub(1)
+    }
+    #[inline(always)]
+    fn least() -> Option<Self> {
+        Some(0)
+    }
+}
+
+impl Step for usize {
+    #[inline(always)]
+    fn succ(&self) -> Option<Self> {
+        self.checked_add(1)
+    }
+    #[inline(always)]
+    fn pred(&self) -> Option<Self> {
+        self.checked_sub(1)
+    }
+    #[inline(always)]
+    fn least() -> Option<Self> {
+        Some(0)
+    }
+}
+
+impl Step for char {
+    #[inline]
+    fn succ(&self) -> Option<Self> {
+        // Skip UTF-16 surrogates, which aren't valid `char`s.
+        match *self {
+            '\u{D7FF}' => Some('\u{E000}'),
+            c => char::from_u32(u32::from(c).checked_add(1)?),
+        }
+    }
+    #[inline]
+    fn pred(&self) -> Option<Self> {
+        match *self {
+            '\u{E000}' => Some('\u{D7FF}'),
+            c => char::from_u32(u32::from(c).checked_sub(1)?),
+        }
+    }
+    #[inline(always)]
+    fn least() -> Option<Self> {
+        Some('\0')
+    }
+}
+
+impl Step for String {
+    #[inline]
+    fn succ(&self) -> Option<Self> {
+        // The least string greater than `s` is `s` followed by the least character.
+        let mut s = self.clone();
+        s.push('\0');
+        Some(s)
+    }
+    #[inline]
+    fn pred(&self) -> Option<Self> {
+        // Only strings ending in the least character have an immediate predecessor.
+        self.strip_suffix('\0').map(str::to_owned)
+    }
+    #[inline(always)]
+    fn least() -> Option<Self> {
+        Some(Self::new())
+    }
+}
+
+impl Step for &str {
+    #[inline(always)]
+    fn least() -> Option<Self> {
+        Some("")
+    }
+}
+
+impl<T: Input> Step for Option<T> {
+    #[inline(always)]
+    fn least() -> Option<Self> {
+        Some(None)
+    }
+}
+
+impl<T: Step, E: Input> Step for Result<T, E> {
+    #[inline(always)]
+    fn least() -> Option<Self> {
+        T::least().map(Ok)
+    }
+}
+
+impl<A: Input, B: Input> Step for (A, B) {}
+
+impl<T: Input> Step for Vec<T> {
+    #[inline(always)]
+    fn least() -> Option<Self> {
+        Some(vec![])
+    }
+}
+
+impl<T: Input> Step for BTreeSet<T> {
+    #[inline(always)]
+    fn least() -> Option<Self> {
+        Some(Self::new())
+    }
+}
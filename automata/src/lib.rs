@@ -97,6 +97,15 @@ macro_rules! update {
     };
 }
 
+/// Call a function on each token's payload (see `Deterministic::accept_with_payload`)
+/// that will also be available to the compiled parser.
+#[macro_export]
+macro_rules! update_with_payload {
+    ($ex:expr) => {
+        $crate::Update::_payload_macro(stringify!($ex), $ex)
+    };
+}
+
 /// Unwrap if we're debugging but `unwrap_unchecked` if we're not.
 #[cfg(any(debug_assertions, test))]
 macro_rules! unwrap {
@@ -170,47 +179,91 @@ macro_rules! ff {
     };
 }
 
+mod accept_all;
+mod accept_mode;
+mod analysis;
+mod assert;
+mod audit;
+mod capabilities;
 mod check;
+mod collect;
 mod combinators;
+mod cow;
 mod ctrl;
 mod curry;
+mod describe;
+mod description;
+mod ebnf;
+mod eof;
+mod evaluate;
 mod f;
 mod generalize;
 mod graph;
 mod in_progress;
 mod input;
+mod lines;
 mod map_indices;
 mod merge;
+mod minimize;
+mod product;
 mod range;
 mod range_map;
+mod recover;
+mod region;
 mod reindex;
+mod rejection;
 mod run;
+mod search;
+mod skip;
+mod spans;
 mod state;
+mod stats;
 mod to_src;
 mod transition;
+mod typed;
 mod update;
+mod walk;
+
+#[cfg(feature = "miette")]
+mod diagnostic;
 
 #[cfg(feature = "quickcheck")]
 mod qc;
 
+#[cfg(feature = "unicode")]
+mod graphemes;
+
 pub use {
+    accept_mode::{AcceptMode, AcceptOutcome},
+    audit::DeterminizationMismatch,
+    capabilities::Capabilities,
     check::{Check, IllFormed},
     ctrl::Ctrl,
     curry::Curry,
-    f::{F, FF},
+    description::{DescriptionError, Position},
+    ebnf::EbnfError,
+    f::{CombineFn, Dynamic, Erased, PayloadFn, UpdateFn, F, FF},
     graph::{Deterministic, Graph, Nondeterministic},
-    in_progress::{InProgress, InputError, ParseError},
-    input::Input,
+    in_progress::{InProgress, InputError, ParseError, Progress},
+    input::{Input, Step},
+    lines::LineError,
     merge::{merge, try_merge, Merge},
+    product::Counterexample,
     range::Range,
     range_map::RangeMap,
+    rejection::Rejection,
     run::Run,
     state::State,
-    to_src::ToSrc,
+    stats::RunStats,
+    to_src::{CodegenOptions, StateDocs, ToSrc},
     transition::Transition,
+    typed::Typed,
     update::Update,
 };
 
+#[cfg(feature = "miette")]
+pub use diagnostic::ParseReport;
+
 #[cfg(test)]
 mod test;
 
@@ -0,0 +1,48 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Run a parser separately on each line of a line-oriented format.
+
+use crate::{Ctrl, Graph, ParseError};
+use core::{error, fmt};
+
+/// Error on one line of input, with the line it happened on.
+#[allow(clippy::exhaustive_structs)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LineError<C: Ctrl<char>> {
+    /// Line, counting from 1.
+    pub line: usize,
+    /// What went wrong on that line.
+    pub error: ParseError<char, C>,
+}
+
+impl<C: Ctrl<char>> fmt::Display for LineError<C> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.error)
+    }
+}
+
+impl<C: Ctrl<char> + fmt::Debug> error::Error for LineError<C> {}
+
+impl<C: Ctrl<char>> Graph<char, C> {
+    /// Run this parser to completion on each line of `text` separately, as `accept_str` would,
+    /// returning one result per line in order, each error tagged with its line number (counting from 1).
+    /// Lines split exactly as in `str::lines`: each ends at `\n` or `\r\n` (neither of which the parser sees),
+    /// and a newline at the very end of `text` doesn't start another (empty) line.
+    #[inline]
+    pub fn parse_lines(&self, text: &str) -> Vec<Result<String, LineError<C>>> {
+        text.lines()
+            .zip(1..)
+            .map(|(line, number)| {
+                self.accept_str(line).map_err(|error| LineError {
+                    line: number,
+                    error,
+                })
+            })
+            .collect()
+    }
+}
@@ -6,7 +6,7 @@
 
 //! Trait to fallibly combine multiple values into one value with identical semantics.
 
-use crate::{Ctrl, Curry, IllFormed, Input, RangeMap, State, Transition, Update, FF};
+use crate::{Ctrl, Curry, IllFormed, Input, RangeMap, State, Step, Transition, Update, FF};
 use core::convert::Infallible;
 use std::collections::{btree_map::Entry, BTreeMap, BTreeSet};
 
@@ -107,7 +107,7 @@ impl<K: Clone + Ord, V: Merge> Merge for BTreeMap<K, V> {
     }
 }
 
-impl<I: Input, C: Ctrl<I>> Merge for State<I, C> {
+impl<I: Step, C: Ctrl<I>> Merge for State<I, C> {
     type Error = IllFormed<I, C>;
     #[inline]
     #[allow(clippy::unwrap_in_result)]
@@ -133,22 +133,30 @@ impl<T> Merge for Vec<T> {
     }
 }
 
-impl<I: Input, C: Ctrl<I>> Merge for Curry<I, C> {
+impl<I: Step, C: Ctrl<I>> Merge for Curry<I, C> {
     type Error = IllFormed<I, C>;
     #[inline]
     fn merge(self, other: Self) -> Result<Self, Self::Error> {
         match (self, other) {
             (Self::Wildcard(lhs), Self::Wildcard(rhs)) => Ok(Self::Wildcard(lhs.merge(rhs)?)),
-            (Self::Wildcard(w), Self::Scrutinize { filter, .. })
-            | (Self::Scrutinize { filter, .. }, Self::Wildcard(w)) => {
-                match filter.0.first_key_value() {
-                    None => Ok(Self::Wildcard(w)),
-                    Some((k, v)) => Err(IllFormed::WildcardMask {
-                        arg_token: Some(k.clone()),
-                        possibility_1: Box::new(w),
-                        possibility_2: Box::new(v.clone()),
-                    }),
+            // A wildcard applies to every token, so it merges into each explicit range and into the fallback.
+            (Self::Wildcard(w), Self::Scrutinize { filter, fallback })
+            | (Self::Scrutinize { filter, fallback }, Self::Wildcard(w)) => {
+                let merged = fallback.map_or_else(|| Ok(w.clone()), |f| f.merge(w.clone()))?;
+                if filter.0.is_empty() {
+                    return Ok(Self::Wildcard(merged));
                 }
+                Ok(Self::Scrutinize {
+                    filter: RangeMap(
+                        filter
+                            .0
+                            .into_iter()
+                            .map(|(k, v)| v.merge(w.clone()).map(|t| (k, t)))
+                            .collect::<Result<_, _>>()?,
+                    ),
+                    fallback: Some(merged),
+                }
+                .prune_fallback())
             }
             (
                 Self::Scrutinize {
@@ -160,20 +168,27 @@ impl<I: Input, C: Ctrl<I>> Merge for Curry<I, C> {
                     fallback: r_fallback,
                 },
             ) => Ok(Self::Scrutinize {
-                filter: l_filter.merge(r_filter)?,
+                // A range on one side still has to take the other side's fallback into account.
+                filter: l_filter.merge_with_fallbacks(
+                    &r_filter,
+                    l_fallback.as_ref(),
+                    r_fallback.as_ref(),
+                    Merge::merge,
+                )?,
                 fallback: l_fallback
                     .merge(r_fallback)
                     .map_or_else(|(a, b)| a.merge(b).map(Some), Ok)?,
-            }),
+            }
+            .prune_fallback()),
         }
     }
 }
 
-impl<I: Input, C: Ctrl<I>> Merge for RangeMap<I, C> {
+impl<I: Step, C: Ctrl<I>> Merge for RangeMap<I, C> {
     type Error = IllFormed<I, C>;
     #[inline]
     fn merge(self, other: Self) -> Result<Self, Self::Error> {
-        Ok(Self(self.0.merge(other.0).map_err(|(_, e)| e)?))
+        self.merge_with(&other, Merge::merge)
     }
 }
 
@@ -0,0 +1,69 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Merge states that no input could ever tell apart.
+
+use crate::{Curry, Deterministic, Input, Rejection};
+use std::collections::{BTreeMap, BTreeSet};
+
+impl<I: Input> Deterministic<I> {
+    /// Merge every pair of states that no input could ever tell apart,
+    /// then remove any state that no input could ever reach.
+    /// Two states are indistinguishable if they accept (or reject) the same way
+    /// and every transition does the same thing and goes to indistinguishable states.
+    #[inline]
+    #[must_use]
+    pub fn minimize(&self) -> Self {
+        // Start by distinguishing only on acceptance, then refine until nothing changes.
+        let mut classes: Vec<usize> = {
+            let mut ids: BTreeMap<&BTreeSet<Rejection<I>>, usize> = BTreeMap::new();
+            self.states
+                .iter()
+                .map(|state| {
+                    let n = ids.len();
+                    *ids.entry(&state.non_accepting).or_insert(n)
+                })
+                .collect()
+        };
+        let mut n_classes = classes.iter().collect::<BTreeSet<_>>().len();
+        loop {
+            let mut ids: BTreeMap<(usize, Curry<I, usize>), usize> = BTreeMap::new();
+            let refined: Vec<usize> = self
+                .states
+                .iter()
+                .zip(&classes)
+                .map(|(state, &class)| {
+                    let signature = (
+                        class,
+                        state.transitions.clone().map_indices(|i| *get!(classes, i)),
+                    );
+                    let n = ids.len();
+                    *ids.entry(signature).or_insert(n)
+                })
+                .collect();
+            classes = refined;
+            if ids.len() == n_classes {
+                break;
+            }
+            n_classes = ids.len();
+        }
+        // One representative per class (any will do, since they're indistinguishable).
+        let mut representatives = BTreeMap::new();
+        for (state, &class) in self.states.iter().zip(&classes) {
+            let _ = representatives.entry(class).or_insert(state);
+        }
+        let mut out = Self {
+            states: representatives
+                .into_values()
+                .map(|state| state.clone().map_indices(|i| *get!(classes, i)))
+                .collect(),
+            initial: *get!(classes, self.initial),
+        }
+        .trim();
+        out.sort();
+        out
+    }
+}
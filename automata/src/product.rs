@@ -0,0 +1,332 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Run two parsers in lockstep and decide acceptance from both at once.
+
+use crate::{
+    Curry, Deterministic, Graph, IllFormed, Input, RangeMap, Rejection, State, Step, ToSrc,
+    Transition, Update,
+};
+use core::{fmt, iter};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Why one parser's language isn't a subset of another's (or why we couldn't tell).
+#[non_exhaustive]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Counterexample<I: Input> {
+    /// Couldn't run the two parsers in lockstep.
+    IllFormed(IllFormed<I, usize>),
+    /// An input the first parser accepts but the second rejects.
+    Accepted(Vec<I>),
+}
+
+impl<I: Input> fmt::Display for Counterexample<I> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::IllFormed(ref e) => write!(f, "Couldn't compare parsers: {e}"),
+            Self::Accepted(ref input) => write!(
+                f,
+                "The first parser accepts {} but the second rejects it",
+                input.to_src(),
+            ),
+        }
+    }
+}
+
+/// A state in each parser, where `None` means that parser has already rejected.
+type Pair = (Option<usize>, Option<usize>);
+
+impl<I: Step> Deterministic<I> {
+    /// Run both parsers in lockstep on the same input and decide whether to accept
+    /// from whether each parser would have accepted (`false` once a parser has rejected).
+    /// The result only decides acceptance: it does not compute any output (i.e. it always returns `()`).
+    /// # Errors
+    /// If either parser opens or closes a region,
+    /// or if the two split their input tokens at a token with no immediate predecessor.
+    #[inline]
+    pub fn product_with<F: Fn(bool, bool) -> bool>(
+        &self,
+        other: &Self,
+        accept: F,
+    ) -> Result<Self, IllFormed<I, usize>> {
+        self.explore_pairs(
+            other,
+            |lhs_t, rhs_t| {
+                Ok(Some((
+                    (
+                        lhs_t.and_then(Self::lateral_dst),
+                        rhs_t.and_then(Self::lateral_dst),
+                    ),
+                    None,
+                )))
+            },
+            |(lhs, rhs)| {
+                if accept(self.accepting(lhs), other.accepting(rhs)) {
+                    BTreeSet::new()
+                } else {
+                    iter::once(Rejection::Product).collect()
+                }
+            },
+        )
+    }
+
+    /// Ordered choice: accept whatever either parser accepts, but wherever both accept the same input, take this parser's output.
+    /// Both run in lockstep for as long as they call the same updates.
+    /// Once they would call different updates on the same token, this one's update wins and the other stops,
+    /// which is only safe if this one accepts everything the other still could from there
+    /// (otherwise the other would have to continue from an output it never computed).
+    /// # Errors
+    /// If the two call different updates on some token after which the other parser could still accept an input this one rejects,
+    /// if either parser opens or closes a region,
+    /// or if the two split their input tokens at a token with no immediate predecessor.
+    #[inline]
+    pub fn ordered_union(&self, other: &Self) -> Result<Self, IllFormed<I, usize>> {
+        self.explore_pairs(
+            other,
+            |lhs_t, rhs_t| match (lhs_t, rhs_t) {
+                (None, None) => Ok(None),
+                (None, Some(r)) => Ok(Some(((None, Self::lateral_dst(r)), r.update().cloned()))),
+                (Some(l), None) => Ok(Some(((Self::lateral_dst(l), None), l.update().cloned()))),
+                (Some(l), Some(r)) => {
+                    let lhs_dst = Self::lateral_dst(l);
+                    let rhs_dst = Self::lateral_dst(r);
+                    if l.update() == r.update() {
+                        return Ok(Some(((lhs_dst, rhs_dst), l.update().cloned())));
+                    }
+                    if let (Some(lhs_i), Some(rhs_i)) = (lhs_dst, rhs_dst) {
+                        match other
+                            .starting_at(rhs_i)
+                            .is_subset_of(&self.starting_at(lhs_i))
+                        {
+                            Ok(()) => {}
+                            Err(Counterexample::IllFormed(e)) => return Err(e),
+                            Err(Counterexample::Accepted(_)) => {
+                                return Err(IllFormed::IncompatibleActions(
+                                    Box::new(l.clone()),
+                                    Box::new(r.clone()),
+                                ))
+                            }
+                        }
+                    }
+                    Ok(Some(((lhs_dst, None), l.update().cloned())))
+                }
+            },
+            |(lhs, rhs)| {
+                if self.accepting(lhs) || other.accepting(rhs) {
+                    BTreeSet::new()
+                } else {
+                    lhs.iter()
+                        .flat_map(|&i| get!(self.states, i).non_accepting.iter())
+                        .chain(
+                            rhs.iter()
+                                .flat_map(|&i| get!(other.states, i).non_accepting.iter()),
+                        )
+                        .cloned()
+                        .collect()
+                }
+            },
+        )
+    }
+
+    /// Run both parsers in lockstep, pairing up one state from each (or `None` once one has rejected),
+    /// with `choose` deciding where each pair of transitions on the same tokens leads (and with what update, if any)
+    /// and `non_accepting` deciding why each pair of states wouldn't accept (nothing if it would).
+    /// # Errors
+    /// If `choose` does, if either parser opens or closes a region,
+    /// or if the two split their input tokens at a token with no immediate predecessor.
+    #[inline]
+    #[allow(clippy::missing_panics_doc, clippy::type_complexity)]
+    fn explore_pairs<
+        T: FnMut(
+            Option<&Transition<I, usize>>,
+            Option<&Transition<I, usize>>,
+        ) -> Result<Option<(Pair, Option<Update<I>>)>, IllFormed<I, usize>>,
+        N: Fn(Pair) -> BTreeSet<Rejection<I>>,
+    >(
+        &self,
+        other: &Self,
+        mut choose: T,
+        non_accepting: N,
+    ) -> Result<Self, IllFormed<I, usize>> {
+        self.reject_regions()?;
+        other.reject_regions()?;
+        let initial = (Some(self.initial), Some(other.initial));
+        let mut indices = BTreeMap::new();
+        let _ = indices.insert(initial, 0);
+        let mut queue = vec![initial];
+        let mut states = vec![];
+        while let Some(pair) = queue.pop() {
+            let (lhs, rhs) = pair;
+            let (mut lhs_filter, lhs_fallback) = self.outgoing(lhs);
+            let (mut rhs_filter, rhs_fallback) = other.outgoing(rhs);
+            let boundaries: BTreeSet<I> = lhs_filter
+                .boundaries()
+                .chain(rhs_filter.boundaries())
+                .collect();
+            for token in &boundaries {
+                lhs_filter.split_at(token)?;
+                rhs_filter.split_at(token)?;
+            }
+            let mut transition =
+                |lhs_t: Option<&Transition<I, usize>>,
+                 rhs_t: Option<&Transition<I, usize>>|
+                 -> Result<Option<Transition<I, usize>>, IllFormed<I, usize>> {
+                    let Some((dst, update)) = choose(lhs_t, rhs_t)? else {
+                        return Ok(None);
+                    };
+                    let n = indices.len();
+                    let i = *indices.entry(dst).or_insert_with(|| {
+                        queue.push(dst);
+                        n
+                    });
+                    Ok(Some(Transition::Lateral { dst: i, update }))
+                };
+            let ranges: BTreeSet<_> = lhs_filter
+                .0
+                .keys()
+                .chain(rhs_filter.0.keys())
+                .cloned()
+                .collect();
+            let mut filter = BTreeMap::new();
+            for range in ranges {
+                if let Some(t) = transition(
+                    lhs_filter.0.get(&range).or(lhs_fallback.as_ref()),
+                    rhs_filter.0.get(&range).or(rhs_fallback.as_ref()),
+                )? {
+                    drop(filter.insert(range, t));
+                }
+            }
+            let fallback = transition(lhs_fallback.as_ref(), rhs_fallback.as_ref())?;
+            states.push((
+                pair,
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(filter),
+                        fallback,
+                    }
+                    .prune_fallback(),
+                    non_accepting: non_accepting(pair),
+                },
+            ));
+        }
+        states.sort_unstable_by_key(|&(pair, _)| *unwrap!(indices.get(&pair)));
+        let mut out = Graph {
+            states: states.into_iter().map(|(_, state)| state).collect(),
+            initial: 0,
+        }
+        .minimize();
+        out.sort();
+        Ok(out)
+    }
+
+    /// The same parser, but starting in this state.
+    #[inline]
+    fn starting_at(&self, initial: usize) -> Self {
+        Graph {
+            states: self.states.clone(),
+            initial,
+        }
+    }
+
+    /// Accept exactly what this parser accepts but the other doesn't.
+    /// # Errors
+    /// See `product_with`.
+    #[inline]
+    pub fn minus(&self, other: &Self) -> Result<Self, IllFormed<I, usize>> {
+        self.product_with(other, |lhs, rhs| lhs && !rhs)
+    }
+
+    /// Accept exactly what this parser rejects.
+    /// # Errors
+    /// If this parser calls any update (since none of them would ever run on input the complement accepts),
+    /// or see `product_with`.
+    #[inline]
+    pub fn complement(&self) -> Result<Self, IllFormed<I, usize>> {
+        if self.involves_updates() {
+            if let Some(update) = self
+                .states
+                .iter()
+                .flat_map(|state| state.transitions.values())
+                .find_map(Transition::update)
+            {
+                return Err(IllFormed::ComplementOverUpdate(update.src.to_string()));
+            }
+        }
+        self.product_with(self, |accept, _| !accept)
+    }
+
+    /// Check whether every input this parser accepts is also accepted by the other (ignoring output),
+    /// e.g. to make sure a loosened grammar still accepts everything it used to.
+    /// # Errors
+    /// With a shortest input (built from `representative_tokens`) that this parser accepts but the other rejects,
+    /// or if either parser opens or closes a region (see `product_with`).
+    #[inline]
+    pub fn is_subset_of(&self, other: &Self) -> Result<(), Counterexample<I>> {
+        self.minus(other)
+            .map_err(Counterexample::IllFormed)?
+            .shortest_accepted()
+            .map_or(Ok(()), |input| Err(Counterexample::Accepted(input)))
+    }
+
+    /// Check whether these two parsers accept exactly the same inputs (ignoring output).
+    /// # Errors
+    /// See `product_with`.
+    #[inline]
+    pub fn equivalent(&self, other: &Self) -> Result<bool, IllFormed<I, usize>> {
+        self.product_with(other, |lhs, rhs| lhs != rhs)
+            .map(|difference| difference.accepts_nothing())
+    }
+
+    /// Explicit transitions and fallback out of a state (or nothing, if we've already rejected).
+    #[inline]
+    #[allow(clippy::type_complexity)]
+    fn outgoing(&self, state: Option<usize>) -> (RangeMap<I, usize>, Option<Transition<I, usize>>) {
+        match state.map(|i| &get!(self.states, i).transitions) {
+            None => (RangeMap(BTreeMap::new()), None),
+            Some(&Curry::Wildcard(ref t)) => (RangeMap(BTreeMap::new()), Some(t.clone())),
+            Some(&Curry::Scrutinize {
+                ref filter,
+                ref fallback,
+            }) => (filter.clone(), fallback.clone()),
+        }
+    }
+
+    /// Where a lateral transition leads (regions are rejected before we ever get here).
+    #[inline]
+    const fn lateral_dst(t: &Transition<I, usize>) -> Option<usize> {
+        match *t {
+            Transition::Lateral { dst, .. } => Some(dst),
+            Transition::Call { .. } | Transition::Return { .. } => None,
+        }
+    }
+
+    /// Whether input ending in this state would be accepted (`false` if we've already rejected).
+    #[inline]
+    fn accepting(&self, state: Option<usize>) -> bool {
+        state.is_some_and(|i| get!(self.states, i).non_accepting.is_empty())
+    }
+}
+
+impl<I: Input> Deterministic<I> {
+    /// Error out if any transition opens or closes a region.
+    #[inline]
+    pub(crate) fn reject_regions(&self) -> Result<(), IllFormed<I, usize>> {
+        if !self.involves_calls() {
+            return Ok(());
+        }
+        // Find a region to point to.
+        self.states
+            .iter()
+            .flat_map(|state| state.transitions.values())
+            .try_fold((), |(), t| match *t {
+                Transition::Lateral { .. } => Ok(()),
+                Transition::Call { region, .. } | Transition::Return { region } => {
+                    Err(IllFormed::ProductOverRegion(region))
+                }
+            })
+    }
+}
@@ -6,7 +6,7 @@
 
 //! `QuickCheck` implementations for various types.
 
-use crate::{Ctrl, Curry, Graph, Input, Range, RangeMap, State, Transition, Update, FF};
+use crate::{Ctrl, Curry, Graph, Input, Range, RangeMap, Rejection, State, Transition, Update, FF};
 use core::{iter, num::NonZeroUsize};
 use quickcheck::{Arbitrary, Gen};
 use std::collections::{BTreeMap, BTreeSet};
@@ -91,6 +91,46 @@ impl<I: Arbitrary + Input> Arbitrary for Range<I> {
     }
 }
 
+impl<I: Arbitrary + Input> Arbitrary for Rejection<I> {
+    #[inline]
+    fn arbitrary(g: &mut Gen) -> Self {
+        match u8::arbitrary(g) & 15 {
+            0 => Self::ExpectedRange(Range::arbitrary(g)),
+            1 => Self::ExpectedEndAfter(Range::arbitrary(g)),
+            2 => Self::ExpectedEndAfterAny,
+            3 => Self::UnfinishedConcatenation,
+            4 => Self::UnfinishedCall,
+            5 => Self::UnexpectedToken,
+            6 => Self::Product,
+            7 => Self::ExpectedEof(I::arbitrary(g)),
+            8 => Self::Terminated(I::arbitrary(g)),
+            _ => Self::Custom(String::arbitrary(g).into()),
+        }
+    }
+    #[inline]
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        match *self {
+            Self::ExpectedRange(ref range) => Box::new(range.shrink().map(Self::ExpectedRange)),
+            Self::ExpectedEndAfter(ref range) => {
+                Box::new(range.shrink().map(Self::ExpectedEndAfter))
+            }
+            Self::ExpectedEof(ref eof) => Box::new(eof.shrink().map(Self::ExpectedEof)),
+            Self::Terminated(ref token) => Box::new(token.shrink().map(Self::Terminated)),
+            Self::Custom(ref message) => Box::new(
+                message
+                    .to_string()
+                    .shrink()
+                    .map(|shrunk| Self::Custom(shrunk.into())),
+            ),
+            Self::ExpectedEndAfterAny
+            | Self::UnfinishedConcatenation
+            | Self::UnfinishedCall
+            | Self::UnexpectedToken
+            | Self::Product => Box::new(iter::empty()),
+        }
+    }
+}
+
 impl<I: 'static + Input> Arbitrary for Update<I> {
     #[inline(always)]
     fn arbitrary(_: &mut Gen) -> Self {
@@ -202,10 +242,10 @@ impl<C: Ctrl<u8>> Curry<u8, C> {
         if bool::arbitrary(g) {
             Self::Wildcard(Transition::arbitrary_given(n_states, g))
         } else {
-            Self::Scrutinize {
-                filter: RangeMap::arbitrary_given(n_states, g),
-                fallback: bool::arbitrary(g).then(|| Transition::arbitrary_given(n_states, g)),
-            }
+            let filter = RangeMap::arbitrary_given(n_states, g);
+            let fallback = (bool::arbitrary(g) && !filter.covers_everything())
+                .then(|| Transition::arbitrary_given(n_states, g));
+            Self::Scrutinize { filter, fallback }
         }
     }
 }
@@ -263,12 +303,7 @@ impl<C: Ctrl<u8>> Transition<u8, C> {
 impl Arbitrary for FF {
     #[inline]
     fn arbitrary(_: &mut Gen) -> Self {
-        Self {
-            lhs_t: "()".to_owned(),
-            rhs_t: "()".to_owned(),
-            output_t: "()".to_owned(),
-            src: "|(), ()| ()".to_owned(),
-        }
+        ff!(|(), ()| ())
     }
     #[inline]
     fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
@@ -45,3 +45,51 @@ impl<I: Input> Range<I> {
         (first <= last).then_some(Self { first, last })
     }
 }
+
+/// `const` versions of the methods above for primitive tokens, e.g. to build lookup tables at compile time.
+/// (The generic methods can't be `const`, since comparing arbitrary tokens calls trait methods.)
+macro_rules! const_methods {
+    ($($t:ty),*) => {$(
+        impl Range<$t> {
+            /// Trivial range with a single inhabitant, usable in `const` contexts.
+            #[inline]
+            #[must_use]
+            pub const fn const_unit(value: $t) -> Self {
+                Self {
+                    first: value,
+                    last: value,
+                }
+            }
+
+            /// Check if a value lies within this range, usable in `const` contexts.
+            #[inline]
+            #[must_use]
+            pub const fn const_contains(&self, value: $t) -> bool {
+                value >= self.first && value <= self.last
+            }
+
+            /// If two ranges overlap, return their intersection, usable in `const` contexts.
+            #[inline]
+            #[must_use]
+            pub const fn const_intersection(self, other: Self) -> Option<Self> {
+                let first = if self.first > other.first {
+                    self.first
+                } else {
+                    other.first
+                };
+                let last = if self.last < other.last {
+                    self.last
+                } else {
+                    other.last
+                };
+                if first <= last {
+                    Some(Self { first, last })
+                } else {
+                    None
+                }
+            }
+        }
+    )*};
+}
+
+const_methods!(u8, usize, char);
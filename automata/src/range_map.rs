@@ -6,8 +6,8 @@
 
 //! Map from ranges of keys to values.
 
-use crate::{Ctrl, IllFormed, Input, Range, Transition};
-use core::cmp;
+use crate::{Ctrl, IllFormed, Input, Range, Step, Transition};
+use core::{cmp, hash, iter, mem};
 use std::collections::{BTreeMap, BTreeSet};
 
 /// Map from ranges of keys to values.
@@ -36,6 +36,13 @@ impl<I: Input, C: Ctrl<I>> PartialEq for RangeMap<I, C> {
     }
 }
 
+impl<I: Input + hash::Hash, C: Ctrl<I> + hash::Hash> hash::Hash for RangeMap<I, C> {
+    #[inline]
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
 impl<I: Input, C: Ctrl<I>> Ord for RangeMap<I, C> {
     #[inline]
     fn cmp(&self, other: &Self) -> cmp::Ordering {
@@ -121,6 +128,150 @@ impl<I: Input, C: Ctrl<I>> RangeMap<I, C> {
     }
 }
 
+impl<I: Step, C: Ctrl<I>> RangeMap<I, C> {
+    /// Split any range that contains this token (but doesn't start with it) into two:
+    /// one ending immediately before this token and one starting with it.
+    /// Doesn't change which transition any token takes.
+    /// # Errors
+    /// If we need to split a range but this token has no immediate predecessor.
+    #[inline]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn split_at(&mut self, token: &I) -> Result<(), IllFormed<I, C>> {
+        let Some(range) = self
+            .0
+            .keys()
+            .find(|r| r.first < *token && r.contains(token))
+            .cloned()
+        else {
+            return Ok(());
+        };
+        let before = token
+            .pred()
+            .ok_or_else(|| IllFormed::Unsplittable(token.clone()))?;
+        let transition = unwrap!(self.0.remove(&range));
+        drop(self.0.insert(
+            Range {
+                first: range.first,
+                last: before,
+            },
+            transition.clone(),
+        ));
+        drop(self.0.insert(
+            Range {
+                first: token.clone(),
+                last: range.last,
+            },
+            transition,
+        ));
+        Ok(())
+    }
+
+    /// Every token at which a range starts or immediately after which a range ends:
+    /// splitting another map at all of these aligns its ranges with this map's.
+    #[inline]
+    pub fn boundaries(&self) -> impl Iterator<Item = I> + '_ {
+        self.0
+            .keys()
+            .flat_map(|r| iter::once(r.first.clone()).chain(r.last.succ()))
+    }
+
+    /// Combine two maps into one that takes, on each token, whichever transition either map would take,
+    /// resolving tokens that both maps would take with `combine`.
+    /// Ranges that only partially overlap are split first, so `combine` sees every overlap exactly once.
+    /// # Errors
+    /// If `combine` fails, or if we need to split a range at a token with no immediate predecessor.
+    #[inline]
+    #[allow(clippy::type_complexity)]
+    pub fn merge_with<
+        F: FnMut(Transition<I, C>, Transition<I, C>) -> Result<Transition<I, C>, IllFormed<I, C>>,
+    >(
+        &self,
+        other: &Self,
+        combine: F,
+    ) -> Result<Self, IllFormed<I, C>> {
+        self.merge_with_fallbacks(other, None, None, combine)
+    }
+
+    /// Like `merge_with`, but each map also has a transition (its `fallback`) for any token none of its ranges match:
+    /// a range in one map then combines with the other map's fallback instead of shadowing it.
+    /// # Errors
+    /// If `combine` fails, or if we need to split a range at a token with no immediate predecessor.
+    #[inline]
+    #[allow(clippy::type_complexity)]
+    pub fn merge_with_fallbacks<
+        F: FnMut(Transition<I, C>, Transition<I, C>) -> Result<Transition<I, C>, IllFormed<I, C>>,
+    >(
+        &self,
+        other: &Self,
+        fallback: Option<&Transition<I, C>>,
+        other_fallback: Option<&Transition<I, C>>,
+        mut combine: F,
+    ) -> Result<Self, IllFormed<I, C>> {
+        let (mut lhs, mut rhs) = (self.clone(), other.clone());
+        let boundaries: BTreeSet<I> = lhs.boundaries().chain(rhs.boundaries()).collect();
+        for token in &boundaries {
+            lhs.split_at(token)?;
+            rhs.split_at(token)?;
+        }
+        let mut merged = BTreeMap::new();
+        for (range, transition) in lhs.0 {
+            let both = match (rhs.0.remove(&range), other_fallback) {
+                (Some(other_transition), _) => combine(transition, other_transition)?,
+                (None, Some(f)) => combine(transition, f.clone())?,
+                (None, None) => transition,
+            };
+            drop(merged.insert(range, both));
+        }
+        for (range, transition) in rhs.0 {
+            let both = match fallback {
+                Some(f) => combine(f.clone(), transition)?,
+                None => transition,
+            };
+            drop(merged.insert(range, both));
+        }
+        Ok(Self(merged))
+    }
+
+    /// Merge each pair of adjacent ranges (i.e. with no token in between) that take identical transitions.
+    /// Doesn't change which transition any token takes.
+    #[inline]
+    pub fn coalesce(&mut self) {
+        let mut merged: Vec<(Range<I>, Transition<I, C>)> = vec![];
+        for (range, transition) in mem::take(&mut self.0) {
+            if let Some(&mut (ref mut prev, ref prev_transition)) = merged.last_mut() {
+                if *prev_transition == transition && prev.last.succ().as_ref() == Some(&range.first)
+                {
+                    prev.last = range.last;
+                    continue;
+                }
+            }
+            merged.push((range, transition));
+        }
+        self.0 = merged.into_iter().collect();
+    }
+
+    /// Whether every possible token falls in some range (so a fallback next to these ranges could never run).
+    /// Always `false` for tokens that don't implement `succ` and `pred`, since we can't tell.
+    #[inline]
+    #[must_use]
+    pub fn covers_everything(&self) -> bool {
+        let mut expected = None;
+        for range in self.0.keys() {
+            match expected {
+                // Least token, unless it's only that we can't step from it.
+                None if range.first.pred().is_none() && range.first.succ().is_some() => {}
+                Some(ref next) if *next == range.first => {}
+                _ => return false,
+            }
+            match range.last.succ() {
+                None => return true,
+                next @ Some(_) => expected = next,
+            }
+        }
+        false
+    }
+}
+
 impl<I: Input> RangeMap<I, usize> {
     /// Convert the control parameter from `usize` to anything else.
     #[inline]
@@ -0,0 +1,60 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Keep parsing after a bad token instead of stopping at the first error.
+
+use crate::{Ctrl, Graph, Input, ParseError, Run};
+use core::cell::Cell;
+use std::collections::BTreeSet;
+
+impl<I: Input, C: Ctrl<I>> Graph<I, C> {
+    /// Run this parser to completion as in `accept`, but recover from bad tokens instead of stopping at the first one
+    /// (classic panic-mode recovery, e.g. to report every error at once in an editor):
+    /// record the error, skip input up to and including the next token in `sync` (starting with the bad token itself),
+    /// then pick up exactly where the parser was before the bad token.
+    /// Returns what `accept` would have returned had the skipped tokens never been there,
+    /// along with every error we recovered from (and the index of the token that caused it).
+    /// Errors once input runs out, and errors in the parser itself, end the run as usual.
+    #[inline]
+    #[allow(clippy::missing_panics_doc, clippy::type_complexity)]
+    pub fn accept_recovering<In: IntoIterator<Item = I>>(
+        &self,
+        input: In,
+        sync: &BTreeSet<I>,
+    ) -> (
+        Result<String, ParseError<I, C>>,
+        Vec<(usize, ParseError<I, C>)>,
+    ) {
+        // The most recent token read, so we know which token (if any) caused an error.
+        let last = Cell::new(None);
+        let mut run = input
+            .into_iter()
+            .inspect(|token| last.set(Some(token.clone())))
+            .run(self);
+        let mut errors = vec![];
+        loop {
+            last.set(None);
+            let error = match run.next() {
+                None => return (Ok(run.output_t), errors),
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => e,
+            };
+            let (Some(mut skipped), &ParseError::BadInput(..)) = (last.take(), &error) else {
+                return (Err(error), errors);
+            };
+            errors.push((run.consumed, error));
+            // A failed step leaves the parser as it was, so all we have to do is skip input.
+            run.consumed = unwrap!(run.consumed.checked_add(1));
+            while !sync.contains(&skipped) {
+                let Some(token) = run.input.next() else {
+                    break;
+                };
+                skipped = token;
+                run.consumed = unwrap!(run.consumed.checked_add(1));
+            }
+        }
+    }
+}
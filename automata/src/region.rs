@@ -0,0 +1,230 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Inspect and replace the sub-automaton that runs inside a region.
+
+use crate::{Ctrl, Curry, Graph, IllFormed, Input, Transition};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A one-to-one correspondence between region names in two parsers, built up as we go.
+#[derive(Default)]
+struct Renaming {
+    /// Names in the first parser to names in the second.
+    forward: BTreeMap<&'static str, &'static str>,
+    /// Names in the second parser to names in the first.
+    backward: BTreeMap<&'static str, &'static str>,
+}
+
+impl Renaming {
+    /// Check that these names correspond (or make them correspond if neither has been seen yet).
+    #[inline]
+    fn consistent(&mut self, lhs: &'static str, rhs: &'static str) -> bool {
+        *self.forward.entry(lhs).or_insert(rhs) == rhs
+            && *self.backward.entry(rhs).or_insert(lhs) == lhs
+    }
+}
+
+impl<I: Input, C: Ctrl<I>> Graph<I, C> {
+    /// Every state reachable once we've opened a region (i.e., from its detour).
+    /// # Errors
+    /// If no transition opens this region or (deterministic) calls disagree on where to detour.
+    #[inline]
+    pub fn states_in_region(
+        &self,
+        region: &'static str,
+    ) -> Result<BTreeSet<usize>, IllFormed<I, C>> {
+        Ok(self.reachable_from(self.region_detour(region)?.view()))
+    }
+
+    /// States reachable within a region's detour as a standalone automaton,
+    /// starting where the region's detour starts.
+    /// Useful to optimize (e.g. `minimize`) one region in isolation, then `set_region_subgraph`.
+    /// # Errors
+    /// If no transition opens this region or (deterministic) calls disagree on where to detour.
+    #[inline]
+    pub fn region_subgraph(&self, region: &'static str) -> Result<Self, IllFormed<I, C>> {
+        let detour = self.region_detour(region)?;
+        Ok(self.restrict(&self.reachable_from(detour.view()), detour))
+    }
+
+    /// Replace the automaton that runs inside a region (e.g. with a minimized `region_subgraph`).
+    /// Every call opening this region will detour into the new subgraph instead,
+    /// and any state no longer reachable is removed.
+    /// # Errors
+    /// If no transition opens this region (in which case nothing changes).
+    #[inline]
+    #[allow(clippy::missing_panics_doc, clippy::unwrap_in_result)]
+    pub fn set_region_subgraph(
+        &mut self,
+        region: &'static str,
+        subgraph: Self,
+    ) -> Result<(), IllFormed<I, C>> {
+        if !self
+            .states
+            .iter()
+            .flat_map(|state| state.transitions.values())
+            .any(|t| !t.detours_into(region).is_empty())
+        {
+            return Err(IllFormed::RegionDNE(region));
+        }
+        let size = self.states.len();
+        let Self {
+            states, initial, ..
+        } = subgraph.map_indices(|i| i.checked_add(size).expect("Absurdly huge number of states"));
+        self.states.extend(states);
+        for state in &mut self.states {
+            for t in state.transitions.values_mut() {
+                t.redirect_region(region, &initial);
+            }
+        }
+        *self = self.clone().trim();
+        self.sort();
+        Ok(())
+    }
+
+    /// Check whether two parsers are identical except for what they name their regions,
+    /// as long as names correspond one-to-one (e.g. `"parentheses"` everywhere in one and `"parens"` everywhere in the other).
+    /// Like `==`, this compares states index by index, so sort both parsers first if their order might differ.
+    #[inline]
+    #[must_use]
+    pub fn structurally_eq_ignoring_region_names(&self, other: &Self) -> bool {
+        let mut renaming = Renaming::default();
+        self.initial == other.initial
+            && self.states.len() == other.states.len()
+            && self.states.iter().zip(&other.states).all(|(lhs, rhs)| {
+                lhs.non_accepting == rhs.non_accepting
+                    && match (&lhs.transitions, &rhs.transitions) {
+                        (&Curry::Wildcard(ref l), &Curry::Wildcard(ref r)) => {
+                            l.eq_renaming(r, &mut renaming)
+                        }
+                        (
+                            &Curry::Scrutinize {
+                                filter: ref l_filter,
+                                fallback: ref l_fallback,
+                            },
+                            &Curry::Scrutinize {
+                                filter: ref r_filter,
+                                fallback: ref r_fallback,
+                            },
+                        ) => {
+                            l_filter.0.len() == r_filter.0.len()
+                                && l_filter.0.iter().zip(&r_filter.0).all(
+                                    |((l_range, l), (r_range, r))| {
+                                        l_range == r_range && l.eq_renaming(r, &mut renaming)
+                                    },
+                                )
+                                && match (l_fallback.as_ref(), r_fallback.as_ref()) {
+                                    (None, None) => true,
+                                    (Some(l), Some(r)) => l.eq_renaming(r, &mut renaming),
+                                    (None, Some(_)) | (Some(_), None) => false,
+                                }
+                        }
+                        (&Curry::Wildcard(..), &Curry::Scrutinize { .. })
+                        | (&Curry::Scrutinize { .. }, &Curry::Wildcard(..)) => false,
+                    }
+            })
+    }
+
+    /// Merge the detours of every call that opens this region.
+    #[inline]
+    fn region_detour(&self, region: &'static str) -> Result<C, IllFormed<I, C>> {
+        self.states
+            .iter()
+            .flat_map(|state| state.transitions.values())
+            .flat_map(|t| t.detours_into(region))
+            .try_fold(None, |acc: Option<C>, detour| {
+                acc.map_or_else(
+                    || Ok(Some(detour.clone())),
+                    |extant| {
+                        extant
+                            .merge(detour.clone())
+                            .map(Some)
+                            .map_err(|(a, b)| IllFormed::Superposition(a, b))
+                    },
+                )
+            })?
+            .ok_or(IllFormed::RegionDNE(region))
+    }
+}
+
+impl<I: Input, C: Ctrl<I>> Transition<I, C> {
+    /// Every detour (including in continuations) that opens this region.
+    #[inline]
+    fn detours_into(&self, region: &'static str) -> Vec<&C> {
+        match *self {
+            Self::Lateral { .. } | Self::Return { .. } => vec![],
+            Self::Call {
+                region: r,
+                ref detour,
+                ref dst,
+                ..
+            } => {
+                let mut v = dst.detours_into(region);
+                if r == region {
+                    v.push(detour);
+                }
+                v
+            }
+        }
+    }
+
+    /// Check whether two transitions are identical except for consistently renamed regions.
+    #[inline]
+    fn eq_renaming(&self, other: &Self, renaming: &mut Renaming) -> bool {
+        match (self, other) {
+            (
+                &Self::Lateral {
+                    dst: ref l_dst,
+                    update: ref l_update,
+                },
+                &Self::Lateral {
+                    dst: ref r_dst,
+                    update: ref r_update,
+                },
+            ) => l_dst == r_dst && l_update == r_update,
+            (
+                &Self::Call {
+                    region: l_region,
+                    detour: ref l_detour,
+                    dst: ref l_dst,
+                    combine: ref l_combine,
+                },
+                &Self::Call {
+                    region: r_region,
+                    detour: ref r_detour,
+                    dst: ref r_dst,
+                    combine: ref r_combine,
+                },
+            ) => {
+                renaming.consistent(l_region, r_region)
+                    && l_detour == r_detour
+                    && l_combine == r_combine
+                    && l_dst.eq_renaming(r_dst, renaming)
+            }
+            (&Self::Return { region: l_region }, &Self::Return { region: r_region }) => {
+                renaming.consistent(l_region, r_region)
+            }
+            _ => false,
+        }
+    }
+
+    /// Point every call that opens this region (including in continuations) at a new detour.
+    #[inline]
+    pub(crate) fn redirect_region(&mut self, region: &'static str, new_detour: &C) {
+        if let Self::Call {
+            region: r,
+            ref mut detour,
+            ref mut dst,
+            ..
+        } = *self
+        {
+            if r == region {
+                *detour = new_detour.clone();
+            }
+            dst.redirect_region(region, new_detour);
+        }
+    }
+}
@@ -0,0 +1,99 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Why a state doesn't accept, kept structured until someone actually wants to read it.
+
+use crate::{Input, Range};
+use core::fmt;
+use std::sync::Arc;
+
+/// Why a state doesn't accept if input ends there.
+///
+/// Formatted (in English) only when displayed, so building huge parsers doesn't build huge strings,
+/// and anything reading these can match on what went wrong instead of parsing a message.
+#[non_exhaustive]
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Rejection<I: Input> {
+    /// Expected a token in this range, but input ended.
+    ExpectedRange(Range<I>),
+    /// Expected a single token in this range and nothing after it (e.g. from `any_of`).
+    ExpectedEndAfter(Range<I>),
+    /// Expected a single token (of any kind) and nothing after it (e.g. from `any`).
+    ExpectedEndAfterAny,
+    /// Expected this end-of-input sentinel (see `with_eof`), but input ended without it.
+    ExpectedEof(I),
+    /// Hit this terminator (see `with_terminator`) before the parser could accept.
+    Terminated(I),
+    /// Ran the first parser in a concatenation (`>>`) but not the second.
+    UnfinishedConcatenation,
+    /// Ran the first parser in a call (`^`) but not the second.
+    UnfinishedCall,
+    /// No transition (and hence no state) follows the last token.
+    UnexpectedToken,
+    /// Rejected by whatever combined the decisions of two parsers run in lockstep (e.g. `minus`).
+    Product,
+    /// Anything else, already written out.
+    Custom(Arc<str>),
+}
+
+impl<I: Input> fmt::Display for Rejection<I> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::ExpectedRange(ref range) => write!(
+                f,
+                "Expected a token on [{}..={}]",
+                range.first.to_src(),
+                range.last.to_src(),
+            ),
+            Self::ExpectedEndAfter(ref range) => write!(
+                f,
+                "Expected only a single token on [{}..={}] but got another token after it",
+                range.first.to_src(),
+                range.last.to_src(),
+            ),
+            Self::ExpectedEndAfterAny => write!(
+                f,
+                "Expected only a single token but got another token after it",
+            ),
+            Self::ExpectedEof(ref eof) => {
+                write!(f, "Expected the end-of-input sentinel {}", eof.to_src())
+            }
+            Self::Terminated(ref token) => write!(
+                f,
+                "Hit the terminator {} before the input was complete",
+                token.to_src(),
+            ),
+            Self::UnfinishedConcatenation => write!(
+                f,
+                "Ran the first part of a two-parser concatenation \
+                (with `>>`) but not the second one.",
+            ),
+            Self::UnfinishedCall => write!(
+                f,
+                "Ran the first part of a two-parser call \
+                (with `^`) but not the second one.",
+            ),
+            Self::UnexpectedToken => write!(f, "Unexpected token"),
+            Self::Product => write!(f, "Rejected by a combination of parsers"),
+            Self::Custom(ref message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl<I: Input> From<&str> for Rejection<I> {
+    #[inline]
+    fn from(message: &str) -> Self {
+        Self::Custom(message.into())
+    }
+}
+
+impl<I: Input> From<String> for Rejection<I> {
+    #[inline]
+    fn from(message: String) -> Self {
+        Self::Custom(message.into())
+    }
+}
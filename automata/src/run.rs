@@ -38,6 +38,7 @@ where
             stack: vec![],
             ctrl: graph.initial.clone(),
             output_t: "()".to_owned(),
+            consumed: 0,
         }
     }
 }
@@ -0,0 +1,322 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Search for inputs with particular properties.
+
+use crate::{analysis::Reach, Ctrl, Curry, Deterministic, Graph, Step, Transition};
+use core::{iter, mem};
+use std::collections::{BTreeSet, VecDeque};
+
+impl<I: Step, C: Ctrl<I>> Graph<I, C> {
+    /// At least one token from each group of tokens that every state treats identically:
+    /// the first token in each range, the tokens immediately before and after each range,
+    /// and, if any state takes a wildcard or fallback transition, the least token (see `Step::least`),
+    /// which lands outside every range whenever no range starts from it.
+    /// Empty only if no state ever scrutinizes its input and we don't know any token of this type.
+    #[inline]
+    #[must_use]
+    pub fn representative_tokens(&self) -> BTreeSet<I> {
+        let mut tokens: BTreeSet<I> = self
+            .states
+            .iter()
+            .filter_map(|state| match state.transitions {
+                Curry::Wildcard(..) => None,
+                Curry::Scrutinize { ref filter, .. } => Some(filter.0.keys()),
+            })
+            .flatten()
+            .flat_map(|range| {
+                range
+                    .first
+                    .pred()
+                    .into_iter()
+                    .chain(iter::once(range.first.clone()))
+                    .chain(range.last.succ())
+            })
+            .collect();
+        if self.states.iter().any(|state| {
+            matches!(
+                state.transitions,
+                Curry::Wildcard(..)
+                    | Curry::Scrutinize {
+                        fallback: Some(..),
+                        ..
+                    }
+            )
+        }) {
+            tokens.extend(I::least());
+        }
+        tokens
+    }
+}
+
+impl<I: Step> Deterministic<I> {
+    /// A shortest input this parser accepts, if any (built from `representative_tokens`, shortest first, then by token).
+    /// Follows regions exactly as `accepts_nothing` does, so this is `None` exactly when that's `true`,
+    /// unless `representative_tokens` is empty and every input this parser accepts needs a token (so there's none to build one from).
+    #[inline]
+    #[must_use]
+    pub fn shortest_accepted(&self) -> Option<Vec<I>> {
+        Reach::new(self)
+            .accepting()
+            .into_values()
+            .filter_map(|word| word.into_iter().collect::<Option<Vec<I>>>())
+            .min_by(|lhs, rhs| lhs.len().cmp(&rhs.len()).then_with(|| lhs.cmp(rhs)))
+    }
+
+    /// One shortest input ending in each accepting state with every region closed, e.g. for documentation
+    /// ("this parser accepts, e.g., `()`, `(A,)`, and `(A, B)`"). Built exactly like `shortest_accepted`.
+    /// An accepting initial state gets the empty input; accepting states no input reaches this way are skipped.
+    #[inline]
+    #[must_use]
+    pub fn acceptance_examples(&self) -> Vec<(usize, Vec<I>)> {
+        Reach::new(self)
+            .accepting()
+            .into_iter()
+            .filter_map(|(i, word)| Some((i, word.into_iter().collect::<Option<Vec<I>>>()?)))
+            .collect()
+    }
+
+    /// The first `n` inputs this parser accepts in breadth-first order (shortest first, then by token),
+    /// always the same for the same parser, e.g. as stable inputs for benchmarks.
+    /// Built like `acceptance_examples` from `representative_tokens`, but without stopping at one input per state
+    /// or limiting how deep regions go. Fewer than `n` if there aren't `n` inputs of length at most
+    /// `n + 1` times the number of states.
+    #[inline]
+    #[must_use]
+    pub fn canonical_inputs(&self, n: usize) -> Vec<Vec<I>> {
+        let tokens = self.representative_tokens();
+        let max_len = self.states.len().saturating_mul(n.saturating_add(1));
+        let mut inputs = vec![];
+        let mut queue = VecDeque::from([(self.initial, vec![], vec![])]);
+        while let Some((i, stack, path)) = queue.pop_front() {
+            if inputs.len() >= n {
+                break;
+            }
+            let state = get!(self.states, i);
+            if stack.is_empty() && state.non_accepting.is_empty() {
+                inputs.push(path.clone());
+            }
+            if path.len() >= max_len {
+                continue;
+            }
+            for token in &tokens {
+                let Ok(Some(transition)) = state.transitions.get(token) else {
+                    continue;
+                };
+                let mut next_stack = stack.clone();
+                if let Some(dst) = Self::step(transition, &mut next_stack) {
+                    let mut next = path.clone();
+                    next.push(token.clone());
+                    queue.push_back((dst, next_stack, next));
+                }
+            }
+        }
+        inputs
+    }
+
+    /// Take a transition without computing any output, returning the next state (or `None` if we can't return).
+    #[inline]
+    fn step<'graph>(
+        transition: &'graph Transition<I, usize>,
+        stack: &mut Vec<&'graph Transition<I, usize>>,
+    ) -> Option<usize> {
+        match *transition {
+            Transition::Lateral { dst, .. } => Some(dst),
+            Transition::Call {
+                detour, ref dst, ..
+            } => {
+                stack.push(dst);
+                Some(detour)
+            }
+            Transition::Return { .. } => {
+                let dst = stack.pop()?;
+                Self::step(dst, stack)
+            }
+        }
+    }
+
+    /// Name each state after a shortest input that reaches it (e.g. `s_open_paren_a` after `(a`),
+    /// so names stay the same when unrelated states come and go: pass to `to_src_named_by` as `|i| names[i].clone()`.
+    /// The initial state is `s_initial`; states we can't reach this way keep their numeric name (e.g. `state_3`).
+    /// Only follows transitions on tokens from `representative_tokens`, entering (but not leaving) regions.
+    #[inline]
+    #[must_use]
+    pub fn breadcrumb_names(&self) -> Vec<String> {
+        let tokens = self.representative_tokens();
+        let mut names: Vec<Option<String>> = vec![None; self.states.len()];
+        let mut taken = BTreeSet::new();
+        let mut queue = VecDeque::from([(self.initial, "s_initial".to_owned())]);
+        while let Some((i, breadcrumb)) = queue.pop_front() {
+            let Some(slot @ &mut None) = names.get_mut(i) else {
+                continue;
+            };
+            let name = if taken.contains(&breadcrumb) {
+                format!("{breadcrumb}_{i}")
+            } else {
+                breadcrumb
+            };
+            let prefix = if i == self.initial {
+                "s".to_owned()
+            } else {
+                name.clone()
+            };
+            let _ = taken.insert(name.clone());
+            *slot = Some(name);
+            for token in &tokens {
+                let Ok(Some(
+                    &Transition::Lateral { dst, .. } | &Transition::Call { detour: dst, .. },
+                )) = get!(self.states, i).transitions.get(token)
+                else {
+                    continue;
+                };
+                if get!(names, dst).is_none() {
+                    queue.push_back((dst, format!("{prefix}_{}", sanitize(&token.to_src()))));
+                }
+            }
+        }
+        names
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| name.unwrap_or_else(|| format!("state_{i}")))
+            .collect()
+    }
+
+    /// A shortest input reaching each state (by index), all from one breadth-first search,
+    /// following transitions on tokens from `representative_tokens` and entering (but not leaving) regions
+    /// exactly as in `breadcrumb_names`. `None` for states we can't reach this way.
+    #[inline]
+    #[must_use]
+    pub(crate) fn shortest_inputs(&self) -> Vec<Option<Vec<I>>> {
+        let tokens = self.representative_tokens();
+        // How we first reached each state: the state before it and the token in between.
+        let mut parents: Vec<Option<Option<(usize, I)>>> = vec![None; self.states.len()];
+        if let Some(slot) = parents.get_mut(self.initial) {
+            *slot = Some(None);
+        }
+        let mut queue = VecDeque::from([self.initial]);
+        while let Some(i) = queue.pop_front() {
+            let Some(state) = self.states.get(i) else {
+                continue;
+            };
+            for token in &tokens {
+                let Ok(Some(
+                    &Transition::Lateral { dst, .. } | &Transition::Call { detour: dst, .. },
+                )) = state.transitions.get(token)
+                else {
+                    continue;
+                };
+                if let Some(slot @ &mut None) = parents.get_mut(dst) {
+                    *slot = Some(Some((i, token.clone())));
+                    queue.push_back(dst);
+                }
+            }
+        }
+        (0..self.states.len())
+            .map(|mut i| {
+                let mut path = vec![];
+                while let &Some((parent, ref token)) = get!(parents, i).as_ref()? {
+                    path.push(token.clone());
+                    i = parent;
+                }
+                path.reverse();
+                Some(path)
+            })
+            .collect()
+    }
+
+    /// Whether no input this parser accepts is a strict prefix of another input it accepts
+    /// (e.g. a self-delimiting code): i.e. whether no accepting state reaches another accepting state
+    /// (or itself) on at least one more token.
+    /// Conservative around regions: assumes every region it opens could close again,
+    /// so this may report `false` for a prefix-free parser but never `true` for one that isn't.
+    #[inline]
+    #[must_use]
+    pub fn is_prefix_free(&self) -> bool {
+        // Each state paired with whether we've already passed through an accepting state on the way there.
+        let mut visited = BTreeSet::new();
+        let mut stack = vec![(self.initial, false)];
+        while let Some((i, after_accepting)) = stack.pop() {
+            if !visited.insert((i, after_accepting)) {
+                continue;
+            }
+            let state = get!(self.states, i);
+            let accepting = state.non_accepting.is_empty();
+            if accepting && after_accepting {
+                return false;
+            }
+            // Regions are irrelevant except that we land on the call's continuation after closing one.
+            let after = after_accepting || accepting;
+            stack.extend(
+                state
+                    .transitions
+                    .values()
+                    .flat_map(|t| match *t {
+                        Transition::Lateral { ref dst, .. } => iter::once(dst).collect(),
+                        Transition::Call { ref dst, .. } => dst.dsts(),
+                        Transition::Return { .. } => BTreeSet::new(),
+                    })
+                    .map(|&dst| (dst, after)),
+            );
+        }
+        true
+    }
+}
+
+/// Turn a token's source code into something that can go in an identifier,
+/// keeping runs of lowercase letters and digits and spelling out everything else.
+#[inline]
+fn sanitize(src: &str) -> String {
+    let unquoted = src
+        .strip_prefix("b'")
+        .or_else(|| src.strip_prefix('\''))
+        .and_then(|s| s.strip_suffix('\''))
+        .or_else(|| src.strip_prefix('"')?.strip_suffix('"'))
+        .unwrap_or(src);
+    let mut pieces: Vec<String> = vec![];
+    let mut word = String::new();
+    for c in unquoted.chars() {
+        let spelled = match c {
+            'a'..='z' | '0'..='9' => {
+                word.push(c);
+                continue;
+            }
+            'A'..='Z' => format!("cap_{}", c.to_ascii_lowercase()),
+            '(' => "open_paren".to_owned(),
+            ')' => "close_paren".to_owned(),
+            '[' => "open_bracket".to_owned(),
+            ']' => "close_bracket".to_owned(),
+            '{' => "open_brace".to_owned(),
+            '}' => "close_brace".to_owned(),
+            '<' => "lt".to_owned(),
+            '>' => "gt".to_owned(),
+            '+' => "plus".to_owned(),
+            '-' => "minus".to_owned(),
+            '*' => "star".to_owned(),
+            '/' => "slash".to_owned(),
+            '\\' => "backslash".to_owned(),
+            ',' => "comma".to_owned(),
+            '.' => "dot".to_owned(),
+            ':' => "colon".to_owned(),
+            ';' => "semicolon".to_owned(),
+            '=' => "eq".to_owned(),
+            '!' => "bang".to_owned(),
+            '?' => "question".to_owned(),
+            '\'' => "quote".to_owned(),
+            '"' => "double_quote".to_owned(),
+            ' ' => "space".to_owned(),
+            '_' => "underscore".to_owned(),
+            _ => format!("u{:x}", u32::from(c)),
+        };
+        if !word.is_empty() {
+            pieces.push(mem::take(&mut word));
+        }
+        pieces.push(spelled);
+    }
+    if !word.is_empty() {
+        pieces.push(word);
+    }
+    pieces.join("_")
+}
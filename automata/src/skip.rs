@@ -0,0 +1,101 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Ignore anything matching a "skip" parser (e.g. whitespace or comments) between significant tokens.
+
+use crate::{Curry, Deterministic, Graph, IllFormed, Input, Merge, State, Step};
+
+impl<I: Step> Deterministic<I> {
+    /// Before each significant token (and at the end of the input),
+    /// discard any number of inputs that `skip` accepts, e.g. whitespace or comments,
+    /// instead of weaving optional whitespace through every part of a grammar.
+    /// Whatever `skip` would compute is thrown away.
+    /// Grammars with significant whitespace can't also skip whitespace:
+    /// if any token `skip` could start with (or continue with) is also significant in the same place, that's an error.
+    /// # Errors
+    /// If `skip` opens or closes a region, or if anything `skip` reads could also be significant (`SkipOverlap`).
+    #[inline]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn with_skip(&self, skip: &Self) -> Result<Self, IllFormed<I, usize>> {
+        let skipper = skip.clone().discard()?.star();
+        let n = skipper.states.len();
+        let index = |significant: usize, skipping: usize| {
+            significant
+                .checked_mul(n)
+                .and_then(|i| i.checked_add(skipping))
+                .expect("Absurdly huge number of states")
+        };
+        let mut states = vec![];
+        for (i, significant) in self.states.iter().enumerate() {
+            for skipping in &skipper.states {
+                // Keep reading whatever we're skipping, but stay in the same significant state.
+                let mut transitions = skipping.transitions.clone().map_indices(|k| index(i, k));
+                let accepting = skipping.non_accepting.is_empty();
+                if accepting {
+                    // Done skipping (for now): significant tokens are fair game.
+                    overlap(&significant.transitions, &transitions)?;
+                    transitions = transitions.merge(
+                        significant
+                            .transitions
+                            .clone()
+                            .map_indices(|s| index(s, skipper.initial)),
+                    )?;
+                }
+                states.push(State {
+                    transitions,
+                    non_accepting: if accepting {
+                        significant.non_accepting.clone()
+                    } else {
+                        skipping.non_accepting.clone()
+                    },
+                });
+            }
+        }
+        let mut out = Graph {
+            states,
+            initial: index(self.initial, skipper.initial),
+        }
+        .trim();
+        out.sort();
+        Ok(out)
+    }
+}
+
+/// Error out if any token could take both a significant transition and a skipping transition.
+#[inline]
+fn overlap<I: Input>(
+    significant: &Curry<I, usize>,
+    skipping: &Curry<I, usize>,
+) -> Result<(), IllFormed<I, usize>> {
+    let Curry::Scrutinize {
+        filter: ref skip_filter,
+        fallback: None,
+    } = *skipping
+    else {
+        return Err(IllFormed::SkipOverlap(None));
+    };
+    for skip_range in skip_filter.0.keys() {
+        match *significant {
+            Curry::Wildcard(..)
+            | Curry::Scrutinize {
+                fallback: Some(..), ..
+            } => return Err(IllFormed::SkipOverlap(Some(skip_range.clone()))),
+            Curry::Scrutinize {
+                ref filter,
+                fallback: None,
+            } => {
+                if let Some(both) = filter
+                    .0
+                    .keys()
+                    .find_map(|range| range.clone().intersection(skip_range.clone()))
+                {
+                    return Err(IllFormed::SkipOverlap(Some(both)));
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,64 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Report which tokens each function along a run was called on, e.g. to build source maps.
+
+use crate::{walk::Visit, Deterministic, Input, ParseError, Update, FF};
+
+/// Every function called so far with the range of input indices it covers.
+struct Spans<'graph>(Vec<(&'graph str, usize, usize)>);
+
+impl<'graph, I: Input> Visit<'graph, I, usize> for Spans<'graph> {
+    type Payload = ();
+    type Saved = ();
+
+    #[inline]
+    fn lateral(
+        &mut self,
+        update: Option<&'graph Update<I>>,
+        _: &I,
+        start: usize,
+        index: usize,
+    ) -> Result<(), ParseError<I, usize>> {
+        if let Some(f) = update {
+            self.0.push((&f.src, start, index.saturating_add(1)));
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn open(&mut self) {}
+
+    #[inline]
+    fn close(
+        &mut self,
+        combine: &'graph FF,
+        (): (),
+        opened: usize,
+        index: usize,
+    ) -> Result<(), ParseError<I, usize>> {
+        self.0.push((&combine.src, opened, index.saturating_add(1)));
+        Ok(())
+    }
+}
+
+impl<I: Input> Deterministic<I> {
+    /// Run this parser to completion and report each function it calls (by source code)
+    /// with the range of input indices it covers, `[start, end)`, in the order they're called:
+    /// an update covers the one token it's called on, and a combining function covers its whole region, delimiters included.
+    /// # Errors
+    /// If the parser rejects this input.
+    #[inline]
+    #[allow(clippy::type_complexity)]
+    pub fn accept_spans<In: IntoIterator<Item = I>>(
+        &self,
+        input: In,
+    ) -> Result<Vec<(&str, usize, usize)>, ParseError<I, usize>> {
+        let mut spans = Spans(vec![]);
+        self.walk(input.into_iter().map(|token| (token, ())), &mut spans)?;
+        Ok(spans.0)
+    }
+}
@@ -6,8 +6,8 @@
 
 //! State, i.e. a node in an automaton graph.
 
-use crate::{Ctrl, Curry, IllFormed, Input};
-use core::cmp;
+use crate::{Ctrl, Curry, IllFormed, Input, Rejection};
+use core::{cmp, hash};
 use std::collections::BTreeSet;
 
 /// State, i.e. a node in an automaton graph.
@@ -17,7 +17,8 @@ pub struct State<I: Input, C: Ctrl<I>> {
     /// Map from input tokens to actions.
     pub transitions: Curry<I, C>,
     /// If input ends while in this state, should we accept?
-    pub non_accepting: BTreeSet<String>,
+    /// Empty to accept; otherwise, every reason not to.
+    pub non_accepting: BTreeSet<Rejection<I>>,
 }
 
 impl<I: Input, C: Ctrl<I>> State<I, C> {
@@ -77,6 +78,14 @@ impl<I: Input, C: Ctrl<I>> PartialEq for State<I, C> {
     }
 }
 
+impl<I: Input + hash::Hash, C: Ctrl<I> + hash::Hash> hash::Hash for State<I, C> {
+    #[inline]
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.transitions.hash(state);
+        self.non_accepting.hash(state);
+    }
+}
+
 impl<I: Input, C: Ctrl<I>> Ord for State<I, C> {
     #[inline]
     fn cmp(&self, other: &Self) -> cmp::Ordering {
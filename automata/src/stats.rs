@@ -0,0 +1,111 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Count what a run spends its effort on, to see where a slow parse goes.
+
+use crate::{in_progress::step, Ctrl, Curry, Graph, Input, ParseError, Transition};
+
+/// How much work one run did (see `Graph::accept_counted`).
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct RunStats {
+    /// Tokens read from the input (including one that made the parser fail, if any).
+    pub tokens: usize,
+    /// Ranges checked against a token to look up its transition.
+    /// Every range in a state is checked (to catch overlaps), so coalescing adjacent ranges cuts this down.
+    pub comparisons: usize,
+    /// Regions opened (i.e. transitions pushed onto the stack).
+    pub pushes: usize,
+    /// Regions closed (i.e. transitions popped off the stack).
+    pub pops: usize,
+    /// States looked up, summed over every token.
+    /// A deterministic parser looks up one state per token; a nondeterministic parser looks up every state it could be in,
+    /// so this counts the work of following every path at once.
+    pub states: usize,
+}
+
+impl RunStats {
+    /// Count reading `token` in these states (with this stack), before acting on it.
+    /// Returns how many regions acting on it would open and close.
+    #[inline]
+    fn read<I: Input, C: Ctrl<I>>(
+        &mut self,
+        graph: &Graph<I, C>,
+        ctrl: &C,
+        token: &I,
+        stack: &[Transition<I, C>],
+    ) -> (usize, usize) {
+        self.tokens = unwrap!(self.tokens.checked_add(1));
+        let mut taken = None;
+        for state in ctrl.view().filter_map(|i| graph.states.get(i)) {
+            self.states = unwrap!(self.states.checked_add(1));
+            if let Curry::Scrutinize { ref filter, .. } = state.transitions {
+                self.comparisons = unwrap!(self.comparisons.checked_add(filter.0.len()));
+            }
+            // Transitions taken together have to agree on what they do to the stack, so any one of them will do.
+            if let Ok(Some(t)) = state.transitions.get(token) {
+                taken = taken.or(Some(t));
+            }
+        }
+        taken.map_or((0, 0), |t| stack_effect(t, stack))
+    }
+}
+
+/// How many regions taking this transition (with this stack) opens and closes.
+#[inline]
+fn stack_effect<I: Input, C: Ctrl<I>>(
+    transition: &Transition<I, C>,
+    stack: &[Transition<I, C>],
+) -> (usize, usize) {
+    match *transition {
+        Transition::Lateral { .. } => (0, 0),
+        Transition::Call { .. } => (1, 0),
+        // Closing a region continues wherever the call that opened it said to.
+        Transition::Return { .. } => match stack.split_last() {
+            Some((&Transition::Call { ref dst, .. }, below)) => {
+                let (pushes, pops) = stack_effect(dst, below);
+                (pushes, unwrap!(pops.checked_add(1)))
+            }
+            _ => (0, 1),
+        },
+    }
+}
+
+impl<I: Input, C: Ctrl<I>> Graph<I, C> {
+    /// Run this parser to completion as in `accept`, counting what it does along the way.
+    /// Counts up to the point of failure, if any, so a rejected run still says how much work it took.
+    #[inline]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn accept_counted<In: IntoIterator<Item = I>>(
+        &self,
+        input: In,
+    ) -> (Result<String, ParseError<I, C>>, RunStats) {
+        let mut stats = RunStats::default();
+        let mut tokens = input.into_iter();
+        let mut ctrl = self.initial.clone();
+        let mut stack = vec![];
+        let mut output_t = "()".to_owned();
+        let mut consumed: usize = 0;
+        let result = loop {
+            let maybe_token = tokens.next();
+            let (pushes, pops) = maybe_token
+                .as_ref()
+                .map_or((0, 0), |token| stats.read(self, &ctrl, token, &stack));
+            match step(self, &ctrl, maybe_token, &mut stack, &output_t, consumed) {
+                Err(e) => break Err(e),
+                Ok((None, o)) => break Ok(o),
+                Ok((Some(c), o)) => {
+                    stats.pushes = unwrap!(stats.pushes.checked_add(pushes));
+                    stats.pops = unwrap!(stats.pops.checked_add(pops));
+                    ctrl = c;
+                    output_t = o;
+                    consumed = consumed.saturating_add(1);
+                }
+            }
+        };
+        (result, stats)
+    }
+}
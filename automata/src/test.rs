@@ -16,6 +16,7 @@
 )]
 
 use crate::*;
+use std::collections::BTreeMap;
 
 #[inline]
 #[must_use]
@@ -35,12 +36,54 @@ fn splittable<I: Input, C: Ctrl<I>>(parser: &Graph<I, C>, input: &[I]) -> bool {
     }
 }
 
+/// Whether combining parsers failed because they genuinely conflict (e.g. two different updates on the same token)
+/// rather than because of a bug in the combinator (e.g. a malformed intermediate graph).
+#[inline]
+#[must_use]
+fn conflict<C: Ctrl<u8>>(e: &IllFormed<u8, C>) -> bool {
+    matches!(
+        *e,
+        IllFormed::IncompatibleCallbacks(..)
+            | IllFormed::IncompatibleCombinators(..)
+            | IllFormed::IncompatibleActions(..)
+            | IllFormed::AmbiguousRegions(..)
+            | IllFormed::TypeMismatch(..)
+            | IllFormed::WrongReturnType(..)
+            | IllFormed::ComposeTypeMismatch { .. }
+    )
+}
+
+/// Any single token whatsoever (like `.` in a regular expression).
+#[inline]
+#[must_use]
+fn any<I: Input>() -> Deterministic<I> {
+    Graph {
+        states: vec![
+            State {
+                transitions: Curry::Scrutinize {
+                    filter: RangeMap(BTreeMap::new()),
+                    fallback: None,
+                },
+                non_accepting: BTreeSet::new(),
+            },
+            State {
+                transitions: Curry::Wildcard(Transition::Lateral {
+                    dst: 0,
+                    update: None,
+                }),
+                non_accepting: iter::once(Rejection::ExpectedEndAfterAny).collect(),
+            },
+        ],
+        initial: 1,
+    }
+}
+
 #[cfg(feature = "quickcheck")]
 mod prop {
     use super::*;
     use core::num::NonZeroUsize;
     use quickcheck::*;
-    use std::{collections::BTreeSet, env, panic};
+    use std::{collections::BTreeSet, env};
 
     #[inline]
     fn gen_size() -> usize {
@@ -182,6 +225,81 @@ mod prop {
         }
     }
 
+    /// Check that `Eq`, `PartialOrd`, and `Ord` agree and that `Ord` is a total order on these three values.
+    #[inline]
+    fn ord_consistent<T: Ord>(a: &T, b: &T, c: &T) -> bool {
+        (a == b) == a.cmp(b).is_eq()
+            && a.partial_cmp(b) == Some(a.cmp(b))
+            && a.cmp(b) == b.cmp(a).reverse()
+            && a.cmp(a).is_eq()
+            && (!(a <= b && b <= c) || a <= c)
+            && (!(a == b && b == c) || a == c)
+    }
+
+    #[test]
+    fn ord_consistent_transition_deterministic() {
+        let gs = gen_size();
+        let tests = qc_tests();
+        for size in 0..tests {
+            // Few states, so that equal values show up often.
+            let n_states = nz(2.max((4 * size) / tests));
+            let rng = &mut Gen::new(2.max((gs * size * size) / (tests * tests)));
+            let [a, b, c] =
+                [(); 3].map(|()| Transition::<u8, usize>::arbitrary_given(n_states, rng));
+            assert!(ord_consistent(&a, &b, &c), "{a:?} {b:?} {c:?}");
+            assert!(ord_consistent(&a, &a.clone(), &b), "{a:?} {b:?}");
+        }
+    }
+
+    #[test]
+    fn ord_consistent_transition_nondeterministic() {
+        let gs = gen_size();
+        let tests = qc_tests();
+        for size in 0..tests {
+            let n_states = nz(2.max((4 * size) / tests));
+            let rng = &mut Gen::new(2.max((gs * size * size) / (tests * tests)));
+            let [a, b, c] =
+                [(); 3].map(|()| Transition::<u8, BTreeSet<usize>>::arbitrary_given(n_states, rng));
+            assert!(ord_consistent(&a, &b, &c), "{a:?} {b:?} {c:?}");
+            assert!(ord_consistent(&a, &a.clone(), &b), "{a:?} {b:?}");
+        }
+    }
+
+    #[test]
+    fn ord_consistent_curry() {
+        let gs = gen_size();
+        let tests = qc_tests();
+        for size in 0..tests {
+            let n_states = nz(2.max((4 * size) / tests));
+            let rng = &mut Gen::new(2.max((gs * size * size) / (tests * tests)));
+            let [a, b, c] = [(); 3].map(|()| Curry::<u8, usize>::arbitrary_given(n_states, rng));
+            assert!(ord_consistent(&a, &b, &c), "{a:?} {b:?} {c:?}");
+        }
+    }
+
+    /// Whether every way of asking what a parser accepts gives the same answer.
+    #[inline]
+    #[must_use]
+    fn reachability_agrees(d: &Deterministic<u8>) -> bool {
+        let shortest = d.shortest_accepted();
+        let examples = d.acceptance_examples();
+        let nothing = d.accepts_nothing();
+        shortest.is_some() != nothing
+            && examples.is_empty() == nothing
+            && examples
+                .iter()
+                .map(|&(_, ref input)| input)
+                .min_by(|lhs, rhs| lhs.len().cmp(&rhs.len()).then_with(|| lhs.cmp(rhs)))
+                == shortest.as_ref()
+            && examples.iter().all(|&(_, ref input)| {
+                !matches!(
+                    d.accept(input.iter().copied()),
+                    Err(ParseError::BadInput(..) | ParseError::Incomplete { .. })
+                )
+            })
+            && d.clone().trim().accepts_nothing() == nothing
+    }
+
     quickcheck! {
         fn range_both_contains_implies_intersection(
             v: u8,
@@ -241,17 +359,16 @@ mod prop {
             rhs: Deterministic<u8>,
             input: Vec<u8>
         ) -> bool {
-            if lhs.involves_any_fallback() || rhs.involves_any_fallback() {
-                return true;
-            }
-            let Ok(union) = panic::catch_unwind(|| lhs.clone() | rhs.clone()) else {
-                return true;
+            let union = match lhs.clone().try_union(rhs.clone()) {
+                Ok(union) => union,
+                // A genuine conflict, which can't depend on which parser comes first.
+                Err(e) => return conflict(&e) && rhs.try_union(lhs).is_err_and(|reverse| conflict(&reverse)),
             };
             if union.check().is_err() {
                 return false;
             }
             if union.determinize().is_err() {
-                return true;
+                return false;
             }
             let union_accept = union.accept(input.iter().copied());
             if !match (
@@ -266,20 +383,23 @@ mod prop {
                     }
                 }
                 (Err(e), Ok(out)) | (Ok(out), Err(e)) => match e {
-                    ParseError::BadInput(..) => union_accept == Ok(out),
+                    ParseError::BadInput(..) | ParseError::Incomplete { .. } => {
+                        union_accept == Ok(out)
+                    }
                     ParseError::BadParser(..) => unreachable!(),
                 },
                 (Err(ParseError::BadParser(..)), Err(..))
                 | (Err(..), Err(ParseError::BadParser(..))) => {
                     matches!(union_accept, Err(ParseError::BadParser(..)))
                 }
-                (Err(ParseError::BadInput(..)), Err(ParseError::BadInput(..))) => {
-                    union_accept.is_err()
-                }
+                (
+                    Err(ParseError::BadInput(..) | ParseError::Incomplete { .. }),
+                    Err(ParseError::BadInput(..) | ParseError::Incomplete { .. }),
+                ) => union_accept.is_err(),
             } {
                 return false;
             }
-            let Ok(symm) = panic::catch_unwind(|| rhs | lhs) else {
+            let Ok(symm) = rhs.try_union(lhs) else {
                 return false;
             };
             if symm.check().is_err() {
@@ -291,6 +411,24 @@ mod prop {
             union_accept == symm.accept(input.iter().copied())
         }
 
+        fn union_ranges_disjoint(lhs: Deterministic<u8>, rhs: Deterministic<u8>) -> bool {
+            lhs.try_union(rhs).map_or_else(|e| conflict(&e), |union| {
+                union.states.iter().all(|state| match state.transitions {
+                    Curry::Wildcard(..) => true,
+                    Curry::Scrutinize { ref filter, .. } => filter
+                        .0
+                        .keys()
+                        .zip(filter.0.keys().skip(1))
+                        .all(|(a, b)| a.last < b.first),
+                })
+            })
+        }
+
+        fn accepts_empty(parser: Nondeterministic<u8>) -> bool {
+            parser.check().is_err()
+                || parser.accepts_empty() == parser.accept(iter::empty()).is_ok()
+        }
+
         fn sort(parser: Nondeterministic<u8>, input: Vec<u8>) -> bool {
             let pre = parser.accept(input.iter().copied());
             let mut sorted = parser;
@@ -301,43 +439,246 @@ mod prop {
                 Err(ParseError::BadInput(_)) => {
                     matches!(post, Err(ParseError::BadInput(_)))
                 }
+                Err(e @ ParseError::Incomplete { .. }) => post == Err(e),
                 Err(ParseError::BadParser(_)) => true
             }
         }
 
         fn shr(lhs: Deterministic<u8>, rhs: Deterministic<u8>, input: Vec<u8>) -> bool {
-            if lhs.involves_any_fallback() || rhs.involves_any_fallback() {
-                return true;
-            }
             let splittable = (0..=input.len()).any(|i| {
                 lhs.accept(input[..i].iter().copied()).is_ok() &&
                 rhs.accept(input[i..].iter().copied()).is_ok()
             });
-            let Ok(concat) = panic::catch_unwind(|| lhs >> rhs) else {
-                return true;
+            let concat = match lhs.try_concat(rhs) {
+                Ok(concat) => concat,
+                Err(e) => return conflict(&e),
             };
             if concat.check().is_err() {
                 return false;
             }
             if concat.determinize().is_err() {
-                return true;
+                return false;
             }
             concat.accept(input).is_ok() == splittable
         }
 
-        fn star(d: Deterministic<u8>, input: Vec<u8>) -> bool {
-            if d.involves_any_fallback() {
+        fn concat_many_matches_fold(parsers: Vec<Deterministic<u8>>) -> bool {
+            if parsers.len() > 4 {
                 return true;
             }
-            if !splittable(&d, &input) {
+            let mut iter = parsers.clone().into_iter();
+            let Some(first) = iter.next() else {
+                return Deterministic::concat_many(parsers).is_none();
+            };
+            let folded = match iter.try_fold(first, Deterministic::try_concat) {
+                Ok(folded) => folded,
+                Err(e) => return conflict(&e),
+            };
+            Deterministic::concat_many(parsers) == Some(folded)
+        }
+
+        fn union_many_matches_fold(parsers: Vec<Deterministic<u8>>) -> bool {
+            if parsers.len() > 4 {
+                return true;
+            }
+            let mut iter = parsers.clone().into_iter();
+            let Some(first) = iter.next() else {
+                return Deterministic::union_many(parsers).is_none();
+            };
+            let folded = match iter.try_fold(first, Deterministic::try_union) {
+                Ok(folded) => folded,
+                Err(e) => return conflict(&e),
+            };
+            Deterministic::union_many(parsers) == Some(folded)
+        }
+
+        fn shl_reads_like_shr(lhs: Deterministic<u8>, rhs: Deterministic<u8>, input: Vec<u8>) -> bool {
+            let Ok(discarded) = rhs.clone().discard() else {
+                return rhs.involves_calls();
+            };
+            let kept = match lhs.clone().try_concat(discarded) {
+                Ok(kept) => kept,
+                Err(e) => return conflict(&e),
+            };
+            if kept.involves_updates() && !lhs.involves_updates() {
+                return false;
+            }
+            let concat = match lhs.try_concat(rhs) {
+                Ok(concat) => concat,
+                Err(e) => return conflict(&e),
+            };
+            match concat.accept(input.iter().copied()) {
+                Ok(_) => kept.accept(input).is_ok(),
+                Err(ParseError::BadParser(..)) => true,
+                Err(_) => kept.accept(input).is_err(),
+            }
+        }
+
+        fn discard_accepts_the_same(d: Deterministic<u8>, input: Vec<u8>) -> bool {
+            let Ok(discarded) = d.clone().discard() else {
+                return d.involves_calls();
+            };
+            !discarded.involves_updates() && match d.accept(input.iter().copied()) {
+                Ok(_) => discarded.accept(input) == Ok("()".to_owned()),
+                Err(ParseError::BadParser(..)) => true,
+                Err(e) => discarded.accept(input) == Err(e),
+            }
+        }
+
+        fn shl_and_skip_discard_trailing_input(
+            lhs: Deterministic<u8>,
+            skip: Deterministic<u8>,
+            lhs_input: Vec<u8>,
+            skip_input: Vec<u8>
+        ) -> bool {
+            if skip.involves_calls() {
+                return true;
+            }
+            if lhs.accept(lhs_input.iter().copied()).is_err()
+                || skip.accept(skip_input.iter().copied()).is_err()
+            {
+                return true;
+            }
+            let input: Vec<u8> = lhs_input.into_iter().chain(skip_input).collect();
+            let shl_ok = match skip.clone().try_star() {
+                Err(e) => conflict(&e),
+                Ok(star) => lhs
+                    .clone()
+                    .try_concat(star.discard().unwrap())
+                    .map_or_else(|e| conflict(&e), |shl| shl.accept(input.iter().copied()).is_ok()),
+            };
+            let skip_ok = lhs.with_skip(&skip).map_or_else(
+                |e| conflict(&e) || matches!(e, IllFormed::SkipOverlap(..)),
+                |skipping| skipping.accept(input.iter().copied()).is_ok(),
+            );
+            shl_ok && skip_ok
+        }
+
+        fn union_commutes_exactly(lhs: Deterministic<u8>, rhs: Deterministic<u8>) -> bool {
+            match (lhs.clone().try_union(rhs.clone()), rhs.try_union(lhs)) {
+                (Ok(forward), Ok(backward)) => forward == backward,
+                (Err(forward), Err(backward)) => conflict(&forward) && conflict(&backward),
+                (Ok(..), Err(..)) | (Err(..), Ok(..)) => false,
+            }
+        }
+
+        fn disjoint_union_matches_determinized(lhs: Deterministic<u8>, rhs: Deterministic<u8>) -> bool {
+            let Some(fast) = lhs.disjoint_union(&rhs) else {
                 return true;
+            };
+            lhs.determinized_union(rhs) == Ok(fast)
+        }
+
+        fn union_associates_exactly(a: Deterministic<u8>, b: Deterministic<u8>, c: Deterministic<u8>) -> bool {
+            let left = a.clone().try_union(b.clone()).and_then(|ab| ab.try_union(c.clone()));
+            let right = b.try_union(c).and_then(|bc| a.try_union(bc));
+            match (left, right) {
+                (Ok(l), Ok(r)) => l == r,
+                (Err(l), Err(r)) => conflict(&l) && conflict(&r),
+                (Ok(..), Err(..)) | (Err(..), Ok(..)) => false,
             }
-            let Ok(star) = panic::catch_unwind(|| d.star()) else {
+        }
+
+        fn sort_ignores_numbering(d: Deterministic<u8>, seed: usize) -> bool {
+            // Unreachable states have no canonical place, so leave them out.
+            let d = d.trim();
+            let n = d.states.len();
+            let Some(shift) = seed.checked_rem(n) else {
+                return true;
+            };
+            let permute = |i: usize| (i + shift).checked_rem(n).unwrap();
+            let mut permuted = d.clone().map_indices(permute);
+            let mut states: Vec<_> = permuted.states.drain(..).enumerate().collect();
+            states.sort_by_key(|&(i, _)| permute(i));
+            permuted.states = states.into_iter().map(|(_, s)| s).collect();
+            let mut original = d;
+            original.sort();
+            permuted.sort();
+            original == permuted
+        }
+
+        fn star(d: Deterministic<u8>, input: Vec<u8>) -> bool {
+            if !splittable(&d, &input) {
                 return true;
+            }
+            let star = match d.try_star() {
+                Ok(star) => star,
+                Err(e) => return conflict(&e),
             };
             star.accept(input).is_ok()
         }
 
+        fn product_with(
+            lhs: Deterministic<u8>,
+            rhs: Deterministic<u8>,
+            input: Vec<u8>
+        ) -> bool {
+            let Ok(and) = lhs.product_with(&rhs, |x, y| x && y) else {
+                return true; // regions
+            };
+            let in_lhs = lhs.accept(input.iter().copied()).is_ok();
+            let in_rhs = rhs.accept(input.iter().copied()).is_ok();
+            let minus = lhs.minus(&rhs).unwrap();
+            let neither = lhs.product_with(&rhs, |x, y| !x && !y).unwrap();
+            let either = lhs.product_with(&rhs, |x, y| x || y).unwrap();
+            let not_either = either.product_with(&either, |x, _| !x).unwrap();
+            and.accept(input.iter().copied()).is_ok() == (in_lhs && in_rhs)
+                && minus.accept(input.iter().copied()).is_ok() == (in_lhs && !in_rhs)
+                && either.accept(input.iter().copied()).is_ok() == (in_lhs || in_rhs)
+                && neither.equivalent(&not_either) == Ok(true)
+                && lhs.equivalent(&lhs) == Ok(true)
+        }
+
+        fn capabilities_of_union(lhs: Nondeterministic<u8>, rhs: Nondeterministic<u8>) -> bool {
+            let expected = lhs.capabilities() | rhs.capabilities();
+            let size = lhs.states.len();
+            let mut union = lhs;
+            let shifted = rhs.map_indices(|i| i.saturating_add(size));
+            union.states.extend(shifted.states);
+            union.initial.extend(shifted.initial);
+            union.capabilities() == expected
+        }
+
+        fn range_map_merge_with(lhs: Deterministic<u8>, rhs: Deterministic<u8>) -> bool {
+            let (
+                &Curry::Scrutinize { filter: ref l_filter, .. },
+                &Curry::Scrutinize { filter: ref r_filter, .. },
+            ) = (
+                &lhs.states[lhs.initial].transitions,
+                &rhs.states[rhs.initial].transitions,
+            ) else {
+                return true;
+            };
+            let Ok(merged) = l_filter.merge_with(r_filter, Merge::merge) else {
+                return true; // e.g. superposition
+            };
+            (0..=u8::MAX).all(|token| {
+                let expected = match (l_filter.get(&token).unwrap(), r_filter.get(&token).unwrap()) {
+                    (Some(l), Some(r)) => Some(l.clone().merge(r.clone()).unwrap()),
+                    (l, r) => l.or(r).cloned(),
+                };
+                merged.get(&token).unwrap() == expected.as_ref()
+            })
+        }
+
+        fn reachability_analyses_agree(d: Deterministic<u8>) -> bool {
+            reachability_agrees(&d)
+                && reachability_agrees(&any())
+                && match any().try_concat(d) {
+                    Ok(after_any) => reachability_agrees(&after_any),
+                    Err(e) => conflict(&e),
+                }
+        }
+
+        fn arbitrary_ranges_nonempty(d: Deterministic<u8>) -> bool {
+            d.states.iter().all(|state| match state.transitions {
+                Curry::Wildcard(..) => true,
+                Curry::Scrutinize { ref filter, .. } => {
+                    filter.0.keys().all(|range| range.first <= range.last)
+                }
+            })
+        }
+
         // TODO:
         /*
         fn star_star_identity(d: Deterministic<u8>, input: Vec<u8>) -> bool {
@@ -373,16 +714,17 @@ mod reduced {
     }
 
     fn union(lhs: Deterministic<u8>, rhs: Deterministic<u8>, input: Vec<u8>) {
-        if lhs.involves_any_fallback() || rhs.involves_any_fallback() {
-            return;
-        }
-        let Ok(union) = panic::catch_unwind(|| lhs.clone() | rhs.clone()) else {
-            return;
+        let union = match lhs.clone().try_union(rhs.clone()) {
+            Ok(union) => union,
+            Err(e) => {
+                // A genuine conflict, which can't depend on which parser comes first.
+                assert!(conflict(&e), "{e}");
+                assert!(rhs.try_union(lhs).is_err_and(|reverse| conflict(&reverse)));
+                return;
+            }
         };
         union.check().unwrap();
-        if union.determinize().is_err() {
-            return;
-        }
+        drop(union.determinize().unwrap());
         {
             println!();
             println!("LHS:");
@@ -423,50 +765,49 @@ mod reduced {
                 }
             }
             (Err(e), Ok(out)) | (Ok(out), Err(e)) => match e {
-                ParseError::BadInput(..) => assert_eq!(union_accept, Ok(out)),
+                ParseError::BadInput(..) | ParseError::Incomplete { .. } => {
+                    assert_eq!(union_accept, Ok(out));
+                }
                 ParseError::BadParser(..) => unreachable!(),
             },
             (Err(ParseError::BadParser(..)), Err(..))
             | (Err(..), Err(ParseError::BadParser(..))) => {
                 assert!(matches!(union_accept, Err(ParseError::BadParser(..))));
             }
-            (Err(ParseError::BadInput(..)), Err(ParseError::BadInput(..))) => {
+            (
+                Err(ParseError::BadInput(..) | ParseError::Incomplete { .. }),
+                Err(ParseError::BadInput(..) | ParseError::Incomplete { .. }),
+            ) => {
                 let _ = union_accept.as_ref().unwrap_err();
             }
         }
-        let symm = rhs | lhs;
+        let symm = rhs.try_union(lhs).unwrap();
         symm.check().unwrap();
         drop(symm.determinize().unwrap());
         assert_eq!(union_accept, symm.accept(input));
     }
 
     fn shr(lhs: Deterministic<u8>, rhs: Deterministic<u8>, input: Vec<u8>) {
-        if lhs.involves_any_fallback() || rhs.involves_any_fallback() {
-            return;
-        }
         let splittable = (0..=input.len()).any(|i| {
             lhs.accept(input[..i].iter().copied()).is_ok()
                 && rhs.accept(input[i..].iter().copied()).is_ok()
         });
-        let Ok(concat) = panic::catch_unwind(|| lhs >> rhs) else {
-            return;
+        let concat = match lhs.try_concat(rhs) {
+            Ok(concat) => concat,
+            Err(e) => return assert!(conflict(&e), "{e}"),
         };
         concat.check().unwrap();
-        if concat.determinize().is_err() {
-            return;
-        }
+        drop(concat.determinize().unwrap());
         assert_eq!(concat.accept(input).is_ok(), splittable);
     }
 
     fn star(d: Deterministic<u8>, input: Vec<u8>) {
-        if d.involves_any_fallback() {
-            return;
-        }
         if !splittable(&d, &input) {
             return;
         }
-        let Ok(star) = panic::catch_unwind(|| d.star()) else {
-            return;
+        let star = match d.try_star() {
+            Ok(star) => star,
+            Err(e) => return assert!(conflict(&e), "{e}"),
         };
         println!("{star:?}");
         drop(star.accept(input).unwrap());
@@ -488,80 +829,114 @@ mod reduced {
 
     #[test]
     fn determinize_identity_1() {
-        determinize_identity(
-            &Graph {
+        let d = Graph {
+            states: vec![
+                State {
+                    transitions: Curry::Wildcard(Transition::Lateral {
+                        dst: 4,
+                        update: None,
+                    }),
+                    non_accepting: BTreeSet::new(),
+                },
+                State {
+                    transitions: Curry::Wildcard(Transition::Return { region: "region" }),
+                    non_accepting: BTreeSet::new(),
+                },
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(BTreeMap::new()),
+                        fallback: None,
+                    },
+                    non_accepting: BTreeSet::new(),
+                },
+                State {
+                    transitions: Curry::Wildcard(Transition::Return { region: "region" }),
+                    non_accepting: iter::once("".into()).collect(),
+                },
+                State {
+                    transitions: Curry::Wildcard(Transition::Call {
+                        region: "region",
+                        detour: 0,
+                        dst: Box::new(Transition::Lateral {
+                            dst: 1,
+                            update: None,
+                        }),
+                        combine: ff!(|(), ()| ()),
+                    }),
+                    non_accepting: BTreeSet::new(),
+                },
+            ],
+            initial: 0,
+        };
+        // Unreachable states go, and the rest keep their wildcards.
+        assert_eq!(
+            d.determinize(),
+            Ok(Graph {
                 states: vec![
                     State {
                         transitions: Curry::Wildcard(Transition::Lateral {
-                            dst: 4,
+                            dst: 1,
                             update: None,
                         }),
                         non_accepting: BTreeSet::new(),
                     },
-                    State {
-                        transitions: Curry::Wildcard(Transition::Return { region: "region" }),
-                        non_accepting: BTreeSet::new(),
-                    },
-                    State {
-                        transitions: Curry::Scrutinize {
-                            filter: RangeMap(BTreeMap::new()),
-                            fallback: None,
-                        },
-                        non_accepting: BTreeSet::new(),
-                    },
-                    State {
-                        transitions: Curry::Wildcard(Transition::Return { region: "region" }),
-                        non_accepting: iter::once(String::new()).collect(),
-                    },
                     State {
                         transitions: Curry::Wildcard(Transition::Call {
                             region: "region",
                             detour: 0,
                             dst: Box::new(Transition::Lateral {
-                                dst: 1,
+                                dst: 2,
                                 update: None,
                             }),
-                            combine: FF {
-                                src: "|(), ()| ()".to_owned(),
-                                lhs_t: "()".to_owned(),
-                                rhs_t: "()".to_owned(),
-                                output_t: "()".to_owned(),
-                            },
+                            combine: ff!(|(), ()| ()),
                         }),
                         non_accepting: BTreeSet::new(),
                     },
+                    State {
+                        transitions: Curry::Wildcard(Transition::Return { region: "region" }),
+                        non_accepting: BTreeSet::new(),
+                    },
                 ],
                 initial: 0,
-            },
-            vec![],
+            }),
         );
+        determinize_identity(&d, vec![]);
     }
 
     #[test]
     fn union_1() {
-        union(
-            Graph {
-                states: vec![State {
-                    transitions: Curry::Scrutinize {
-                        filter: RangeMap(BTreeMap::new()),
-                        fallback: Some(Transition::Lateral {
-                            dst: 0,
-                            update: None,
-                        }),
-                    },
-                    non_accepting: BTreeSet::new(),
-                }],
-                initial: 0,
-            },
-            Graph {
-                states: vec![State {
-                    transitions: Curry::Wildcard(Transition::Return { region: "region" }),
-                    non_accepting: BTreeSet::new(),
-                }],
-                initial: 0,
-            },
-            vec![0],
+        let lhs = Graph {
+            states: vec![State {
+                transitions: Curry::Scrutinize {
+                    filter: RangeMap(BTreeMap::new()),
+                    fallback: Some(Transition::Lateral {
+                        dst: 0,
+                        update: None,
+                    }),
+                },
+                non_accepting: BTreeSet::new(),
+            }],
+            initial: 0,
+        };
+        let rhs = Graph {
+            states: vec![State {
+                transitions: Curry::Wildcard(Transition::Return { region: "region" }),
+                non_accepting: BTreeSet::new(),
+            }],
+            initial: 0,
+        };
+        // The wildcard merges into the fallback, where looping and returning genuinely conflict on every token.
+        assert_eq!(
+            lhs.clone().try_union(rhs.clone()),
+            Err(IllFormed::IncompatibleActions(
+                Box::new(Transition::Lateral {
+                    dst: iter::once(1).collect(),
+                    update: None,
+                }),
+                Box::new(Transition::Return { region: "region" }),
+            )),
         );
+        union(lhs, rhs, vec![0]);
     }
 
     #[test]
@@ -716,4 +1091,3460 @@ mod reduced {
             vec![0, 0],
         );
     }
+
+    #[test]
+    fn empty_range() {
+        let parser = Graph {
+            states: vec![
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(
+                            iter::once((
+                                Range {
+                                    first: b'z',
+                                    last: b'a',
+                                },
+                                Transition::Lateral {
+                                    dst: 1,
+                                    update: None,
+                                },
+                            ))
+                            .collect(),
+                        ),
+                        fallback: None,
+                    },
+                    non_accepting: BTreeSet::new(),
+                },
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(BTreeMap::new()),
+                        fallback: None,
+                    },
+                    non_accepting: BTreeSet::new(),
+                },
+            ],
+            initial: 0,
+        };
+        assert_eq!(
+            parser.check(),
+            Err(IllFormed::EmptyRange {
+                state: 0,
+                range: Range {
+                    first: b'z',
+                    last: b'a',
+                },
+            }),
+        );
+    }
+}
+
+mod unit {
+    use super::*;
+    use core::{cell::RefCell, num::NonZeroUsize, ptr};
+    use std::{
+        borrow::Cow,
+        collections::{BTreeMap, BTreeSet},
+        env, fs, panic, process,
+        process::Command,
+    };
+
+    /// Accept exactly this sequence of characters.
+    fn literal(s: &str) -> Deterministic<char> {
+        Graph {
+            states: s
+                .chars()
+                .enumerate()
+                .map(|(i, c)| State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(
+                            iter::once((
+                                Range::unit(c),
+                                Transition::Lateral {
+                                    dst: i + 1,
+                                    update: None,
+                                },
+                            ))
+                            .collect(),
+                        ),
+                        fallback: None,
+                    },
+                    non_accepting: iter::once(format!("Expected `{c}`").into()).collect(),
+                })
+                .chain(iter::once(State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(BTreeMap::new()),
+                        fallback: None,
+                    },
+                    non_accepting: BTreeSet::new(),
+                }))
+                .collect(),
+            initial: 0,
+        }
+    }
+
+    /// Accept one or more ASCII letters.
+    fn word() -> Deterministic<char> {
+        let letters = || Curry::Scrutinize {
+            filter: RangeMap(
+                [
+                    (
+                        Range {
+                            first: 'A',
+                            last: 'Z',
+                        },
+                        Transition::Lateral {
+                            dst: 1,
+                            update: None,
+                        },
+                    ),
+                    (
+                        Range {
+                            first: 'a',
+                            last: 'z',
+                        },
+                        Transition::Lateral {
+                            dst: 1,
+                            update: None,
+                        },
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            fallback: None,
+        };
+        Graph {
+            states: vec![
+                State {
+                    transitions: letters(),
+                    non_accepting: iter::once("Expected a letter".into()).collect(),
+                },
+                State {
+                    transitions: letters(),
+                    non_accepting: BTreeSet::new(),
+                },
+            ],
+            initial: 0,
+        }
+    }
+
+    #[test]
+    fn accept_case_insensitive() {
+        let get = literal("get");
+        assert_eq!(
+            get.accept("GET".chars()),
+            Err(ParseError::BadInput(InputError::Absurd)),
+        );
+        assert_eq!(
+            get.accept_case_insensitive("GET".chars()),
+            Ok("()".to_owned())
+        );
+        assert_eq!(
+            get.accept_case_insensitive("gEt".chars()),
+            Ok("()".to_owned())
+        );
+        assert_eq!(
+            get.accept_case_insensitive("GETS".chars()),
+            Err(ParseError::BadInput(InputError::Absurd)),
+        );
+    }
+
+    #[test]
+    fn region_subgraph_round_trip() {
+        let parser = dyck_d();
+        let region = parser.region_subgraph("parentheses").unwrap();
+        assert_eq!(region, dyck_d());
+        assert_eq!(
+            parser.states_in_region("parentheses").unwrap(),
+            iter::once(0).collect()
+        );
+        let mut spliced = parser.clone();
+        spliced
+            .set_region_subgraph("parentheses", region.minimize())
+            .unwrap();
+        spliced.check().unwrap();
+        assert_eq!(spliced.states.len(), 2);
+        for input in ["", "()", "(())()", "(()", ")"] {
+            assert_eq!(
+                spliced.accept(input.chars()).is_ok(),
+                parser.accept(input.chars()).is_ok(),
+            );
+        }
+        assert_eq!(spliced.minimize(), parser);
+        assert_eq!(spliced.region_subgraph("parentheses").unwrap(), parser);
+        // A typo in the region's name shouldn't silently do nothing.
+        let mut typo = parser.clone();
+        assert_eq!(
+            typo.set_region_subgraph("parenthesis", region),
+            Err(IllFormed::RegionDNE("parenthesis")),
+        );
+        assert_eq!(typo, parser);
+    }
+
+    #[test]
+    fn region_subgraph_nonexistent() {
+        assert_eq!(
+            dyck_d().region_subgraph("brackets"),
+            Err(IllFormed::RegionDNE("brackets")),
+        );
+    }
+
+    #[test]
+    fn regions_compare_by_value() {
+        let leak = || -> &'static str { Box::leak("parentheses".to_owned().into_boxed_str()) };
+        let (lhs, rhs) = (leak(), leak());
+        assert!(!ptr::eq(lhs, rhs));
+        let close = |region| Transition::<char, usize>::Return { region };
+        assert_eq!(close(lhs), close(rhs));
+        assert_eq!(close(lhs).merge(close(rhs)), Ok(close("parentheses")));
+        let parser = dyck_d();
+        assert_eq!(parser.region_subgraph(lhs), parser.region_subgraph(rhs));
+        assert_eq!(
+            parser.states_in_region(lhs),
+            parser.states_in_region("parentheses"),
+        );
+        let mut renamed = parser.clone();
+        for state in &mut renamed.states {
+            for transition in state.transitions.values_mut() {
+                match *transition {
+                    Transition::Call { ref mut region, .. } => *region = lhs,
+                    Transition::Return { ref mut region } => *region = rhs,
+                    Transition::Lateral { .. } => {}
+                }
+            }
+        }
+        assert_eq!(renamed.check(), Ok(()));
+        assert_eq!(renamed, parser);
+        assert_eq!(renamed.accept("(()())".chars()), Ok("()".to_owned()));
+        assert_eq!((renamed | parser.clone()).determinize(), Ok(parser));
+    }
+
+    #[test]
+    fn determinize_rejects_an_accepting_state_of_two_types() {
+        let on = |token: u8, update| {
+            (
+                Range::unit(token),
+                Transition::Lateral {
+                    dst: iter::once(1).collect(),
+                    update,
+                },
+            )
+        };
+        // Both inputs end in the same accepting state, but `a` leaves `()` there and `b` leaves a `u8`.
+        // (States in one subset never disagree like this, since the same input reaches them through the same updates.)
+        let conflict: Nondeterministic<u8> = Graph {
+            states: vec![
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(
+                            [on(b'a', None), on(b'b', Some(update!(|(), _| 0_u8)))]
+                                .into_iter()
+                                .collect(),
+                        ),
+                        fallback: None,
+                    },
+                    non_accepting: iter::once("Expected `a` or `b`".into()).collect(),
+                },
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(BTreeMap::new()),
+                        fallback: None,
+                    },
+                    non_accepting: BTreeSet::new(),
+                },
+            ],
+            initial: iter::once(0).collect(),
+        };
+        assert_eq!(
+            conflict.determinize(),
+            Err(IllFormed::ComposeTypeMismatch {
+                lhs_out: "u8".to_owned(),
+                rhs_in: "()".to_owned(),
+            }),
+        );
+    }
+
+    #[test]
+    fn accept_all() {
+        let on_a = |dst: &[usize], update| Curry::Scrutinize {
+            filter: RangeMap(
+                iter::once((
+                    Range::unit('a'),
+                    Transition::Lateral {
+                        dst: dst.iter().copied().collect(),
+                        update: Some(update),
+                    },
+                ))
+                .collect(),
+            ),
+            fallback: None,
+        };
+        // After one `a`, we could be counting up or multiplying by ten.
+        let ambiguous: Nondeterministic<char> = Graph {
+            states: vec![
+                State {
+                    transitions: on_a(&[1, 2], update!(|(), _c: char| 1_usize)),
+                    non_accepting: iter::once("Expected `a`".into()).collect(),
+                },
+                State {
+                    transitions: on_a(&[3], update!(|n: usize, _c: char| n + 1)),
+                    non_accepting: iter::once("Expected `a`".into()).collect(),
+                },
+                State {
+                    transitions: on_a(&[3], update!(|n: usize, _c: char| n * 10)),
+                    non_accepting: iter::once("Expected `a`".into()).collect(),
+                },
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(BTreeMap::new()),
+                        fallback: None,
+                    },
+                    non_accepting: BTreeSet::new(),
+                },
+            ],
+            initial: iter::once(0).collect(),
+        };
+        assert!(matches!(ambiguous.determinize(), Err(..)));
+        assert_eq!(
+            ambiguous.accept_all::<usize, _>("aa".chars()),
+            Ok(vec![2, 10])
+        );
+        assert_eq!(ambiguous.accept_all::<usize, _>("a".chars()), Ok(vec![]));
+        assert_eq!(ambiguous.accept_all::<usize, _>("aaa".chars()), Ok(vec![]));
+        assert_eq!(
+            ambiguous.accept_all::<u8, _>("aa".chars()),
+            Err(IllFormed::WrongReturnType(
+                "usize".to_owned(),
+                "u8".to_owned()
+            )),
+        );
+        // Deterministic parsers have at most one output, the same as `accept`.
+        let dyck = dyck_d();
+        assert_eq!(dyck.accept_all::<(), _>("(()())".chars()), Ok(vec![()]));
+        assert_eq!(dyck.accept_all::<(), _>("(()".chars()), Ok(vec![]));
+    }
+
+    #[test]
+    fn accepts_empty_from_any_initial_state() {
+        let state = |accepting: bool| State {
+            transitions: Curry::Scrutinize {
+                filter: RangeMap(BTreeMap::new()),
+                fallback: None,
+            },
+            non_accepting: if accepting {
+                BTreeSet::new()
+            } else {
+                iter::once("Nope".into()).collect()
+            },
+        };
+        let mut parser: Nondeterministic<u8> = Graph {
+            states: vec![state(false), state(true), state(false)],
+            initial: [0, 1].into_iter().collect(),
+        };
+        assert!(parser.accepts_empty());
+        assert_eq!(parser.accept(iter::empty()), Ok("()".to_owned()));
+        parser.initial = [0, 2].into_iter().collect();
+        assert!(!parser.accepts_empty());
+        assert_eq!(
+            parser.accept(iter::empty()),
+            Err(ParseError::BadInput(InputError::NotAccepting)),
+        );
+    }
+
+    #[test]
+    fn invalid_initial() {
+        let mut d = dyck_d();
+        d.initial = d.states.len();
+        assert_eq!(
+            d.check(),
+            Err(IllFormed::InitialOutOfBounds(d.states.len()))
+        );
+        let mut nd = dyck_nd();
+        nd.initial = [0, 42].into_iter().collect();
+        assert_eq!(nd.check(), Err(IllFormed::InitialOutOfBounds(42)));
+        nd.initial = BTreeSet::new();
+        assert_eq!(nd.check(), Err(IllFormed::EmptyInitial));
+        assert!(matches!(
+            panic::catch_unwind(|| nd.accept(iter::empty())),
+            Err(..)
+        ));
+    }
+
+    #[test]
+    fn compose_type_mismatch() {
+        let one = |c, update| -> Deterministic<char> {
+            Graph {
+                states: vec![
+                    State {
+                        transitions: Curry::Scrutinize {
+                            filter: RangeMap(
+                                iter::once((
+                                    Range::unit(c),
+                                    Transition::Lateral {
+                                        dst: 1,
+                                        update: Some(update),
+                                    },
+                                ))
+                                .collect(),
+                            ),
+                            fallback: None,
+                        },
+                        non_accepting: iter::once(Rejection::UnexpectedToken).collect(),
+                    },
+                    State {
+                        transitions: Curry::Scrutinize {
+                            filter: RangeMap(BTreeMap::new()),
+                            fallback: None,
+                        },
+                        non_accepting: BTreeSet::new(),
+                    },
+                ],
+                initial: 0,
+            }
+        };
+        let count = one('a', update!(|(), _| 5_usize));
+        let byte = one('b', update!(|x: u8, _| x));
+        let Err(panicked) = panic::catch_unwind(|| count.clone() >> byte) else {
+            panic!("Composed a `usize` into a `u8`");
+        };
+        let message = panicked.downcast::<String>().unwrap();
+        assert!(
+            message.contains("outputs `usize` into another that expects `u8`"),
+            "{message}",
+        );
+        let mut glued = count.clone();
+        glued.states[1].transitions = Curry::Scrutinize {
+            filter: RangeMap(
+                iter::once((
+                    Range::unit('b'),
+                    Transition::Lateral {
+                        dst: 2,
+                        update: None,
+                    },
+                ))
+                .collect(),
+            ),
+            fallback: None,
+        };
+        glued.states.push(
+            one('c', update!(|x: u8, _| x))
+                .states
+                .swap_remove(0)
+                .map_indices(|_| 1),
+        );
+        assert_eq!(
+            glued.check(),
+            Err(IllFormed::ComposeTypeMismatch {
+                lhs_out: "usize".to_owned(),
+                rhs_in: "u8".to_owned(),
+            }),
+        );
+        let counted = count >> one('b', update!(|x: usize, _| x));
+        assert_eq!(counted.check(), Ok(()));
+        assert_eq!(counted.accept("ab".chars()), Ok("usize".to_owned()));
+    }
+
+    #[test]
+    fn product_splits_ranges() {
+        let lowercase: Deterministic<u8> = Graph {
+            states: vec![
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(
+                            iter::once((
+                                Range {
+                                    first: b'a',
+                                    last: b'z',
+                                },
+                                Transition::Lateral {
+                                    dst: 1,
+                                    update: None,
+                                },
+                            ))
+                            .collect(),
+                        ),
+                        fallback: None,
+                    },
+                    non_accepting: iter::once("Expected a letter".into()).collect(),
+                },
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(BTreeMap::new()),
+                        fallback: None,
+                    },
+                    non_accepting: BTreeSet::new(),
+                },
+            ],
+            initial: 0,
+        };
+        let m = Graph {
+            states: vec![
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(
+                            iter::once((
+                                Range::unit(b'm'),
+                                Transition::Lateral {
+                                    dst: 1,
+                                    update: None,
+                                },
+                            ))
+                            .collect(),
+                        ),
+                        fallback: None,
+                    },
+                    non_accepting: iter::once("Expected `m`".into()).collect(),
+                },
+                lowercase.states[1].clone(),
+            ],
+            initial: 0,
+        };
+        let not_m = lowercase.minus(&m).unwrap();
+        for token in 0..=u8::MAX {
+            assert_eq!(
+                not_m.accept(iter::once(token)).is_ok(),
+                token.is_ascii_lowercase() && token != b'm',
+            );
+        }
+        assert_eq!((lowercase.clone() & m.clone()).equivalent(&m), Ok(true));
+        assert_eq!(lowercase.equivalent(&m), Ok(false));
+        assert_eq!(
+            dyck_d().product_with(&dyck_d(), |a, b| a && b),
+            Err(IllFormed::ProductOverRegion("parentheses")),
+        );
+    }
+
+    #[test]
+    fn run_into() {
+        let word = word();
+        let mut matched = vec![];
+        assert_eq!(
+            word.run_into("abc".chars(), &mut matched, |v, &c| v.push(c)),
+            Ok("()".to_owned()),
+        );
+        assert_eq!(matched, ['a', 'b', 'c']);
+        matched.clear();
+        assert_eq!(
+            word.run_into("ab1".chars(), &mut matched, |v, &c| v.push(c)),
+            Err(ParseError::BadInput(InputError::Absurd)),
+        );
+        assert_eq!(matched, ['a', 'b']);
+    }
+
+    #[test]
+    fn structurally_eq_ignoring_region_names() {
+        fn rename(t: &mut Transition<char, usize>) {
+            match *t {
+                Transition::Lateral { .. } => {}
+                Transition::Call {
+                    ref mut region,
+                    ref mut dst,
+                    ..
+                } => {
+                    *region = "parens";
+                    rename(dst);
+                }
+                Transition::Return { ref mut region } => *region = "parens",
+            }
+        }
+        let original = dyck_d();
+        let mut renamed = original.clone();
+        for state in &mut renamed.states {
+            for t in state.transitions.values_mut() {
+                rename(t);
+            }
+        }
+        assert_ne!(original, renamed);
+        assert!(original.structurally_eq_ignoring_region_names(&renamed));
+        assert!(!original.structurally_eq_ignoring_region_names(&literal("()")));
+    }
+
+    #[test]
+    fn shr_associative_and_linear() {
+        let (a, b, c) = (literal("ab"), word(), literal("c"));
+        let mut left = (a.clone() >> b.clone()) >> c.clone();
+        let mut right = a >> (b >> c);
+        left.sort();
+        right.sort();
+        assert_eq!(left, right);
+        // One state per character plus one to accept, without any epsilon hubs piling up:
+        let mut chain = literal("x");
+        for n in 2..=20 {
+            chain = chain >> literal("x");
+            assert_eq!(chain.states.len(), n + 1);
+        }
+    }
+
+    #[test]
+    fn step_budget() {
+        let word = word();
+        for input in ["", "a", "hello", "hello world"] {
+            for budget in 1..=4 {
+                let mut run = input.chars().run(&word);
+                let result = loop {
+                    if let Progress::Done(result) = run.step_budget(budget) {
+                        break result;
+                    }
+                };
+                assert_eq!(result, word.accept(input.chars()));
+            }
+        }
+    }
+
+    #[test]
+    fn subset() {
+        let word = word();
+        let keyword = literal("in");
+        assert_eq!(keyword.is_subset_of(&word), Ok(()));
+        assert!(word.is_subset_of(&keyword).is_err());
+        assert_eq!(word.is_subset_of(&word), Ok(()));
+    }
+
+    #[test]
+    fn acceptance_examples() {
+        let tuple = Deterministic::from_ebnf(
+            r#"tuple = "(" ")" | "(" "a" "," ")" "!"? | "(" "a" "," "b" ")" "?"*;"#,
+        )
+        .unwrap()
+        .remove("tuple")
+        .unwrap();
+        let examples: Vec<String> = tuple
+            .acceptance_examples()
+            .into_iter()
+            .map(|(_, input)| input.into_iter().collect())
+            .collect();
+        assert_eq!(examples, ["()", "(a,)", "(a,b)"]);
+        let dyck = dyck_d();
+        let balanced = dyck.acceptance_examples();
+        assert_eq!(balanced.first(), Some(&(dyck.initial, vec![])));
+        for (_, input) in balanced {
+            drop(dyck.accept(input).unwrap());
+        }
+    }
+
+    #[test]
+    fn reachability_through_regions() {
+        let brackets = |closes: bool| -> Deterministic<char> {
+            let on = |c, t| Curry::Scrutinize {
+                filter: RangeMap(iter::once((Range::unit(c), t)).collect()),
+                fallback: None,
+            };
+            let rejecting = || iter::once(Rejection::UnexpectedToken).collect();
+            Graph {
+                states: vec![
+                    State {
+                        transitions: on(
+                            '[',
+                            Transition::Call {
+                                region: "brackets",
+                                detour: 1,
+                                dst: Box::new(Transition::Lateral {
+                                    dst: 3,
+                                    update: None,
+                                }),
+                                combine: ff!(|(), ()| ()),
+                            },
+                        ),
+                        non_accepting: rejecting(),
+                    },
+                    State {
+                        transitions: on(
+                            'x',
+                            Transition::Lateral {
+                                dst: 2,
+                                update: None,
+                            },
+                        ),
+                        non_accepting: rejecting(),
+                    },
+                    State {
+                        transitions: on(
+                            ']',
+                            if closes {
+                                Transition::Return { region: "brackets" }
+                            } else {
+                                Transition::Lateral {
+                                    dst: 2,
+                                    update: None,
+                                }
+                            },
+                        ),
+                        non_accepting: rejecting(),
+                    },
+                    State {
+                        transitions: Curry::Scrutinize {
+                            filter: RangeMap(BTreeMap::new()),
+                            fallback: None,
+                        },
+                        non_accepting: BTreeSet::new(),
+                    },
+                ],
+                initial: 0,
+            }
+        };
+        let closes = brackets(true);
+        assert_eq!(closes.check(), Ok(()));
+        assert!(!closes.accepts_nothing());
+        let input: Vec<char> = "[x]".chars().collect();
+        assert_eq!(closes.shortest_accepted(), Some(input.clone()));
+        assert_eq!(closes.acceptance_examples(), [(3, input.clone())]);
+        assert_eq!(closes.accept(input), Ok("()".to_owned()));
+        // The region never closes, so the state after it is out of reach:
+        let never = brackets(false);
+        assert!(never.accepts_nothing());
+        assert_eq!(never.shortest_accepted(), None);
+        assert_eq!(never.acceptance_examples(), []);
+        assert!(!dyck_d().accepts_nothing());
+        assert_eq!(dyck_d().shortest_accepted(), Some(vec![]));
+    }
+
+    #[test]
+    fn structural_hash() {
+        use core::hash::BuildHasher;
+        use std::{collections::HashMap, hash::RandomState};
+        let hasher = RandomState::new();
+        let abc = literal("abc");
+        assert_eq!(hasher.hash_one(&abc), hasher.hash_one(literal("abc")));
+        assert_eq!(
+            hasher.hash_one(abc.clone().generalize()),
+            hasher.hash_one(literal("abc").generalize()),
+        );
+        // Widen the first range by one endpoint:
+        let mut widened = abc.clone();
+        let Curry::Scrutinize { ref mut filter, .. } =
+            unwrap!(widened.states.get_mut(abc.initial)).transitions
+        else {
+            panic!("`literal` always scrutinizes");
+        };
+        let (range, t) = filter.0.pop_first().unwrap();
+        drop(filter.0.insert(Range { last: 'b', ..range }, t));
+        assert_ne!(abc, widened);
+        assert_ne!(hasher.hash_one(&abc), hasher.hash_one(&widened));
+        let mut cache = HashMap::new();
+        let _ = cache.insert(abc, "abc");
+        let _ = cache.insert(widened, "[ab]bc");
+        assert_eq!(cache.get(&literal("abc")), Some(&"abc"));
+    }
+
+    #[test]
+    fn canonical_inputs() {
+        let tuple = Deterministic::from_ebnf(
+            r#"tuple = "(" ")" | "(" "a" "," ")" "!"? | "(" "a" "," "b" ")" "?"*;"#,
+        )
+        .unwrap()
+        .remove("tuple")
+        .unwrap();
+        let inputs: Vec<String> = tuple
+            .canonical_inputs(6)
+            .into_iter()
+            .map(|input| input.into_iter().collect())
+            .collect();
+        assert_eq!(
+            inputs,
+            ["()", "(a,)", "(a,)!", "(a,b)", "(a,b)?", "(a,b)??"]
+        );
+        let dyck = dyck_d();
+        let balanced = dyck.canonical_inputs(20);
+        assert_eq!(balanced.len(), 20);
+        assert_eq!(balanced, dyck.canonical_inputs(20));
+        assert!(balanced
+            .iter()
+            .zip(balanced.iter().skip(1))
+            .all(|(a, b)| a.len() <= b.len()));
+        for input in balanced {
+            drop(dyck.accept(input).unwrap());
+        }
+        assert_eq!(literal("ab").canonical_inputs(3), [vec!['a', 'b']]);
+    }
+
+    #[test]
+    fn subset_counterexample() {
+        let tuple = |src| {
+            Deterministic::from_ebnf(src)
+                .unwrap()
+                .remove("tuple")
+                .unwrap()
+        };
+        let strict = tuple(r#"tuple = "(" "x" ("," "x")* ")";"#);
+        let trailing = tuple(r#"tuple = "(" "x" ("," "x")* ","? ")";"#);
+        assert_eq!(strict.is_subset_of(&trailing), Ok(()));
+        let Err(Counterexample::Accepted(input)) = trailing.is_subset_of(&strict) else {
+            panic!("a trailing comma should be a counterexample")
+        };
+        assert_eq!(input.into_iter().collect::<String>(), "(x,)");
+    }
+
+    #[test]
+    fn disjoint_union() {
+        let yes = literal("yes");
+        let no = literal("no");
+        let fast = yes.disjoint_union(&no).unwrap();
+        assert_eq!(fast, yes.clone().determinized_union(no.clone()).unwrap());
+        assert_eq!(yes.clone() | no, fast);
+        for input in ["yes", "no"] {
+            assert_eq!(fast.accept(input.chars()), Ok("()".to_owned()));
+        }
+        // Both start with `y`, so only determinizing can tell them apart.
+        assert_eq!(yes.disjoint_union(&literal("yo")), None);
+    }
+
+    #[test]
+    fn ordered_union() {
+        // Accept exactly `s`, outputting whichever number `tag` returns on the first character.
+        let tagged = |s: &str, tag: Update<char>| {
+            let mut parser = literal(s);
+            for t in parser.states[parser.initial].transitions.values_mut() {
+                if let Transition::Lateral { ref mut update, .. } = *t {
+                    *update = Some(tag.clone());
+                }
+            }
+            parser
+        };
+        let one = || update!(|(), _c: char| 1_u8);
+        let two = || update!(|(), _c: char| 2_u8);
+        let left = tagged("ab", one());
+        let right = tagged("ab", two());
+        let ordered = left.clone() + right.clone();
+        ordered.check().unwrap();
+        assert_eq!(ordered.typed::<u8>().unwrap().accept("ab".chars()), Ok(1));
+        assert_eq!(
+            (right.clone() + left.clone())
+                .typed::<u8>()
+                .unwrap()
+                .accept("ab".chars()),
+            Ok(2),
+        );
+        assert!(matches!(panic::catch_unwind(|| left | right), Err(..)));
+        // While both sides agree, both keep running.
+        let agree = tagged("ab", one()) + tagged("ac", one());
+        for input in ["ab", "ac"] {
+            assert_eq!(
+                agree.clone().typed::<u8>().unwrap().accept(input.chars()),
+                Ok(1)
+            );
+        }
+        // Add `update` to every transition after the first character.
+        let then = |mut parser: Deterministic<char>, update: Update<char>| {
+            for (i, state) in parser.states.iter_mut().enumerate() {
+                if i != parser.initial {
+                    for t in state.transitions.values_mut() {
+                        if let Transition::Lateral {
+                            update: ref mut slot,
+                            ..
+                        } = *t
+                        {
+                            *slot = Some(update.clone());
+                        }
+                    }
+                }
+            }
+            parser
+        };
+        // Both sides compute the same output on `a`, so the right side can keep reading it afterward.
+        let lockstep = then(tagged("ab", one()), update!(|n: u8, _c: char| n + 10))
+            + then(tagged("ac", one()), update!(|n: u8, _c: char| n + 20));
+        lockstep.check().unwrap();
+        for (input, expected) in [("ab", 11), ("ac", 21)] {
+            assert_eq!(
+                lockstep
+                    .clone()
+                    .typed::<u8>()
+                    .unwrap()
+                    .accept(input.chars()),
+                Ok(expected)
+            );
+        }
+        // Once they disagree, the left side's update wins, which is fine as long as the left side accepts whatever the right side still could...
+        let covered = (tagged("a", one()) >> (literal("b") | literal("c")))
+            + then(tagged("ac", two()), update!(|n: u8, _c: char| n + 20));
+        for input in ["ab", "ac"] {
+            assert_eq!(
+                covered.clone().typed::<u8>().unwrap().accept(input.chars()),
+                Ok(1)
+            );
+        }
+        // ...but not otherwise, since the right side would then have to continue from an output it never computed.
+        assert!(matches!(
+            tagged("ab", one()).ordered_union(&then(
+                tagged("ac", two()),
+                update!(|n: u8, _c: char| n + 20)
+            )),
+            Err(IllFormed::IncompatibleActions(..)),
+        ));
+        // Without any overlap, it's just a union.
+        let keywords = literal("yes") + literal("no");
+        assert_eq!(
+            keywords.equivalent(&(literal("yes") | literal("no"))),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn concat_many_and_union_many() {
+        let fields: Vec<_> = (0_u8..30).map(|i| literal(&format!("f{i};"))).collect();
+        let record = Deterministic::concat_many(fields.clone()).unwrap();
+        assert_eq!(
+            Some(&record),
+            fields.into_iter().reduce(|acc, p| acc >> p).as_ref()
+        );
+        let input = (0_u8..30).fold(String::new(), |acc, i| acc + &format!("f{i};"));
+        assert_eq!(record.accept(input.chars()), Ok("()".to_owned()));
+        let keywords: Vec<_> = ["let", "letrec", "loop", "if", "in", "int"]
+            .into_iter()
+            .map(literal)
+            .collect();
+        let union = Deterministic::union_many(keywords.clone()).unwrap();
+        assert_eq!(
+            Some(&union),
+            keywords.into_iter().reduce(|acc, p| acc | p).as_ref()
+        );
+        assert_eq!(Deterministic::<char>::concat_many(vec![]), None);
+        assert_eq!(Deterministic::<char>::union_many(vec![]), None);
+    }
+
+    #[test]
+    fn optional_and_repeat() {
+        let ab = literal("ab");
+        let maybe = ab.clone().optional();
+        assert_eq!(maybe.accept("".chars()), Ok("()".to_owned()));
+        assert_eq!(maybe.accept("ab".chars()), Ok("()".to_owned()));
+        assert!(matches!(maybe.accept("abab".chars()), Err(..)));
+        let thrice = ab.clone().repeat(3);
+        assert_eq!(
+            Some(&thrice),
+            [ab.clone(), ab.clone(), ab.clone()]
+                .into_iter()
+                .reduce(|acc, p| acc >> p)
+                .as_ref()
+        );
+        assert_eq!(thrice.accept("ababab".chars()), Ok("()".to_owned()));
+        assert!(matches!(thrice.accept("abab".chars()), Err(..)));
+        assert!(matches!(thrice.accept("abababab".chars()), Err(..)));
+        let none = ab.repeat(0);
+        assert_eq!(none.accept("".chars()), Ok("()".to_owned()));
+        assert!(matches!(none.accept("ab".chars()), Err(..)));
+    }
+
+    #[test]
+    fn union_of_keywords_shares_prefixes() {
+        let keywords = [
+            "let", "letrec", "loop", "if", "in", "int", "else", "elif", "end", "for", "fn",
+            "false", "true", "type", "trait", "match", "mut", "mod", "move", "macro",
+        ];
+        let union = keywords
+            .into_iter()
+            .map(literal)
+            .reduce(|acc, keyword| acc | keyword)
+            .unwrap();
+        let trie_size = keywords
+            .iter()
+            .flat_map(|keyword| (0..=keyword.len()).map(|i| keyword.get(..i).unwrap()))
+            .collect::<BTreeSet<_>>()
+            .len();
+        assert!(
+            union.states.len() <= trie_size,
+            "{} states, but a trie would need only {trie_size}",
+            union.states.len(),
+        );
+        for keyword in keywords {
+            assert_eq!(union.accept(keyword.chars()), Ok("()".to_owned()));
+        }
+        for prefix in ["", "le", "letr", "letre", "lo", "ma", "tr"] {
+            assert!(
+                matches!(
+                    union.accept(prefix.chars()),
+                    Err(ParseError::Incomplete { consumed, ref open, .. })
+                        if consumed == prefix.len() && open.is_empty(),
+                ),
+                "{prefix:?}",
+            );
+        }
+    }
+
+    #[test]
+    fn incomplete_lists_open_regions() {
+        let parser = dyck_d();
+        let incomplete = |consumed, open: Vec<&'static str>| ParseError::Incomplete {
+            expected: vec![Range::unit('('), Range::unit(')')],
+            consumed,
+            open,
+        };
+        assert_eq!(
+            parser.accept("(".chars()),
+            Err(incomplete(1, vec!["parentheses"]))
+        );
+        assert_eq!(
+            parser.accept("(()((".chars()),
+            Err(incomplete(5, vec!["parentheses"; 3])),
+        );
+        assert_eq!(
+            parser.clone().typed::<()>().unwrap().accept("((".chars()),
+            Err(incomplete(2, vec!["parentheses"; 2])),
+        );
+        assert_eq!(
+            parser.accept_spans("()(".chars()),
+            Err(ParseError::Incomplete {
+                expected: vec![Range::unit('('), Range::unit(')')],
+                consumed: 3,
+                open: vec!["parentheses"],
+            }),
+        );
+        // Wrong tokens are still wrong, not incomplete:
+        assert_eq!(
+            parser.accept("())".chars()),
+            Err(ParseError::BadInput(InputError::Unopened)),
+        );
+        assert_eq!(
+            parser.accept("(x".chars()),
+            Err(ParseError::BadInput(InputError::Absurd)),
+        );
+        // Streaming callers see the same thing once input runs out:
+        let mut run = "((".chars().run(&parser);
+        assert_eq!(
+            run.step_budget(10),
+            Progress::Done(Err(incomplete(2, vec!["parentheses"; 2]))),
+        );
+    }
+
+    #[test]
+    fn describe() {
+        let keywords = literal("yes") | literal("no");
+        let description = keywords.describe();
+        assert!(description.contains("\"yes\""), "{description}");
+        assert!(description.contains("\"no\""), "{description}");
+        assert_eq!(literal("ab").star().describe(), "start = \"ab\"* ;\n");
+        assert_eq!(
+            (literal("a") >> literal("b").star()).describe(),
+            "start = 'a' 'b'* ;\n",
+        );
+        assert_eq!(
+            dyck_d().describe(),
+            "start = ('(' parentheses)* ;\nparentheses = ('(' parentheses)* ')' ;\n",
+        );
+    }
+
+    #[test]
+    fn tokens_render_the_same_everywhere() {
+        for c in ['\n', '\t', '\'', '"', '\\', '\0', '\u{e9}', '\u{7f}'] {
+            let src = c.to_src();
+            assert_eq!(to_src::range_pattern(&Range::unit(c)), src);
+            let parser = literal(&c.to_string());
+            assert_eq!(parser.describe(), format!("start = {src} ;\n"));
+            let code = parser.to_src().unwrap();
+            assert!(code.contains(&format!("{src} =>")), "{code}");
+        }
+        for b in [b'\n', b'\'', b'"', b'\\', 0, 0xff] {
+            assert_eq!(to_src::range_pattern(&Range::unit(b)), b.to_src());
+        }
+    }
+
+    #[test]
+    fn fused_updates() {
+        let first = update!(|(), c: char| String::from(c));
+        let second = update!(|mut s: String, c: char| {
+            s.push(c.to_ascii_uppercase());
+            s
+        });
+        let fused = first.then::<usize>(&second).unwrap();
+        assert_eq!(&*fused.input_t, "()");
+        assert_eq!(&*fused.output_t, "String");
+        assert_eq!(
+            &*fused.src,
+            format!(
+                "|acc, token: char| ({})(({})(acc, token.clone()), token)",
+                second.src, first.src
+            ),
+        );
+        let parser: Deterministic<char> = Graph {
+            states: vec![
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(
+                            iter::once((
+                                Range {
+                                    first: 'a',
+                                    last: 'z',
+                                },
+                                Transition::Lateral {
+                                    dst: 1,
+                                    update: Some(fused),
+                                },
+                            ))
+                            .collect(),
+                        ),
+                        fallback: None,
+                    },
+                    non_accepting: iter::once("Expected a letter".into()).collect(),
+                },
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(BTreeMap::new()),
+                        fallback: None,
+                    },
+                    non_accepting: BTreeSet::new(),
+                },
+            ],
+            initial: 0,
+        };
+        parser.check().unwrap();
+        assert_eq!(
+            parser.typed::<String>().unwrap().accept(iter::once('q')),
+            Ok("qQ".to_owned())
+        );
+        assert_eq!(
+            second.then::<usize>(&first),
+            Err(IllFormed::TypeMismatch(
+                "String".to_owned(),
+                "()".to_owned()
+            )),
+        );
+    }
+
+    #[test]
+    fn fused_updates_codegen() {
+        let lower = update!(|(), c: char| print!("{c}"));
+        let upper = update!(|(), c: char| print!("{}", c.to_ascii_uppercase()));
+        let parser: Deterministic<char> = Graph {
+            states: vec![State {
+                transitions: Curry::Scrutinize {
+                    filter: RangeMap(
+                        iter::once((
+                            Range {
+                                first: 'a',
+                                last: 'z',
+                            },
+                            Transition::Lateral {
+                                dst: 0,
+                                update: Some(lower.then::<usize>(&upper).unwrap()),
+                            },
+                        ))
+                        .collect(),
+                    ),
+                    fallback: None,
+                },
+                non_accepting: BTreeSet::new(),
+            }],
+            initial: 0,
+        };
+        let src = parser.to_src().unwrap();
+        let dir = env::temp_dir().join(format!("inator-codegen-fused-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("main.rs");
+        fs::write(
+            &path,
+            format!("{src}\n\nfn main() {{\n    parse(\"abc\".chars()).unwrap();\n}}\n"),
+        )
+        .unwrap();
+        let binary = dir.join("test-binary");
+        let compiled = Command::new("rustc")
+            .args(["--edition", "2021", "-o"])
+            .arg(&binary)
+            .arg(&path)
+            .output()
+            .unwrap();
+        assert!(
+            compiled.status.success(),
+            "{}",
+            String::from_utf8_lossy(&compiled.stderr),
+        );
+        let ran = Command::new(&binary).output().unwrap();
+        assert_eq!(String::from_utf8(ran.stdout).unwrap(), "aAbBcC");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn compact_debug() {
+        let update: Update<u8> = update!(|(), _| {});
+        assert_eq!(format!("{update:?}"), "fn(|(), _| {}) : () -> ()");
+        assert_eq!(format!("{update}"), "fn(|(), _| {}) : () -> ()");
+        let combine = ff!(|(), ()| ());
+        assert_eq!(format!("{combine:?}"), "fn(|(), ()| ()) : (), () -> ()");
+        let pretty = format!("{:#?}", dyck_d());
+        assert!(pretty.lines().count() <= 60, "{pretty}");
+    }
+
+    #[test]
+    fn collect_matched() {
+        let collected = word().collect_matched_string().unwrap();
+        collected.check().unwrap();
+        assert_eq!(collected.accept("abc".chars()), Ok("String".to_owned()));
+        assert_eq!(
+            word().collect_matched().unwrap().accept("abc".chars()),
+            Ok("Vec::<char>".to_owned()),
+        );
+        for parser in [word().star(), word().optional()] {
+            assert_eq!(
+                parser.collect_matched_string(),
+                Err(IllFormed::CollectOverEmpty),
+            );
+            assert_eq!(parser.collect_matched(), Err(IllFormed::CollectOverEmpty));
+        }
+        assert_eq!(
+            IllFormed::<char, usize>::CollectOverEmpty.to_string(),
+            "Can't collect the tokens this parser reads, \
+            since it accepts the empty input, which has no tokens to collect.",
+        );
+    }
+
+    #[test]
+    fn sink_on_reject() {
+        let parser = literal("get").sink_on_reject();
+        parser.check().unwrap();
+        let sink = parser
+            .states
+            .iter()
+            .position(|state| matches!(state.transitions, Curry::Wildcard(..)))
+            .unwrap();
+        let mut run = "gotten".chars().run(&parser);
+        while let Some(Ok(_)) = run.next() {}
+        assert_eq!(run.ctrl, sink);
+        assert_eq!(run.input.next(), None);
+        assert_eq!(
+            parser.accept("gotten".chars()),
+            Err(ParseError::BadInput(InputError::NotAccepting)),
+        );
+        assert_eq!(parser.accept("get".chars()), Ok("()".to_owned()));
+    }
+
+    #[test]
+    fn shortest_accepted() {
+        let parser = literal("goodbye") | literal("hello") | literal("greetings");
+        assert_eq!(parser.shortest_accepted(), Some("hello".chars().collect()),);
+        assert_eq!(word().shortest_accepted().map(|v| v.len()), Some(1));
+        assert_eq!(
+            literal("a")
+                .product_with(&literal("b"), |a, b| a && b)
+                .unwrap()
+                .shortest_accepted(),
+            None,
+        );
+        // Even with nothing to scrutinize, a wildcard still takes the least token.
+        assert_eq!(any::<char>().shortest_accepted(), Some(vec!['\0']));
+        assert!(!any::<char>().accepts_nothing());
+        assert_eq!(
+            (literal("a") | any()).representative_tokens(),
+            ['\0', '`', 'a', 'b'].into_iter().collect(),
+        );
+    }
+
+    #[test]
+    fn explicit_range_beats_fallback() {
+        let to = |dst: usize| Transition::<u8, usize>::Lateral { dst, update: None };
+        let n = NonZeroUsize::new(4).unwrap();
+        let curry = |filter: &[(Range<u8>, usize)]| Curry::Scrutinize {
+            filter: RangeMap(filter.iter().map(|&(r, dst)| (r, to(dst))).collect()),
+            fallback: Some(to(3)),
+        };
+        let partial = curry(&[(
+            Range {
+                first: b'a',
+                last: b'z',
+            },
+            1,
+        )]);
+        assert_eq!(partial.check(n), Ok(()));
+        assert_eq!(partial.get(&b'a'), Ok(Some(&to(1))));
+        assert_eq!(partial.get(&b'z'), Ok(Some(&to(1))));
+        assert_eq!(partial.get(&b'0'), Ok(Some(&to(3))));
+        let full = curry(&[
+            (
+                Range {
+                    first: 0,
+                    last: b'`',
+                },
+                2,
+            ),
+            (
+                Range {
+                    first: b'a',
+                    last: b'z',
+                },
+                1,
+            ),
+            (
+                Range {
+                    first: b'{',
+                    last: 255,
+                },
+                2,
+            ),
+        ]);
+        assert_eq!(
+            full.check(n),
+            Err(IllFormed::UnreachableFallback(Box::new(to(3))))
+        );
+        let gap = curry(&[
+            (
+                Range {
+                    first: 0,
+                    last: b'`',
+                },
+                2,
+            ),
+            (
+                Range {
+                    first: b'b',
+                    last: 255,
+                },
+                2,
+            ),
+        ]);
+        assert_eq!(gap.check(n), Ok(()));
+        assert_eq!(gap.get(&b'a'), Ok(Some(&to(3))));
+    }
+
+    #[test]
+    fn merge_wildcard_into_scrutinize() {
+        let to = |dsts: &[usize]| Transition::<u8, BTreeSet<usize>>::Lateral {
+            dst: dsts.iter().copied().collect(),
+            update: None,
+        };
+        let wildcard = Curry::Wildcard(to(&[1]));
+        let scrutinize = Curry::Scrutinize {
+            filter: RangeMap(iter::once((Range::unit(0), to(&[2]))).collect()),
+            fallback: Some(to(&[3])),
+        };
+        let expected = Curry::Scrutinize {
+            filter: RangeMap(iter::once((Range::unit(0), to(&[1, 2]))).collect()),
+            fallback: Some(to(&[1, 3])),
+        };
+        assert_eq!(
+            wildcard.clone().merge(scrutinize.clone()),
+            Ok(expected.clone())
+        );
+        assert_eq!(scrutinize.merge(wildcard.clone()), Ok(expected));
+        assert_eq!(
+            wildcard.clone().merge(Curry::Scrutinize {
+                filter: RangeMap(BTreeMap::new()),
+                fallback: Some(to(&[3])),
+            }),
+            Ok(Curry::Wildcard(to(&[1, 3]))),
+        );
+        assert_eq!(
+            wildcard.merge(Curry::Scrutinize {
+                filter: RangeMap(BTreeMap::new()),
+                fallback: Some(Transition::Return { region: "region" }),
+            }),
+            Err(IllFormed::IncompatibleActions(
+                Box::new(Transition::Return { region: "region" }),
+                Box::new(to(&[1])),
+            )),
+        );
+    }
+
+    #[test]
+    fn write_example_crate() {
+        let dir = env::temp_dir().join(format!("inator-example-crate-{}", process::id()));
+        dyck_d().write_example_crate(&dir, "dyck").unwrap().unwrap();
+        assert!(!fs::read_to_string(dir.join("Cargo.toml"))
+            .unwrap()
+            .is_empty());
+        for file in ["src/main.rs", "src/parser.rs"] {
+            let src = fs::read_to_string(dir.join(file)).unwrap();
+            assert!(!src.is_empty());
+            drop(syn::parse_file(&src).unwrap());
+        }
+        // One test per accepting state.
+        let main = fs::read_to_string(dir.join("src/main.rs")).unwrap();
+        assert_eq!(
+            main.matches("#[test]").count(),
+            dyck_d().acceptance_examples().len(),
+        );
+        let binary = dir.join("test-binary");
+        let compiled = Command::new("rustc")
+            .args(["--edition", "2021", "--test", "-o"])
+            .arg(&binary)
+            .arg(dir.join("src/main.rs"))
+            .output()
+            .unwrap();
+        assert!(
+            compiled.status.success(),
+            "{}",
+            String::from_utf8_lossy(&compiled.stderr),
+        );
+        let ran = Command::new(&binary).output().unwrap();
+        assert!(
+            ran.status.success(),
+            "{}",
+            String::from_utf8_lossy(&ran.stdout),
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn accept_cow() {
+        assert!(matches!(
+            word().accept_cow("hello"),
+            Ok(Cow::Borrowed("hello"))
+        ));
+        // Letters, or `\n` for a newline.
+        let letter = || Transition::Lateral {
+            dst: 0,
+            update: None,
+        };
+        let unescaped: Deterministic<char> = Graph {
+            states: vec![
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(
+                            [
+                                (
+                                    Range {
+                                        first: 'a',
+                                        last: 'z',
+                                    },
+                                    letter(),
+                                ),
+                                (
+                                    Range::unit('\\'),
+                                    Transition::Lateral {
+                                        dst: 1,
+                                        update: Some(update!(|s: String, _c: char| s)),
+                                    },
+                                ),
+                            ]
+                            .into_iter()
+                            .collect(),
+                        ),
+                        fallback: None,
+                    },
+                    non_accepting: BTreeSet::new(),
+                },
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(
+                            iter::once((
+                                Range::unit('n'),
+                                Transition::Lateral {
+                                    dst: 0,
+                                    update: Some(update!(|mut s: String, _c: char| {
+                                        s.push('\n');
+                                        s
+                                    })),
+                                },
+                            ))
+                            .collect(),
+                        ),
+                        fallback: None,
+                    },
+                    non_accepting: iter::once("Expected `n` after `\\`".into()).collect(),
+                },
+            ],
+            initial: 0,
+        };
+        assert!(matches!(
+            unescaped.accept_cow("abc"),
+            Ok(Cow::Borrowed("abc"))
+        ));
+        let owned = unescaped.accept_cow("ab\\ncd");
+        assert!(
+            matches!(owned, Ok(Cow::Owned(ref s)) if s == "ab\ncd"),
+            "{owned:?}"
+        );
+        assert_eq!(
+            unescaped.accept_cow("ab\\"),
+            Err(ParseError::Incomplete {
+                expected: vec![Range::unit('n')],
+                consumed: 3,
+                open: vec![],
+            }),
+        );
+        assert_eq!(
+            unescaped.accept_cow("a1"),
+            Err(ParseError::BadInput(InputError::Absurd)),
+        );
+    }
+
+    #[test]
+    fn parse_lines() {
+        let results = word().parse_lines("abc\nd3f\r\nghi\n");
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0], Ok("()".to_owned()));
+        assert_eq!(
+            results[1],
+            Err(LineError {
+                line: 2,
+                error: ParseError::BadInput(InputError::Absurd),
+            }),
+        );
+        assert_eq!(results[2], Ok("()".to_owned()));
+        let e = results[1].clone().unwrap_err();
+        assert_eq!(e.to_string(), format!("Line 2: {}", e.error));
+    }
+
+    #[test]
+    fn accept_ref() {
+        let ab = literal("ab");
+        let input: Vec<char> = "ab".chars().collect();
+        assert_eq!(ab.accept_ref(&input), ab.accept("ab".chars()));
+        assert_eq!(ab.accept_ref(&input[..1]), ab.accept("a".chars()));
+        assert_eq!(ab.accept_ref(input), Ok("()".to_owned()));
+    }
+
+    #[test]
+    fn accept_recovering() {
+        // Capital letters in parentheses separated by commas, e.g. `(A,B)`.
+        let on = |range, dst| (range, Transition::Lateral { dst, update: None });
+        let state = |arms: Vec<_>, accepting: bool| State {
+            transitions: Curry::Scrutinize {
+                filter: RangeMap(arms.into_iter().collect()),
+                fallback: None,
+            },
+            non_accepting: if accepting {
+                BTreeSet::new()
+            } else {
+                iter::once(Rejection::UnexpectedToken).collect()
+            },
+        };
+        let tuple: Deterministic<char> = Graph {
+            states: vec![
+                state(vec![on(Range::unit('('), 1)], false),
+                state(
+                    vec![on(
+                        Range {
+                            first: 'A',
+                            last: 'Z',
+                        },
+                        2,
+                    )],
+                    false,
+                ),
+                state(
+                    vec![on(Range::unit(','), 1), on(Range::unit(')'), 3)],
+                    false,
+                ),
+                state(vec![], true),
+            ],
+            initial: 0,
+        };
+        let sync: BTreeSet<char> = [',', ')'].into_iter().collect();
+        let absurd = ParseError::BadInput(InputError::Absurd);
+        assert_eq!(
+            tuple.accept_recovering("(A,,B)".chars(), &sync),
+            (Ok("()".to_owned()), vec![(3, absurd.clone())]),
+        );
+        // Skip everything up to and including the next synchronizing token.
+        assert_eq!(
+            tuple.accept_recovering("(A,#?,B)".chars(), &sync),
+            (Ok("()".to_owned()), vec![(3, absurd.clone())]),
+        );
+        assert_eq!(
+            tuple.accept_recovering("(A,,B,,C)".chars(), &sync),
+            (
+                Ok("()".to_owned()),
+                vec![(3, absurd.clone()), (6, absurd.clone())]
+            ),
+        );
+        // Without errors, exactly the same as `accept`.
+        assert_eq!(
+            tuple.accept_recovering("(A,B)".chars(), &sync),
+            (tuple.accept("(A,B)".chars()), vec![]),
+        );
+        // Running out of input still ends the run.
+        let (result, errors) = tuple.accept_recovering("(AB)".chars(), &sync);
+        assert!(
+            matches!(result, Err(ParseError::Incomplete { .. })),
+            "{result:?}"
+        );
+        assert_eq!(errors, [(2, absurd)]);
+    }
+
+    #[test]
+    fn parse_slice() {
+        let input: Vec<char> = "abcd".chars().collect();
+        assert_eq!(
+            literal("ab").parse_slice(&input),
+            (Ok("()".to_owned()), &['c', 'd'][..]),
+        );
+        let trailing_digit: Vec<char> = "abc1".chars().collect();
+        assert_eq!(
+            word().parse_slice(&trailing_digit),
+            (Ok("()".to_owned()), &['1'][..]),
+        );
+        assert_eq!(
+            literal("b").parse_slice(&trailing_digit),
+            (
+                Err(ParseError::BadInput(InputError::Absurd)),
+                trailing_digit.as_slice(),
+            ),
+        );
+    }
+
+    #[test]
+    fn minimize_merges_redundant_states() {
+        let a_then_loop = |dst| Transition::Lateral { dst, update: None };
+        let redundant: Deterministic<u8> = Graph {
+            states: vec![
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(iter::once((Range::unit(b'a'), a_then_loop(1))).collect()),
+                        fallback: None,
+                    },
+                    non_accepting: BTreeSet::new(),
+                },
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(iter::once((Range::unit(b'a'), a_then_loop(0))).collect()),
+                        fallback: None,
+                    },
+                    non_accepting: BTreeSet::new(),
+                },
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(BTreeMap::new()),
+                        fallback: None,
+                    },
+                    non_accepting: BTreeSet::new(),
+                },
+            ],
+            initial: 0,
+        };
+        let minimized = redundant.minimize();
+        assert_eq!(minimized.states.len(), 1);
+        for input in [vec![], vec![b'a'], vec![b'a', b'a'], vec![b'b']] {
+            assert_eq!(
+                minimized.accept(input.iter().copied()).is_ok(),
+                redundant.accept(input).is_ok(),
+            );
+        }
+    }
+
+    #[test]
+    fn from_ebnf() {
+        let grammar = Deterministic::<char>::from_ebnf(
+            "digit = '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' ;
+             atom = digit | '(' expr ')' ;
+             expr = atom ('+' atom)* ;",
+        )
+        .unwrap();
+        let expr = &grammar["expr"];
+        expr.check().unwrap();
+        for input in ["(1+2)", "1", "((1))+2", "(1+(2+3))+4"] {
+            assert!(expr.accept(input.chars()).is_ok(), "{input:?}");
+        }
+        for input in ["", "(1+", "1+)", "()", "(1+2))", "12"] {
+            assert!(expr.accept(input.chars()).is_err(), "{input:?}");
+        }
+        assert_eq!(
+            Deterministic::<char>::from_ebnf("expr = '1' | expr '+' '1' ;"),
+            Err(EbnfError::UndelimitedRecursion("expr".to_owned())),
+        );
+        assert_eq!(
+            Deterministic::<char>::from_ebnf("expr = atom ;"),
+            Err(EbnfError::UndefinedRule("atom".to_owned())),
+        );
+    }
+
+    #[test]
+    fn accept_mode() {
+        let phone = Deterministic::<char>::from_ebnf(
+            "digit = '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' ;
+             phone = digit digit digit '-' digit digit digit digit ('x' digit digit)? ;",
+        )
+        .unwrap()
+        .remove("phone")
+        .unwrap();
+        let run = |input: &str, mode| phone.accept_mode(input.chars(), mode);
+        let outcome = |consumed, ignored, remainder: &str| AcceptOutcome {
+            output: "()".to_owned(),
+            consumed,
+            ignored,
+            remainder: remainder.chars().collect(),
+        };
+        for mode in [
+            AcceptMode::Strict,
+            AcceptMode::Prefix,
+            AcceptMode::IgnoreTrailing,
+        ] {
+            assert_eq!(run("555-1234", mode), Ok(outcome(8, 0, "")));
+            assert_eq!(run("555-1234x12", mode), Ok(outcome(11, 0, "")));
+            assert_eq!(
+                run("555-12", mode),
+                Err(ParseError::Incomplete {
+                    expected: vec![Range {
+                        first: '0',
+                        last: '9',
+                    }],
+                    consumed: 6,
+                    open: vec![],
+                }),
+            );
+        }
+        assert_eq!(
+            run("555-1234 ext", AcceptMode::Strict),
+            Err(ParseError::BadInput(InputError::Absurd)),
+        );
+        assert_eq!(
+            run("555-1234 ext", AcceptMode::Prefix),
+            Ok(outcome(8, 4, " ext")),
+        );
+        assert_eq!(
+            run("555-1234 ext", AcceptMode::IgnoreTrailing),
+            Ok(outcome(8, 4, "")),
+        );
+        // Half an extension goes back into the remainder, too:
+        assert_eq!(
+            run("555-1234x1!", AcceptMode::Prefix),
+            Ok(outcome(8, 3, "x1!")),
+        );
+        assert_eq!(
+            run("555-1234x1!", AcceptMode::IgnoreTrailing),
+            Ok(outcome(8, 3, "")),
+        );
+    }
+
+    #[test]
+    fn accept_prefix() {
+        let record = Deterministic::<char>::from_ebnf(
+            "digit = '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' ;
+             record = 'r' 'e' 'c' digit ';' ;",
+        )
+        .unwrap()
+        .remove("record")
+        .unwrap();
+        let buffer = "rec1;rec2;";
+        let mut chars = buffer.chars();
+        assert_eq!(
+            record.accept_prefix(chars.by_ref()),
+            Ok(("()".to_owned(), 5))
+        );
+        // Stopped right after choking on the next record's first token:
+        assert_eq!(chars.as_str(), "ec2;");
+        assert_eq!(
+            record.accept_prefix(buffer.chars().skip(5)),
+            Ok(("()".to_owned(), 5)),
+        );
+        assert!(matches!(record.accept_prefix("rec;".chars()), Err(..)));
+        assert!(matches!(record.accept_prefix("".chars()), Err(..)));
+    }
+
+    #[test]
+    fn longest_alternative() {
+        let rules = [literal("in"), literal("int"), literal("i") >> literal("nt")];
+        assert_eq!(
+            Deterministic::longest_alternative(&rules, "int".chars()),
+            Some((1, 3, "()".to_owned())),
+        );
+        assert_eq!(
+            Deterministic::longest_alternative(&rules, "into".chars()),
+            Some((1, 3, "()".to_owned())),
+        );
+        assert_eq!(
+            Deterministic::longest_alternative(&rules, "in t".chars()),
+            Some((0, 2, "()".to_owned())),
+        );
+        assert_eq!(
+            Deterministic::longest_alternative(&rules, "out".chars()),
+            None
+        );
+        assert_eq!(
+            Deterministic::<char>::longest_alternative(&[], "int".chars()),
+            None
+        );
+    }
+
+    #[test]
+    fn codegen_groups_ranges() {
+        let to = |dst| Transition::Lateral { dst, update: None };
+        let fragmented: Deterministic<char> = Graph {
+            states: vec![
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(
+                            [
+                                (Range::unit('a'), to(1)),
+                                (Range::unit('b'), to(1)),
+                                (
+                                    Range {
+                                        first: 'c',
+                                        last: 'f',
+                                    },
+                                    to(1),
+                                ),
+                                (Range::unit('x'), to(1)),
+                                (
+                                    Range {
+                                        first: '0',
+                                        last: '9',
+                                    },
+                                    to(0),
+                                ),
+                            ]
+                            .into_iter()
+                            .collect(),
+                        ),
+                        fallback: None,
+                    },
+                    non_accepting: iter::once("Expected a letter".into()).collect(),
+                },
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(BTreeMap::new()),
+                        fallback: None,
+                    },
+                    non_accepting: BTreeSet::new(),
+                },
+            ],
+            initial: 0,
+        };
+        let src = fragmented.to_src().unwrap();
+        // One arm per distinct transition, down from one per range (plus each state's `_` arm).
+        assert_eq!(
+            src.matches(" => {").count() - src.matches("_ => {").count(),
+            2,
+        );
+        assert!(src.contains("'0'..='9' => {"), "{src}");
+        assert!(src.contains("'a'..='f' | 'x' => {"), "{src}");
+    }
+
+    #[test]
+    #[allow(clippy::too_many_lines)]
+    fn accept() {
+        let digit = |update| Curry::Scrutinize {
+            filter: RangeMap(
+                iter::once((
+                    Range {
+                        first: '0',
+                        last: '9',
+                    },
+                    Transition::Lateral {
+                        dst: 1,
+                        update: Some(update),
+                    },
+                ))
+                .collect(),
+            ),
+            fallback: None,
+        };
+        let number: Deterministic<char> = Graph {
+            states: vec![
+                State {
+                    transitions: digit(update!(|(), c: char| "0123456789".find(c).unwrap())),
+                    non_accepting: iter::once("Expected a digit".into()).collect(),
+                },
+                State {
+                    transitions: digit(update!(
+                        |n: usize, c: char| n * 10 + "0123456789".find(c).unwrap()
+                    )),
+                    non_accepting: BTreeSet::new(),
+                },
+            ],
+            initial: 0,
+        };
+        assert_eq!(
+            number
+                .clone()
+                .typed::<usize>()
+                .unwrap()
+                .accept("1234".chars()),
+            Ok(1234)
+        );
+        let spans: Vec<(usize, usize)> = number
+            .accept_spans("12".chars())
+            .unwrap()
+            .into_iter()
+            .map(|(_, start, end)| (start, end))
+            .collect();
+        assert_eq!(spans, [(0, 1), (1, 2)]);
+        assert_eq!(
+            number
+                .clone()
+                .typed::<usize>()
+                .unwrap()
+                .accept("12a".chars()),
+            Err(ParseError::BadInput(InputError::Absurd)),
+        );
+        assert_eq!(
+            number.typed::<String>(),
+            Err(IllFormed::WrongReturnType(
+                "usize".to_owned(),
+                "String".to_owned(),
+            )),
+        );
+        assert_eq!(
+            word()
+                .collect_matched_string()
+                .unwrap()
+                .typed::<String>()
+                .unwrap()
+                .accept("abc".chars()),
+            Ok("abc".to_owned()),
+        );
+        // The value inside a region is combined with the value from before it.
+        let nested: Deterministic<char> = Graph {
+            states: vec![State {
+                transitions: Curry::Scrutinize {
+                    filter: RangeMap(
+                        [
+                            (
+                                Range::unit('('),
+                                Transition::Call {
+                                    region: "parentheses",
+                                    detour: 0,
+                                    dst: Box::new(Transition::Lateral {
+                                        dst: 0,
+                                        update: None,
+                                    }),
+                                    combine: ff!(|(), ()| ()),
+                                },
+                            ),
+                            (
+                                Range::unit(')'),
+                                Transition::Return {
+                                    region: "parentheses",
+                                },
+                            ),
+                        ]
+                        .into_iter()
+                        .collect(),
+                    ),
+                    fallback: None,
+                },
+                non_accepting: BTreeSet::new(),
+            }],
+            initial: 0,
+        };
+        assert_eq!(
+            nested
+                .clone()
+                .typed::<()>()
+                .unwrap()
+                .accept("(()())".chars()),
+            Ok(())
+        );
+        assert_eq!(
+            nested.typed::<()>().unwrap().accept("(()".chars()),
+            Err(ParseError::Incomplete {
+                expected: vec![Range::unit('('), Range::unit(')')],
+                consumed: 3,
+                open: vec!["parentheses"],
+            }),
+        );
+    }
+
+    #[test]
+    fn accept_with_invariant() {
+        let count = |update| {
+            Curry::Wildcard(Transition::Lateral {
+                dst: 1,
+                update: Some(update),
+            })
+        };
+        // Supposed to count tokens, but (deliberately) starts over after the third.
+        let untyped: Deterministic<char> = Graph {
+            states: vec![
+                State {
+                    transitions: count(update!(|(), _c: char| 1_usize)),
+                    non_accepting: iter::once("Expected a token".into()).collect(),
+                },
+                State {
+                    transitions: count(update!(|n: usize, _c: char| if n == 3 {
+                        0
+                    } else {
+                        n + 1
+                    })),
+                    non_accepting: BTreeSet::new(),
+                },
+            ],
+            initial: 0,
+        };
+        let counter = untyped.typed::<usize>().unwrap();
+        let positive = |n: &usize| *n > 0;
+        assert_eq!(
+            counter.accept_with_invariant("abc".chars(), positive),
+            Ok(3_usize),
+        );
+        let broken =
+            panic::catch_unwind(|| counter.accept_with_invariant("abcd".chars(), positive));
+        if cfg!(debug_assertions) {
+            let Err(payload) = broken else {
+                panic!("Invariant not checked");
+            };
+            assert_eq!(
+                payload.downcast_ref::<String>().map(String::as_str),
+                Some("Invariant violated by the update on the token at index 3"),
+            );
+        } else {
+            assert_eq!(broken.ok(), Some(Ok(0)));
+        }
+    }
+
+    #[test]
+    fn run_emitting() {
+        let on = |pairs: &[(char, usize, Option<Update<char>>)]| Curry::Scrutinize {
+            filter: RangeMap(
+                pairs
+                    .iter()
+                    .map(|&(c, dst, ref update)| {
+                        (
+                            Range::unit(c),
+                            Transition::Lateral {
+                                dst,
+                                update: update.clone(),
+                            },
+                        )
+                    })
+                    .collect(),
+            ),
+            fallback: None,
+        };
+        // Records like `aaa;`, counting how many have finished.
+        let untyped: Deterministic<char> = Graph {
+            states: vec![
+                State {
+                    transitions: on(&[('a', 1, Some(update!(|(), _c: char| 0_usize)))]),
+                    non_accepting: iter::once("Expected a record".into()).collect(),
+                },
+                State {
+                    transitions: on(&[
+                        ('a', 1, None),
+                        (';', 2, Some(update!(|n: usize, _c: char| n + 1))),
+                    ]),
+                    non_accepting: iter::once("Expected `;`".into()).collect(),
+                },
+                State {
+                    transitions: on(&[('a', 1, None)]),
+                    non_accepting: BTreeSet::new(),
+                },
+            ],
+            initial: 0,
+        };
+        let records = untyped.typed::<usize>().unwrap();
+        let mut emitted = vec![];
+        assert_eq!(
+            records.run_emitting("aa;a;aaa;".chars(), |&n| emitted.push(n)),
+            Ok(3),
+        );
+        assert_eq!(emitted, [1, 2, 3]);
+        emitted.clear();
+        assert!(matches!(
+            records.run_emitting("a;aa".chars(), |&n| emitted.push(n)),
+            Err(ParseError::Incomplete { .. }),
+        ));
+        assert_eq!(emitted, [1]);
+    }
+
+    #[test]
+    fn const_range() {
+        const _: () = assert!(Range {
+            first: 0_u8,
+            last: 9
+        }
+        .const_contains(5));
+        const DIGITS: Range<u8> = Range {
+            first: b'0',
+            last: b'9',
+        };
+        const FIVE: bool = DIGITS.const_contains(b'5');
+        const LETTER: bool = DIGITS.const_contains(b'a');
+        const HIGH: Option<Range<u8>> = DIGITS.const_intersection(Range {
+            first: b'5',
+            last: b'z',
+        });
+        const APART: Option<Range<char>> =
+            Range::<char>::const_unit('a').const_intersection(Range {
+                first: 'b',
+                last: 'z',
+            });
+        assert_eq!(FIVE, DIGITS.contains(&b'5'));
+        assert_eq!(LETTER, DIGITS.contains(&b'a'));
+        assert_eq!(
+            HIGH,
+            DIGITS.intersection(Range {
+                first: b'5',
+                last: b'z'
+            })
+        );
+        assert_eq!(
+            HIGH,
+            Some(Range {
+                first: b'5',
+                last: b'9'
+            })
+        );
+        assert_eq!(APART, None);
+    }
+
+    #[test]
+    fn capabilities() {
+        assert_eq!(literal("ab").capabilities(), Capabilities::default());
+        assert_eq!(
+            dyck_d().capabilities(),
+            Capabilities {
+                calls: true,
+                ..Capabilities::default()
+            },
+        );
+        let matched = word().collect_matched_string().unwrap();
+        assert!(matched.involves_updates());
+        assert!(!matched.involves_calls());
+        assert!(matches!(
+            matched.complement(),
+            Err(IllFormed::ComplementOverUpdate(..)),
+        ));
+        assert_eq!(
+            word().complement().unwrap().accept("ab1".chars()),
+            Ok("()".to_owned()),
+        );
+        let anything: Deterministic<char> = Graph {
+            states: vec![State {
+                transitions: Curry::Wildcard(Transition::Lateral {
+                    dst: 0,
+                    update: None,
+                }),
+                non_accepting: BTreeSet::new(),
+            }],
+            initial: 0,
+        };
+        assert!(anything.involves_wildcards());
+        assert!(!anything.involves_any_fallback());
+        let otherwise: Deterministic<char> = Graph {
+            states: vec![State {
+                transitions: Curry::Scrutinize {
+                    filter: RangeMap(BTreeMap::new()),
+                    fallback: Some(Transition::Lateral {
+                        dst: 0,
+                        update: None,
+                    }),
+                },
+                non_accepting: BTreeSet::new(),
+            }],
+            initial: 0,
+        };
+        assert_eq!(
+            otherwise.capabilities() | anything.capabilities(),
+            Capabilities {
+                fallback: true,
+                wildcards: true,
+                ..Capabilities::default()
+            },
+        );
+    }
+
+    #[test]
+    fn call_threads_detour_output() {
+        let state = |filter: Vec<(Range<char>, Transition<char, usize>)>, accepting: bool| State {
+            transitions: Curry::Scrutinize {
+                filter: RangeMap(filter.into_iter().collect()),
+                fallback: None,
+            },
+            non_accepting: if accepting {
+                BTreeSet::new()
+            } else {
+                iter::once("Not done yet".into()).collect()
+            },
+        };
+        let parenthesized: Deterministic<char> = Graph {
+            states: vec![
+                state(
+                    vec![(
+                        Range::unit('('),
+                        Transition::Call {
+                            region: "parentheses",
+                            detour: 1,
+                            dst: Box::new(Transition::Lateral {
+                                dst: 3,
+                                update: None,
+                            }),
+                            combine: ff!(|(), n: usize| n),
+                        },
+                    )],
+                    false,
+                ),
+                state(
+                    vec![(
+                        Range::unit('5'),
+                        Transition::Lateral {
+                            dst: 2,
+                            update: Some(update!(|(), _| 5_usize)),
+                        },
+                    )],
+                    false,
+                ),
+                state(
+                    vec![(
+                        Range::unit(')'),
+                        Transition::Return {
+                            region: "parentheses",
+                        },
+                    )],
+                    false,
+                ),
+                state(vec![], true),
+            ],
+            initial: 0,
+        };
+        parenthesized.check().unwrap();
+        assert_eq!(parenthesized.accept("(5)".chars()), Ok("usize".to_owned()));
+        assert_eq!(
+            parenthesized
+                .clone()
+                .typed::<usize>()
+                .unwrap()
+                .accept("(5)".chars()),
+            Ok(5)
+        );
+        // The update covers the digit, and combining covers the whole region:
+        assert_eq!(
+            parenthesized.accept_spans("(5)".chars()),
+            Ok(vec![("|(), _| 5_usize", 1, 2), ("|(), n: usize| n", 0, 3)]),
+        );
+    }
+
+    #[test]
+    fn audit_determinization() {
+        let mut either = literal("ab").generalize();
+        let size = either.states.len();
+        let Graph {
+            states: other_states,
+            initial: other_initial,
+        } = literal("ac").generalize().map_indices(|i| i + size);
+        either.states.extend(other_states);
+        either.initial.extend(other_initial);
+        either.sort();
+        either.audit_determinization(1_000).unwrap();
+        // Deliberately corrupt the deterministic parser: accept "ac" but not "ab".
+        let corrupted = literal("ac");
+        let Err(DeterminizationMismatch::Counterexample {
+            input,
+            original,
+            deterministic,
+        }) = either.audit_against(&corrupted, 1_000)
+        else {
+            panic!("Audit missed a corrupted determinization")
+        };
+        assert_eq!(input, vec!['a', 'b']);
+        assert_eq!(original, Ok("()".to_owned()));
+        assert_eq!(deterministic, Err(ParseError::BadInput(InputError::Absurd)));
+    }
+
+    #[test]
+    fn breadcrumb_names() {
+        let open_a = literal("(a");
+        let names = open_a.breadcrumb_names();
+        let src = open_a.to_src_named_by(|i| names[i].clone()).unwrap();
+        assert!(src.contains("fn s_initial<"), "{src}");
+        assert!(src.contains("fn s_open_paren<"), "{src}");
+        assert!(src.contains("fn s_open_paren_a<"), "{src}");
+        // An unrelated alternative adds states and shifts indices but doesn't rename anything above.
+        let with_another = literal("(a") | literal("xyz");
+        let more_names = with_another.breadcrumb_names();
+        assert_ne!(open_a.states.len(), with_another.states.len());
+        for name in &names {
+            assert!(more_names.contains(name), "{name} not in {more_names:?}");
+        }
+        assert!(more_names.contains(&"s_x_y".to_owned()));
+        assert!(open_a.to_src().unwrap().contains("fn state_0<"));
+    }
+
+    #[test]
+    fn state_docs() {
+        let name = |i| format!("state_{i}");
+        let docs = |options: CodegenOptions| {
+            let src = (literal("abc") | literal("d"))
+                .to_src_with(name, options)
+                .unwrap();
+            src.lines()
+                .filter(|line| line.starts_with("/// Reached") || line.starts_with("/// Not"))
+                .map(str::to_owned)
+                .collect::<Vec<_>>()
+        };
+        assert!(docs(CodegenOptions::default()).is_empty());
+        assert_eq!(
+            docs(CodegenOptions::default().state_docs(StateDocs::Full)),
+            [
+                "/// Reached by the empty input.",
+                "/// Reached by, e.g., `'a'`.",
+                "/// Reached by, e.g., `'d'`.",
+                "/// Reached by, e.g., `'a' 'b'`.",
+            ],
+        );
+        assert_eq!(
+            docs(CodegenOptions::default().state_docs(StateDocs::TruncatedTo(1))),
+            [
+                "/// Reached by the empty input.",
+                "/// Reached by, e.g., `'a'`.",
+                "/// Reached by, e.g., `'d'`.",
+                "/// Reached by, e.g., `'a' ...`.",
+            ],
+        );
+        assert_eq!(
+            dyck_d().to_src().unwrap(),
+            dyck_d()
+                .to_src_with(name, CodegenOptions::default().state_docs(StateDocs::Off))
+                .unwrap(),
+        );
+    }
+
+    #[test]
+    fn warn_if_accepts_empty() {
+        let warn = CodegenOptions::default().warn_if_accepts_empty(true);
+        let name = |i| format!("state_{i}");
+        let maybe_a = literal("a").star();
+        assert!(maybe_a.accepts_empty());
+        let src = maybe_a.to_src_with(name, warn).unwrap();
+        assert!(src.contains("#[deprecated("), "{src}");
+        assert!(!maybe_a.to_src().unwrap().contains("#[deprecated("));
+        let a = literal("a");
+        assert!(!a.accepts_empty());
+        assert!(!a.to_src_with(name, warn).unwrap().contains("#[deprecated("));
+    }
+
+    #[test]
+    fn codegen_handle() {
+        let name = |i| format!("state_{i}");
+        let maybe_a = literal("a").star();
+        assert!(!maybe_a.to_src().unwrap().contains("pub struct"));
+        let options = CodegenOptions::default()
+            .entry_point("maybe_a")
+            .handle("MaybeA");
+        let src = maybe_a.to_src_with(name, options).unwrap();
+        assert!(src.contains("pub struct MaybeA;"), "{src}");
+        assert!(
+            src.contains(r#"pub const NAME: &'static str = "maybe_a";"#),
+            "{src}"
+        );
+        assert!(src.contains("maybe_a(input)"), "{src}");
+        assert!(!src.contains("#[deprecated("), "{src}");
+        // Anyone calling the handle gets the same warning as anyone calling the function.
+        let warned = maybe_a
+            .to_src_with(name, options.warn_if_accepts_empty(true))
+            .unwrap();
+        assert_eq!(warned.matches("#[deprecated(").count(), 2, "{warned}");
+    }
+
+    #[test]
+    fn concat_starts_rhs_only_after_lhs() {
+        let abb = literal("ab") >> literal("b");
+        assert_eq!(abb.accept("abb".chars()), Ok("()".to_owned()));
+        for rejected in ["ab", "abbb", "abbbb", "b"] {
+            assert!(
+                matches!(abb.accept(rejected.chars()), Err(..)),
+                "{rejected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn call_starts_rhs_only_after_lhs() {
+        // `b` then `)` to close the region.
+        let closed: Deterministic<char> = Graph {
+            states: vec![
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(
+                            iter::once((
+                                Range::unit('b'),
+                                Transition::Lateral {
+                                    dst: 1,
+                                    update: None,
+                                },
+                            ))
+                            .collect(),
+                        ),
+                        fallback: None,
+                    },
+                    non_accepting: iter::once(Rejection::UnexpectedToken).collect(),
+                },
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(
+                            iter::once((Range::unit(')'), Transition::Return { region: "r" }))
+                                .collect(),
+                        ),
+                        fallback: None,
+                    },
+                    non_accepting: iter::once(Rejection::UnexpectedToken).collect(),
+                },
+            ],
+            initial: 0,
+        };
+        let call = literal("a") ^ ("r", closed, ff!(|(), ()| ()));
+        assert_eq!(call.accept("ab)".chars()), Ok("()".to_owned()));
+        for rejected in ["a", "ab", "ab)b)", "b)", "abb)"] {
+            assert!(
+                matches!(call.accept(rejected.chars()), Err(..)),
+                "{rejected:?}"
+            );
+        }
+        // Only the left-hand parser can start.
+        assert_eq!(
+            call.accept(iter::empty()),
+            Err(ParseError::Incomplete {
+                expected: vec![Range::unit('a')],
+                consumed: 0,
+                open: vec![],
+            }),
+        );
+        // A right-hand parser that never closes its region never returns.
+        let open = literal("a") ^ ("r", literal("b"), ff!(|(), ()| ()));
+        assert_eq!(
+            open.accept(iter::empty()),
+            Err(ParseError::Incomplete {
+                expected: vec![Range::unit('a')],
+                consumed: 0,
+                open: vec![],
+            }),
+        );
+        assert!(matches!(open.accept("ab".chars()), Err(..)));
+    }
+
+    #[test]
+    fn with_skip() {
+        let space = || literal(" ");
+        let pair = (literal("(") >> word() >> literal(",") >> word() >> literal(")"))
+            .with_skip(&space())
+            .unwrap();
+        pair.check().unwrap();
+        for input in ["(a,b)", "( a , b )", "  (ab ,  cd)  "] {
+            assert_eq!(pair.accept(input.chars()), Ok("()".to_owned()), "{input:?}");
+        }
+        for input in ["(a b)", "( a , b", "(a,,b)"] {
+            assert!(pair.accept(input.chars()).is_err(), "{input:?}");
+        }
+        // Significant spaces can't also be skipped.
+        assert_eq!(
+            (literal("a") >> space() >> literal("b")).with_skip(&space()),
+            Err(IllFormed::SkipOverlap(Some(Range::unit(' ')))),
+        );
+    }
+
+    #[test]
+    fn shl_keeps_left_output() {
+        let digit: Deterministic<char> = Graph {
+            states: vec![
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(
+                            iter::once((
+                                Range {
+                                    first: '0',
+                                    last: '9',
+                                },
+                                Transition::Lateral {
+                                    dst: 1,
+                                    update: Some(update!(|(), c: char| "0123456789"
+                                        .find(c)
+                                        .unwrap())),
+                                },
+                            ))
+                            .collect(),
+                        ),
+                        fallback: None,
+                    },
+                    non_accepting: iter::once("Expected a digit".into()).collect(),
+                },
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(BTreeMap::new()),
+                        fallback: None,
+                    },
+                    non_accepting: BTreeSet::new(),
+                },
+            ],
+            initial: 0,
+        };
+        let first = digit.clone() << (literal(",") >> digit.clone());
+        assert_eq!(
+            first
+                .clone()
+                .typed::<usize>()
+                .unwrap()
+                .accept("1,2".chars()),
+            Ok(1)
+        );
+        assert!(matches!(first.accept("1,".chars()), Err(..)));
+        // Without `<<`, the second digit would expect `()` but get the first digit's output, which `>>` now catches.
+        assert!(matches!(
+            panic::catch_unwind(|| digit.clone() >> literal(",") >> digit),
+            Err(..),
+        ));
+        assert_eq!(
+            dyck_d().discard(),
+            Err(IllFormed::ProductOverRegion("parentheses")),
+        );
+    }
+
+    #[test]
+    fn accept_counted() {
+        let mut digits: Deterministic<char> = Graph {
+            states: vec![State {
+                transitions: Curry::Scrutinize {
+                    filter: RangeMap(
+                        ('0'..='9')
+                            .map(|c| {
+                                (
+                                    Range::unit(c),
+                                    Transition::Lateral {
+                                        dst: 0,
+                                        update: None,
+                                    },
+                                )
+                            })
+                            .collect(),
+                    ),
+                    fallback: None,
+                },
+                non_accepting: BTreeSet::new(),
+            }],
+            initial: 0,
+        };
+        let input = "31415926535897932384";
+        let (scanned, before) = digits.accept_counted(input.chars());
+        assert_eq!(scanned, Ok("()".to_owned()));
+        assert_eq!(before.tokens, input.len());
+        assert_eq!(before.states, input.len());
+        assert_eq!(before.comparisons, 10 * input.len());
+        for state in &mut digits.states {
+            if let Curry::Scrutinize { ref mut filter, .. } = state.transitions {
+                filter.coalesce();
+            }
+        }
+        let (coalesced, after) = digits.accept_counted(input.chars());
+        assert_eq!(coalesced, Ok("()".to_owned()));
+        assert_eq!(after.tokens, before.tokens);
+        assert_eq!(after.comparisons, input.len());
+        let (matched, nested) = dyck_d().accept_counted("(()())".chars());
+        assert_eq!(matched, Ok("()".to_owned()));
+        assert_eq!((nested.pushes, nested.pops), (3, 3));
+        // Counts up to the token that made the parser fail.
+        let (absurd, failed) = dyck_d().accept_counted("(x)".chars());
+        assert_eq!(absurd, Err(ParseError::BadInput(InputError::Absurd)));
+        assert_eq!((failed.tokens, failed.pushes, failed.pops), (2, 1, 0));
+        // Running out of input with a region open says so, and still counts everything before it.
+        let (unclosed, unfinished) = dyck_d().accept_counted("(()".chars());
+        assert!(
+            matches!(unclosed, Err(ParseError::Incomplete { .. })),
+            "{unclosed:?}",
+        );
+        assert_eq!(
+            (unfinished.tokens, unfinished.pushes, unfinished.pops),
+            (3, 2, 1),
+        );
+    }
+
+    #[test]
+    fn construction_order_is_irrelevant() {
+        let words = ["ab", "abc", "b", "cab", "ca"];
+        let union = |order: &[usize]| {
+            order
+                .iter()
+                .map(|&i| literal(words[i]))
+                .reduce(|acc, w| acc | w)
+                .unwrap()
+        };
+        let reference = union(&[0, 1, 2, 3, 4]);
+        for order in [[4, 3, 2, 1, 0], [2, 0, 4, 1, 3], [3, 1, 0, 4, 2]] {
+            assert_eq!(union(&order), reference, "{order:?}");
+        }
+        assert_eq!(
+            literal("ab") | (literal("b") | (literal("cab") | (literal("abc") | literal("ca")))),
+            reference,
+        );
+        // Same transitions, inserted into each state's map in the opposite order, with the states reversed.
+        let digits_then_letters: Deterministic<char> = Graph {
+            states: vec![
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(
+                            [('0', '9', 1), ('a', 'z', 0)]
+                                .into_iter()
+                                .map(|(first, last, dst)| {
+                                    (
+                                        Range { first, last },
+                                        Transition::Lateral { dst, update: None },
+                                    )
+                                })
+                                .collect(),
+                        ),
+                        fallback: None,
+                    },
+                    non_accepting: BTreeSet::new(),
+                },
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(BTreeMap::new()),
+                        fallback: None,
+                    },
+                    non_accepting: BTreeSet::new(),
+                },
+            ],
+            initial: 0,
+        };
+        let letters_then_digits: Deterministic<char> = Graph {
+            states: vec![
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(BTreeMap::new()),
+                        fallback: None,
+                    },
+                    non_accepting: BTreeSet::new(),
+                },
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(
+                            [('a', 'z', 1), ('0', '9', 0)]
+                                .into_iter()
+                                .map(|(first, last, dst)| {
+                                    (
+                                        Range { first, last },
+                                        Transition::Lateral { dst, update: None },
+                                    )
+                                })
+                                .collect(),
+                        ),
+                        fallback: None,
+                    },
+                    non_accepting: BTreeSet::new(),
+                },
+            ],
+            initial: 1,
+        };
+        assert_ne!(digits_then_letters, letters_then_digits);
+        let (mut lhs, mut rhs) = (digits_then_letters, letters_then_digits);
+        lhs.sort();
+        rhs.sort();
+        assert_eq!(lhs, rhs);
+    }
+
+    #[test]
+    fn with_eof() {
+        let parser = literal("ab").with_eof('$').unwrap();
+        parser.check().unwrap();
+        assert_eq!(parser.accept("ab$".chars()), Ok("()".to_owned()));
+        assert_eq!(
+            parser.accept_with_eof("ab".chars(), '$'),
+            Ok("()".to_owned())
+        );
+        // Input that ends without the sentinel isn't finished yet.
+        assert_eq!(
+            parser.accept("ab".chars()),
+            Err(ParseError::Incomplete {
+                expected: vec![Range {
+                    first: '$',
+                    last: '$',
+                }],
+                consumed: 2,
+                open: vec![],
+            }),
+        );
+        assert_eq!(
+            parser.accept_with_eof("a".chars(), '$'),
+            Err(ParseError::BadInput(InputError::Absurd)),
+        );
+        assert_eq!(
+            parser.accept("ab$b".chars()),
+            Err(ParseError::BadInput(InputError::Absurd)),
+        );
+        // The sentinel can't already mean something where the parser used to accept.
+        assert!(matches!(
+            (literal("a") | literal("a$")).with_eof('$'),
+            Err(IllFormed::Superposition(..)),
+        ));
+    }
+
+    #[test]
+    fn with_terminator() {
+        let parser = literal("abc").with_terminator('\0').unwrap();
+        parser.check().unwrap();
+        assert_eq!(parser.accept("abc\0".chars()), Ok("()".to_owned()));
+        assert_eq!(parser.accept("abc\0junk".chars()), Ok("()".to_owned()));
+        assert_eq!(
+            parser.accept("ab\0".chars()),
+            Err(ParseError::BadInput(InputError::NotAccepting)),
+        );
+        assert_eq!(
+            parser.accept("ab\0c".chars()),
+            Err(ParseError::BadInput(InputError::Absurd)),
+        );
+        // Even a parser that would read anything stops at the terminator.
+        let anything = Deterministic {
+            states: vec![State {
+                transitions: Curry::Wildcard(Transition::Lateral {
+                    dst: 0,
+                    update: None,
+                }),
+                non_accepting: BTreeSet::new(),
+            }],
+            initial: 0,
+        }
+        .with_terminator('\0')
+        .unwrap();
+        assert_eq!(anything.accept("ab\0".chars()), Ok("()".to_owned()));
+        assert_eq!(anything.accept("\0\0".chars()), Ok("()".to_owned()));
+    }
+
+    #[test]
+    fn call_lateral() {
+        let rebuilt: Deterministic<char> = Graph {
+            states: vec![State {
+                transitions: Curry::Scrutinize {
+                    filter: RangeMap(
+                        [
+                            (
+                                Range::unit('('),
+                                Transition::call_lateral(
+                                    "parentheses",
+                                    0,
+                                    0,
+                                    None,
+                                    ff!(|(), ()| ()),
+                                ),
+                            ),
+                            (
+                                Range::unit(')'),
+                                Transition::Return {
+                                    region: "parentheses",
+                                },
+                            ),
+                        ]
+                        .into_iter()
+                        .collect(),
+                    ),
+                    fallback: None,
+                },
+                non_accepting: BTreeSet::new(),
+            }],
+            initial: 0,
+        };
+        assert_eq!(rebuilt, dyck_d());
+        // Continuations can close the enclosing region or open another one right away.
+        let nested = |dst| Transition::<char, usize>::Call {
+            region: "parentheses",
+            detour: 0,
+            dst: Box::new(dst),
+            combine: ff!(|(), ()| ()),
+        };
+        for t in [
+            nested(Transition::Return {
+                region: "parentheses",
+            }),
+            nested(nested(Transition::Return {
+                region: "parentheses",
+            })),
+        ] {
+            assert_eq!(t.check(NonZeroUsize::new(1).unwrap()), Ok(()));
+        }
+    }
+
+    #[test]
+    fn determinize_coalesces_adjacent_ranges() {
+        let digits: Nondeterministic<char> = Graph {
+            states: vec![
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(
+                            [('0', '4'), ('5', '9')]
+                                .into_iter()
+                                .map(|(first, last)| {
+                                    (
+                                        Range { first, last },
+                                        Transition::Lateral {
+                                            dst: iter::once(1).collect(),
+                                            update: None,
+                                        },
+                                    )
+                                })
+                                .collect(),
+                        ),
+                        fallback: None,
+                    },
+                    non_accepting: iter::once("Expected a digit".into()).collect(),
+                },
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(BTreeMap::new()),
+                        fallback: None,
+                    },
+                    non_accepting: BTreeSet::new(),
+                },
+            ],
+            initial: iter::once(0).collect(),
+        };
+        let d = digits.determinize().unwrap();
+        let Curry::Scrutinize { ref filter, .. } = d.states[d.initial].transitions else {
+            panic!("Expected a filter on digits");
+        };
+        assert_eq!(
+            filter.0.keys().copied().collect::<Vec<_>>(),
+            [Range {
+                first: '0',
+                last: '9',
+            }],
+        );
+        let src = d.to_src().unwrap();
+        assert!(src.contains("'0'..='9'"), "{src}");
+        assert!(!src.contains("'0'..='4'"), "{src}");
+    }
+
+    #[test]
+    fn union_splits_overlapping_ranges() {
+        // One token, chosen by `filter` if possible and by `fallback` otherwise.
+        let one = |filter: Vec<(Range<char>, Transition<char, usize>)>,
+                   fallback: Option<Transition<char, usize>>| Graph {
+            states: vec![
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(filter.into_iter().collect()),
+                        fallback,
+                    },
+                    non_accepting: iter::once("Expected a token".into()).collect(),
+                },
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(BTreeMap::new()),
+                        fallback: None,
+                    },
+                    non_accepting: BTreeSet::new(),
+                },
+            ],
+            initial: 0,
+        };
+        let next = Transition::Lateral {
+            dst: 1,
+            update: None,
+        };
+        let digit = one(
+            vec![(
+                Range {
+                    first: '0',
+                    last: '9',
+                },
+                next.clone(),
+            )],
+            None,
+        );
+        let five = one(vec![(Range::unit('5'), next.clone())], None);
+        let any = one(vec![(Range::unit('z'), next.clone())], Some(next));
+        let parser = (digit.clone() >> literal("a")) | (five >> literal("b"));
+        parser.check().unwrap();
+        for input in ["0a", "4a", "5a", "5b", "6a", "9a"] {
+            assert!(parser.accept(input.chars()).is_ok(), "{input:?}");
+        }
+        for input in ["4b", "6b", "5", "a"] {
+            assert!(parser.accept(input.chars()).is_err(), "{input:?}");
+        }
+        // A range in one parser must not shadow another parser's fallback, either.
+        let with_fallback = (digit >> literal("a")) | (any >> literal("b"));
+        with_fallback.check().unwrap();
+        for input in ["5a", "5b", "zb", "!b"] {
+            assert!(with_fallback.accept(input.chars()).is_ok(), "{input:?}");
+        }
+        for input in ["za", "!a"] {
+            assert!(with_fallback.accept(input.chars()).is_err(), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn is_prefix_free() {
+        let code = literal("ab") | literal("ba") | literal("aa");
+        assert!(code.is_prefix_free());
+        assert!(!literal("a").star().is_prefix_free());
+        assert!(!(literal("a") | literal("ab")).is_prefix_free());
+    }
+
+    #[test]
+    fn codegen_fallback_matches_interpreter() {
+        // Any token but ')' opens a region (via the fallback), and ')' closes it.
+        let parser: Deterministic<char> = Graph {
+            states: vec![State {
+                transitions: Curry::Scrutinize {
+                    filter: RangeMap(
+                        iter::once((Range::unit(')'), Transition::Return { region: "any" }))
+                            .collect(),
+                    ),
+                    fallback: Some(Transition::Call {
+                        region: "any",
+                        detour: 0,
+                        dst: Box::new(Transition::Lateral {
+                            dst: 0,
+                            update: None,
+                        }),
+                        combine: ff!(|(), ()| ()),
+                    }),
+                },
+                non_accepting: BTreeSet::new(),
+            }],
+            initial: 0,
+        };
+        let inputs = ["", "a)", "ab))", "a)b)", "a", ")", "a))"];
+        let expected: Vec<bool> = inputs
+            .iter()
+            .map(|input| parser.accept(input.chars()).is_ok())
+            .collect();
+        assert_eq!(expected, [true, true, true, true, false, false, false],);
+        let dir = env::temp_dir().join(format!("inator-codegen-fallback-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("main.rs");
+        fs::write(
+            &path,
+            format!(
+                "{}\n\nfn main() {{\n    for input in {inputs:?} {{\n        \
+                println!(\"{{}}\", parse(input.chars()).is_ok());\n    }}\n}}\n",
+                parser.to_src().unwrap(),
+            ),
+        )
+        .unwrap();
+        let binary = dir.join("test-binary");
+        let compiled = Command::new("rustc")
+            .args(["--edition", "2021", "-o"])
+            .arg(&binary)
+            .arg(&path)
+            .output()
+            .unwrap();
+        assert!(
+            compiled.status.success(),
+            "{}",
+            String::from_utf8_lossy(&compiled.stderr),
+        );
+        let ran = Command::new(&binary).output().unwrap();
+        let generated: Vec<bool> = String::from_utf8(ran.stdout)
+            .unwrap()
+            .lines()
+            .map(|line| line == "true")
+            .collect();
+        assert_eq!(generated, expected);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn codegen_inline_pass_through() {
+        let letters = || {
+            (
+                Range {
+                    first: 'a',
+                    last: 'z',
+                },
+                Transition::Lateral {
+                    dst: 1,
+                    update: Some(update!(|(), _c: char| ())),
+                },
+            )
+        };
+        let to = |c, dst| (Range::unit(c), Transition::Lateral { dst, update: None });
+        let on = |arms: Vec<(Range<char>, Transition<char, usize>)>| Curry::Scrutinize {
+            filter: RangeMap(arms.into_iter().collect()),
+            fallback: None,
+        };
+        // Lines of letters, each ending in `\r\n`: the state after `\r` only passes `\n` along.
+        let parser: Deterministic<char> = Graph {
+            states: vec![
+                State {
+                    transitions: on(vec![letters(), to('\r', 2)]),
+                    non_accepting: BTreeSet::new(),
+                },
+                State {
+                    transitions: on(vec![letters(), to('\r', 2)]),
+                    non_accepting: iter::once("Expected `\\r`".into()).collect(),
+                },
+                State {
+                    transitions: on(vec![to('\n', 0)]),
+                    non_accepting: iter::once("Expected `\\n`".into()).collect(),
+                },
+            ],
+            initial: 0,
+        };
+        let functions = |src: &str| src.matches("\nfn state_").count();
+        assert_eq!(functions(&parser.to_src().unwrap()), 3);
+        let src = parser
+            .to_src_with(
+                |i| format!("state_{i}"),
+                CodegenOptions::default().inline_pass_through(1),
+            )
+            .unwrap();
+        assert_eq!(functions(&src), 2, "{src}");
+        // Every input up to six tokens long over the tokens that matter.
+        let mut inputs = vec![String::new()];
+        let mut shorter = inputs.clone();
+        for _ in 0_u8..6 {
+            shorter = shorter
+                .iter()
+                .flat_map(|s| ['a', '\r', '\n'].map(|c| format!("{s}{c}")))
+                .collect();
+            inputs.extend(shorter.iter().cloned());
+        }
+        let expected: Vec<bool> = inputs
+            .iter()
+            .map(|input| parser.accept(input.chars()).is_ok())
+            .collect();
+        let dir = env::temp_dir().join(format!("inator-codegen-inline-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("main.rs");
+        fs::write(
+            &path,
+            format!(
+                "{src}\n\nfn main() {{\n    for input in {inputs:?} {{\n        \
+                println!(\"{{}}\", parse(input.chars()).is_ok());\n    }}\n}}\n",
+            ),
+        )
+        .unwrap();
+        let binary = dir.join("test-binary");
+        let compiled = Command::new("rustc")
+            .args(["--edition", "2021", "-o"])
+            .arg(&binary)
+            .arg(&path)
+            .output()
+            .unwrap();
+        assert!(
+            compiled.status.success(),
+            "{}",
+            String::from_utf8_lossy(&compiled.stderr),
+        );
+        let ran = Command::new(&binary).output().unwrap();
+        let generated: Vec<bool> = String::from_utf8(ran.stdout)
+            .unwrap()
+            .lines()
+            .map(|line| line == "true")
+            .collect();
+        assert_eq!(generated, expected);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Kinds of tokens from a (pretend) lexer.
+    #[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+    enum TokenKind {
+        LParen,
+        Ident,
+        Comma,
+        RParen,
+    }
+
+    impl TokenKind {
+        const ALL: [Self; 4] = [Self::LParen, Self::Ident, Self::Comma, Self::RParen];
+
+        fn index(self) -> usize {
+            Self::ALL.iter().position(|&kind| kind == self).unwrap()
+        }
+    }
+
+    impl ToSrc for TokenKind {
+        fn to_src(&self) -> String {
+            format!("TokenKind::{self:?}")
+        }
+        fn src_type() -> String {
+            "TokenKind".to_owned()
+        }
+    }
+
+    impl Step for TokenKind {
+        fn succ(&self) -> Option<Self> {
+            Self::ALL.get(self.index() + 1).copied()
+        }
+        fn pred(&self) -> Option<Self> {
+            Self::ALL.get(self.index().checked_sub(1)?).copied()
+        }
+    }
+
+    thread_local! {
+        /// Where `identifiers` collects the text of each identifier (the same name in generated source code).
+        static IDENTIFIERS: RefCell<Vec<String>> = const { RefCell::new(vec![]) };
+    }
+
+    /// One or more lists of identifiers in parentheses separated by commas, e.g. `(a, b)(c)`,
+    /// collecting the text of every identifier in `IDENTIFIERS`.
+    /// (Generated source code keeps one accumulator type throughout, hence `()` and a side channel.)
+    fn identifiers() -> Deterministic<TokenKind> {
+        let on = |kind, dst, update| (Range::unit(kind), Transition::Lateral { dst, update });
+        let skip = || Some(update!(|(), _: TokenKind| ()));
+        let state = |arms: Vec<_>, accepting: bool| State {
+            transitions: Curry::Scrutinize {
+                filter: RangeMap(arms.into_iter().collect()),
+                fallback: None,
+            },
+            non_accepting: if accepting {
+                BTreeSet::new()
+            } else {
+                iter::once(Rejection::UnexpectedToken).collect()
+            },
+        };
+        Graph {
+            states: vec![
+                state(vec![on(TokenKind::LParen, 1, skip())], false),
+                state(
+                    vec![on(
+                        TokenKind::Ident,
+                        2,
+                        Some(update_with_payload!(|(), (_, s): (TokenKind, String)| {
+                            IDENTIFIERS.with(|ids| ids.borrow_mut().push(s));
+                        })),
+                    )],
+                    false,
+                ),
+                state(
+                    vec![
+                        on(TokenKind::Comma, 1, skip()),
+                        on(TokenKind::RParen, 3, skip()),
+                    ],
+                    false,
+                ),
+                state(vec![on(TokenKind::LParen, 1, skip())], true),
+            ],
+            initial: 0,
+        }
+    }
+
+    #[test]
+    fn accept_with_payload() {
+        let parser = identifiers();
+        parser.check().unwrap();
+        let input = vec![
+            (TokenKind::LParen, "(".to_owned()),
+            (TokenKind::Ident, "foo".to_owned()),
+            (TokenKind::Comma, ",".to_owned()),
+            (TokenKind::Ident, "bar".to_owned()),
+            (TokenKind::RParen, ")".to_owned()),
+            (TokenKind::LParen, "(".to_owned()),
+            (TokenKind::Ident, "baz".to_owned()),
+            (TokenKind::RParen, ")".to_owned()),
+        ];
+        let expected = vec!["foo".to_owned(), "bar".to_owned(), "baz".to_owned()];
+        assert_eq!(
+            parser
+                .clone()
+                .typed::<()>()
+                .unwrap()
+                .accept_with_payload(input.clone()),
+            Ok(()),
+        );
+        assert_eq!(IDENTIFIERS.take(), expected);
+        // Without payloads, there's nothing to collect.
+        assert!(matches!(
+            parser
+                .clone()
+                .typed::<()>()
+                .unwrap()
+                .accept(input.iter().map(|&(kind, _)| kind)),
+            Err(ParseError::BadParser(IllFormed::MissingPayload(..))),
+        ));
+        assert!(matches!(
+            parser.to_src(),
+            Err(IllFormed::MissingPayload(..)),
+        ));
+        assert!(matches!(
+            parser.to_src_with(
+                |i| format!("state_{i}"),
+                CodegenOptions::default().payload("&'static str"),
+            ),
+            Err(IllFormed::TypeMismatch(..)),
+        ));
+        // Generated source code should collect exactly the same identifiers.
+        let src = parser
+            .to_src_with(
+                |i| format!("state_{i}"),
+                CodegenOptions::default().payload("String"),
+            )
+            .unwrap();
+        let dir = env::temp_dir().join(format!("inator-codegen-payload-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("main.rs");
+        fs::write(
+            &path,
+            format!(
+                "{src}\n\n#[derive(Clone, Copy, Debug, PartialEq)]\n\
+                enum TokenKind {{ LParen, Ident, Comma, RParen }}\n\n\
+                use TokenKind::*;\n\n\
+                thread_local! {{\n    \
+                static IDENTIFIERS: std::cell::RefCell<Vec<String>> = const {{ std::cell::RefCell::new(vec![]) }};\n}}\n\n\
+                fn main() {{\n    \
+                let input = {input:?}.map(|(kind, s): (TokenKind, &str)| (kind, s.to_owned()));\n    \
+                parse(input).unwrap();\n    \
+                println!(\"{{:?}}\", IDENTIFIERS.take());\n}}\n",
+            ),
+        )
+        .unwrap();
+        let binary = dir.join("test-binary");
+        let compiled = Command::new("rustc")
+            .args(["--edition", "2021", "-o"])
+            .arg(&binary)
+            .arg(&path)
+            .output()
+            .unwrap();
+        assert!(
+            compiled.status.success(),
+            "{}",
+            String::from_utf8_lossy(&compiled.stderr),
+        );
+        let ran = Command::new(&binary).output().unwrap();
+        assert_eq!(
+            String::from_utf8(ran.stdout).unwrap().trim_end(),
+            format!("{expected:?}"),
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn lazy_star_then() {
+        let input: Vec<char> = "aaab".chars().collect();
+        let greedy = literal("a").star() >> literal("a");
+        let lazy = literal("a").lazy_star_then(literal("a"));
+        assert_eq!(
+            greedy.parse_slice(&input),
+            (Ok("()".to_owned()), &['b'][..]),
+        );
+        assert_eq!(
+            lazy.parse_slice(&input),
+            (Ok("()".to_owned()), &['a', 'a', 'b'][..]),
+        );
+        // Until `follow` accepts, we keep repeating.
+        assert_eq!(
+            literal("a")
+                .lazy_star_then(literal("b"))
+                .accept("aaab".chars()),
+            Ok("()".to_owned()),
+        );
+        assert_eq!(
+            literal("a")
+                .lazy_plus_then(literal("a"))
+                .parse_slice(&input),
+            (Ok("()".to_owned()), &['a', 'b'][..]),
+        );
+    }
+
+    /// Phone numbers like `555-1234`, counting their digits.
+    const PHONE: &str = r#"
+        # Three digits, a dash, then four more.
+        initial = "a"
+
+        [[state]]
+        name = "a"
+        errors = ["Expected a phone number"]
+        transitions = [{ on = "0-9", to = "b", update = "first" }]
+
+        [[state]]
+        name = "b"
+        errors = ["Expected a digit"]
+        transitions = [{ on = "0-9", to = "c", update = "next" }]
+
+        [[state]]
+        name = "c"
+        errors = ["Expected a digit"]
+        transitions = [{ on = "0-9", to = "dash", update = "next" }]
+
+        [[state]]
+        name = "dash"
+        errors = ["Expected a dash"]
+        transitions = [{ on = "-", to = "d" }]
+
+        [[state]]
+        name = "d"
+        errors = ["Expected a digit"]
+        transitions = [{ on = "0-9", to = "e", update = "next" }]
+
+        [[state]]
+        name = "e"
+        errors = ["Expected a digit"]
+        transitions = [{ on = "0-9", to = "f", update = "next" }]
+
+        [[state]]
+        name = "f"
+        errors = ["Expected a digit"]
+        transitions = [{ on = "0-9", to = "g", update = "next" }]
+
+        [[state]]
+        name = "g"
+        errors = ["Expected a digit"]
+        transitions = [{ on = "0-9", to = "done", update = "next" }]
+
+        [[state]]
+        name = "done"
+        accepting = true
+    "#;
+
+    fn phone_updates() -> Vec<(&'static str, Update<char>)> {
+        vec![
+            ("first", update!(|(), _c: char| 1_usize)),
+            ("next", update!(|n: usize, _c: char| n + 1)),
+        ]
+    }
+
+    #[test]
+    fn assert_parses_to_explains_failure() {
+        let phone = Deterministic::from_description(PHONE, &phone_updates(), &[]).unwrap();
+        let message = *panic::catch_unwind(|| phone.assert_parses_to("555-1234".chars(), 8_usize))
+            .unwrap_err()
+            .downcast::<String>()
+            .unwrap();
+        assert!(
+            message
+                .starts_with("Parsed the wrong value (- expected, + actual):\n- 8\n+ 7\nTrace:\n"),
+            "{message}",
+        );
+        assert!(message.contains("   3  '-'"), "{message}");
+        let rejected = *panic::catch_unwind(|| phone.assert_parses_to("555".chars(), 3_usize))
+            .unwrap_err()
+            .downcast::<String>()
+            .unwrap();
+        assert!(
+            rejected.starts_with("Expected 3 but the parser rejected its input: "),
+            "{rejected}",
+        );
+    }
+
+    #[test]
+    fn from_description() {
+        let phone = Deterministic::from_description(PHONE, &phone_updates(), &[]).unwrap();
+        phone.assert_parses_to("555-1234".chars(), 7_usize);
+        for input in ["", "555", "5551234", "555-12345", "55-51234"] {
+            assert!(phone.accept(input.chars()).is_err(), "{input:?}");
+        }
+        assert_eq!(
+            Deterministic::from_description(PHONE, &[], &[])
+                .unwrap_err()
+                .to_string(),
+            "Reference to a function named `first` at line 8, column 57, \
+            but no function with that name was passed alongside the description",
+        );
+        assert_eq!(
+            Deterministic::from_description(
+                &PHONE.replace("to = \"dash\"", "to = \"dahs\""),
+                &phone_updates(),
+                &[]
+            )
+            .unwrap_err()
+            .to_string(),
+            "Reference to undefined state `dahs` at line 18, column 43",
+        );
+        assert_eq!(
+            Deterministic::from_description("initial = \"a\"\n[[state]]\nname = \"a\"\ntransitions = [{ on = \"9-0\", to = \"a\" }]", &[], &[]),
+            Err(DescriptionError::BadRange {
+                at: Position { line: 4, column: 23 },
+                range: "9-0".to_owned(),
+            }),
+        );
+        assert_eq!(
+            Deterministic::from_description("initial = \"a\"\nname = \"a\"", &[], &[]),
+            Err(DescriptionError::UnknownKey {
+                at: Position { line: 2, column: 1 },
+                key: "name".to_owned(),
+            }),
+        );
+    }
+
+    #[test]
+    fn description_round_trip() {
+        let parentheses = Deterministic::from_description(
+            r#"
+            initial = "open"
+
+            [[state]]
+            name = "open"
+            accepting = true
+            transitions = [
+                { on = "(", call = "parentheses", detour = "open", to = "open", combine = "ignore" },
+                { on = ")", return = "parentheses" },
+            ]
+            "#,
+            &[],
+            &[("ignore", ff!(|(), ()| ()))],
+        )
+        .unwrap();
+        assert_eq!(parentheses.accept("(()())".chars()), Ok("()".to_owned()));
+        assert!(matches!(
+            parentheses.accept("(()".chars()),
+            Err(ParseError::Incomplete { .. }),
+        ));
+        let combinators = [("ignore", ff!(|(), ()| ()))];
+        let exported = parentheses.to_description(&[], &combinators).unwrap();
+        assert_eq!(
+            Deterministic::from_description(&exported, &[], &combinators),
+            Ok(parentheses),
+        );
+        let phone = Deterministic::from_description(PHONE, &phone_updates(), &[]).unwrap();
+        let description = phone.to_description(&phone_updates(), &[]).unwrap();
+        assert_eq!(
+            Deterministic::from_description(&description, &phone_updates(), &[]),
+            Ok(phone.clone()),
+        );
+        assert!(matches!(
+            phone.to_description(&[], &[]),
+            Err(DescriptionError::Unnamed(..)),
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "miette")]
+    fn miette_report() {
+        let render = |report: &ParseReport<usize>| {
+            let mut rendered = String::new();
+            miette::NarratableReportHandler::new()
+                .render_report(&mut rendered, report)
+                .unwrap();
+            rendered
+        };
+        let abc = literal("abc");
+        assert_eq!(abc.accept_reporting("abc"), Ok("()".to_owned()));
+        let absurd = abc.accept_reporting("abxc").unwrap_err();
+        assert_eq!(absurd.error, ParseError::BadInput(InputError::Absurd));
+        assert_eq!(absurd.offset, 2);
+        let rendered = render(&absurd);
+        assert!(rendered.contains("Unexpected token"), "{rendered}");
+        assert!(rendered.contains("inator::absurd"), "{rendered}");
+        assert!(rendered.contains("unexpected token"), "{rendered}");
+        assert!(rendered.contains("abxc"), "{rendered}");
+        assert_eq!(
+            dyck_d()
+                .accept_reporting("()\u{e9}")
+                .map_err(|report| report.offset),
+            Err(2),
+        );
+        let incomplete = dyck_d().accept_reporting("(()").unwrap_err();
+        assert!(matches!(incomplete.error, ParseError::Incomplete { .. }));
+        assert_eq!(incomplete.offset, 3);
+        let ended = render(&incomplete);
+        assert!(ended.contains("input ended here"), "{ended}");
+        assert!(ended.contains("`parentheses`"), "{ended}");
+        // Through a `miette::Report`, with what could have come next:
+        let abc_or_abx = literal("ab") >> (literal("c") | literal("x"));
+        let wrong = abc_or_abx.accept_reporting("abz").unwrap_err();
+        assert_eq!(wrong.expected, vec![Range::unit('c'), Range::unit('x')]);
+        let labels: Vec<_> = miette::Diagnostic::labels(&wrong).unwrap().collect();
+        assert_eq!(labels.len(), 2);
+        assert!(labels
+            .iter()
+            .all(|label| label.offset() == 2 && label.len() == 1));
+        let report = miette::Report::new(wrong);
+        let mut narrated = String::new();
+        miette::NarratableReportHandler::new()
+            .render_report(&mut narrated, report.as_ref())
+            .unwrap();
+        assert!(narrated.contains("unexpected token"), "{narrated}");
+        assert!(narrated.contains("expected 'c' or 'x'"), "{narrated}");
+        assert!(narrated.contains("abz"), "{narrated}");
+        assert!(matches!(
+            render(&literal("ab").accept_reporting("a").unwrap_err()).find("expected 'b'"),
+            Some(..),
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "unicode")]
+    fn accept_graphemes() {
+        // Exactly one character, whatever it is.
+        let one: Deterministic<char> = Graph {
+            states: vec![
+                State {
+                    transitions: Curry::Wildcard(Transition::Lateral {
+                        dst: 1,
+                        update: None,
+                    }),
+                    non_accepting: iter::once(Rejection::UnexpectedToken).collect(),
+                },
+                State {
+                    transitions: Curry::Scrutinize {
+                        filter: RangeMap(BTreeMap::new()),
+                        fallback: None,
+                    },
+                    non_accepting: BTreeSet::new(),
+                },
+            ],
+            initial: 0,
+        };
+        // `e` followed by a combining acute accent.
+        let accented = "e\u{301}";
+        assert!(matches!(one.accept(accented.chars()), Err(..)));
+        assert_eq!(one.accept_graphemes(accented), Ok("()".to_owned()));
+        assert_eq!(one.accept_graphemes("e"), Ok("()".to_owned()));
+        assert!(matches!(one.accept_graphemes("ee"), Err(..)));
+        assert!(matches!(one.accept_graphemes(""), Err(..)));
+    }
 }
@@ -7,10 +7,10 @@
 //! Translate an automaton into Rust source code.
 
 use crate::{
-    Ctrl, Curry, Deterministic, Graph, IllFormed, Input, Range, RangeMap, State, Transition,
-    Update, FF,
+    Ctrl, Curry, Deterministic, Graph, IllFormed, Input, Range, RangeMap, Rejection, State, Step,
+    Transition, Update, FF,
 };
-use core::ops::Bound;
+use core::{iter, ops::Bound};
 use std::collections::{BTreeMap, BTreeSet};
 
 /// Translate a value into Rust source code that reproduces it.
@@ -161,7 +161,7 @@ impl ToSrc for &str {
     }
 }
 
-impl<T: Clone + Ord + ToSrc> ToSrc for Range<T> {
+impl<T: Input> ToSrc for Range<T> {
     #[inline]
     #[must_use]
     fn to_src(&self) -> String {
@@ -174,15 +174,264 @@ impl<T: Clone + Ord + ToSrc> ToSrc for Range<T> {
     }
 }
 
-impl<I: Input> Deterministic<I> {
+/// Opt-in extras for generated source code (see `Deterministic::to_src_with`).
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CodegenOptions {
+    /// Mark `parse` as deprecated (so every call warns) if the parser accepts the empty input.
+    pub warn_if_accepts_empty: bool,
+    /// Name of the public function that runs the parser (`parse` if `None`).
+    pub entry_point: Option<&'static str>,
+    /// How many pass-through states deep to write inline instead of calling their functions (none if zero).
+    pub inline_depth: usize,
+    /// Whether to document each state's function with a shortest input that reaches it.
+    pub state_docs: StateDocs,
+    /// Source-code representation of the type of each token's payload, if tokens come with payloads
+    /// (see `Deterministic::accept_with_payload`).
+    pub payload: Option<&'static str>,
+    /// Name of a zero-sized handle to the parser, to pass it around as a value (none if `None`).
+    pub handle: Option<&'static str>,
+}
+
+/// Whether to document each state's function with a shortest input that reaches it (see `CodegenOptions::state_docs`).
+#[allow(clippy::exhaustive_enums)]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum StateDocs {
+    /// No documentation.
+    #[default]
+    Off,
+    /// The entire input, however long.
+    Full,
+    /// At most this many tokens, then an ellipsis (e.g. to keep huge grammars readable).
+    TruncatedTo(usize),
+}
+
+impl CodegenOptions {
+    /// Mark `parse` as deprecated (so every call warns) if the parser accepts the empty input.
+    #[inline]
+    #[must_use]
+    pub const fn warn_if_accepts_empty(mut self, warn: bool) -> Self {
+        self.warn_if_accepts_empty = warn;
+        self
+    }
+
+    /// Name the public function that runs the parser something other than `parse`.
+    #[inline]
+    #[must_use]
+    pub const fn entry_point(mut self, name: &'static str) -> Self {
+        self.entry_point = Some(name);
+        self
+    }
+
+    /// Write pass-through states (those that take exactly one range of tokens to one state, without an update)
+    /// inline where they're reached, up to `depth` in a row, instead of each as its own function.
+    /// Behaves identically but generates fewer functions (and fewer calls), e.g. for long literals.
+    #[inline]
+    #[must_use]
+    pub const fn inline_pass_through(mut self, depth: usize) -> Self {
+        self.inline_depth = depth;
+        self
+    }
+
+    /// Document each state's function with a shortest input that reaches it (found in one search over every state),
+    /// e.g. to read generated source code alongside the grammar.
+    #[inline]
+    #[must_use]
+    pub const fn state_docs(mut self, docs: StateDocs) -> Self {
+        self.state_docs = docs;
+        self
+    }
+
+    /// Take tokens paired with payloads of this type (e.g. `(kind, text)` pairs from a lexer),
+    /// exactly as `Deterministic::accept_with_payload` does, instead of tokens alone.
+    #[inline]
+    #[must_use]
+    pub const fn payload(mut self, payload_t: &'static str) -> Self {
+        self.payload = Some(payload_t);
+        self
+    }
+
+    /// Also write a zero-sized `pub struct` with this name, whose `parse` method runs the parser
+    /// and whose `NAME` is the name of the function it calls,
+    /// e.g. to hand a parser to code that only knows it as a value.
+    /// The free function stays exactly as it was; the handle only wraps it.
+    #[inline]
+    #[must_use]
+    pub const fn handle(mut self, name: &'static str) -> Self {
+        self.handle = Some(name);
+        self
+    }
+}
+
+/// Writes the source code that continues in a state: usually a call to its function,
+/// but for pass-through states (if enabled) the state itself, inline.
+struct Calls<'graph, I: Input> {
+    /// Every state in the parser.
+    states: &'graph [State<I, usize>],
+    /// Name of each state's function.
+    names: &'graph [String],
+    /// How many more pass-through states in a row we may write inline.
+    depth: usize,
+    /// States whose functions something actually calls.
+    called: BTreeSet<usize>,
+    /// Pattern binding each item from the input (an index and a token, plus a payload if tokens come with payloads).
+    binding: &'static str,
+}
+
+impl<I: Step> Calls<'_, I> {
+    /// Continue in state `dst` with this (source code for the) accumulator.
+    #[inline]
+    fn goto(&mut self, dst: usize, acc: &str) -> String {
+        let state = get!(self.states, dst);
+        if let Some(depth) = self.depth.checked_sub(1) {
+            if let Some((range, next)) = state.pass_through() {
+                let on_none = state.on_none();
+                self.depth = depth;
+                let then = self.goto(next, "acc");
+                self.depth = depth.saturating_add(1);
+                return format!(
+                    "{{
+                let acc = {acc};
+                match input.next() {{
+                    None => {on_none},
+                    Some({}) => match token {{
+                        {} => {{
+                            {then}
+                        }}
+                        _ => Err(Error::Absurd {{ index, token }}),
+                    }},
+                }}
+            }}",
+                    self.binding,
+                    range_pattern(range),
+                );
+            }
+        }
+        let _ = self.called.insert(dst);
+        format!("{}(input, {acc}, stack_top)", get!(self.names, dst))
+    }
+}
+
+/// Match arm pattern for a range of tokens: the one place a range (or a single token) becomes source,
+/// shared by codegen and by the grammar `describe` writes, so the two always escape tokens the same way.
+#[inline]
+pub(crate) fn range_pattern<I: Input>(range: &Range<I>) -> String {
+    if range.first == range.last {
+        range.first.to_src()
+    } else {
+        range.to_src()
+    }
+}
+
+impl<I: Step> Deterministic<I> {
     /// Translate a value into Rust source code that reproduces it.
     /// # Errors
     /// If this automaton is ill-formed.
     #[inline]
-    #[allow(clippy::arithmetic_side_effects)] // <-- String concatenation with `+`
     pub fn to_src(&self) -> Result<String, IllFormed<I, usize>> {
+        self.to_src_named_by(|i| format!("state_{i}"))
+    }
+
+    /// Translate a value into Rust source code that reproduces it,
+    /// naming the function for each state (given its index) with a user-supplied function, e.g. `breadcrumb_names`.
+    /// Names must be distinct, valid Rust identifiers.
+    /// # Errors
+    /// If this automaton is ill-formed.
+    #[inline]
+    pub fn to_src_named_by<F: FnMut(usize) -> String>(
+        &self,
+        name: F,
+    ) -> Result<String, IllFormed<I, usize>> {
+        self.to_src_with(name, CodegenOptions::default())
+    }
+
+    /// Translate a value into Rust source code that reproduces it,
+    /// naming states as in `to_src_named_by` and with any opt-in extras in `options`.
+    /// # Errors
+    /// If this automaton is ill-formed.
+    #[inline]
+    #[allow(clippy::arithmetic_side_effects)] // <-- String concatenation with `+`
+    pub fn to_src_with<F: FnMut(usize) -> String>(
+        &self,
+        name: F,
+        options: CodegenOptions,
+    ) -> Result<String, IllFormed<I, usize>> {
+        let warning = if options.warn_if_accepts_empty && self.accepts_empty() {
+            r#"
+/// Note: this parser accepts the empty input.
+#[deprecated(note = "This parser accepts the empty input: is that intentional?")]"#
+        } else {
+            ""
+        };
+        let entry_point = options.entry_point.unwrap_or("parse");
+        let names: Vec<String> = (0..self.states.len()).map(name).collect();
+        let docs = self.state_docs(options.state_docs);
         let token_t = I::src_type();
-        let output_t = self.output_type()?.unwrap_or("core::convert::Infallible");
+        self.check_payloads(options.payload)?;
+        let (item_t, binding) = options.payload.map_or_else(
+            || (token_t.clone(), "(index, token)"),
+            |payload_t| {
+                (
+                    format!("({token_t}, {payload_t})"),
+                    "(index, (token, payload))",
+                )
+            },
+        );
+        let mut calls = Calls {
+            states: &self.states,
+            names: &names,
+            depth: options.inline_depth,
+            called: iter::once(self.initial).collect(),
+            binding,
+        };
+        let (output_t, signatures) = self.signatures()?;
+        // Write each state whose function anything calls (or every state, if we're not inlining).
+        let mut functions = BTreeMap::new();
+        let mut queue: Vec<usize> = if options.inline_depth == 0 {
+            (0..self.states.len()).collect()
+        } else {
+            vec![self.initial]
+        };
+        while let Some(i) = queue.pop() {
+            if functions.contains_key(&i) {
+                continue;
+            }
+            let src = get!(self.states, i).to_src(
+                get!(names, i),
+                get!(docs, i),
+                &item_t,
+                *get!(signatures, i),
+                &mut calls,
+            );
+            drop(functions.insert(i, src));
+            queue.extend(calls.called.iter().filter(|j| !functions.contains_key(j)));
+        }
+        let handle = options.handle.map_or_else(String::new, |handle| {
+            // Pass the warning along to anyone who calls the handle instead.
+            let handle_warning = if warning.is_empty() {
+                String::new()
+            } else {
+                format!("{warning}\n    #[allow(deprecated)]")
+            };
+            format!(
+                r#"
+
+/// Handle to this parser, to pass it around as a value.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct {handle};
+
+impl {handle} {{
+    /// Name of the function this handle calls.
+    pub const NAME: &'static str = "{entry_point}";
+
+    /// Run the parser (exactly as `{entry_point}` does).
+    #[inline]{handle_warning}
+    pub fn parse<I: IntoIterator<Item = {item_t}>>(self, input: I) -> Result<{output_t}, Error> {{
+        {entry_point}(input)
+    }}
+}}"#
+            )
+        });
         Ok(format!(
             r#"//! Automatically generated with [inator](https://crates.io/crates/inator).
 
@@ -225,28 +474,241 @@ type R<I> = Result<(Option<(usize, Option<F<I>>)>, {output_t}), Error>;
 #[repr(transparent)]
 struct F<I>(fn(&mut I, {output_t}) -> R<I>);
 
+#[inline]{warning}
+pub fn {entry_point}<I: IntoIterator<Item = {item_t}>>(input: I) -> Result<{output_t}, Error> {{
+    {}(&mut input.into_iter().enumerate(), (), None)
+}}{handle}{}
+"#,
+            get!(names, self.initial),
+            functions.into_values().collect::<String>(),
+        ))
+    }
+
+    /// What the whole parser outputs, and what each state's function takes and returns:
+    /// whatever the whole parser outputs, or, inside a region, whatever that region hands back to the state that called it.
+    /// # Errors
+    /// If any transition's output doesn't match what its destination expects.
+    #[inline]
+    #[allow(clippy::type_complexity)]
+    fn signatures(&self) -> Result<(&str, Vec<(&str, &str)>), IllFormed<I, usize>> {
+        let output_t = self.output_type()?.unwrap_or("core::convert::Infallible");
+        let mut returns = vec![None; self.states.len()];
+        let mut queue = vec![(self.initial, output_t)];
+        while let Some((i, returned)) = queue.pop() {
+            let Some(slot @ &mut None) = returns.get_mut(i) else {
+                continue;
+            };
+            *slot = Some(returned);
+            for transition in get!(self.states, i).transitions.values() {
+                follow(transition, returned, &mut queue);
+            }
+        }
+        let signatures = self
+            .accumulator_types()?
+            .into_iter()
+            .zip(returns)
+            .map(|(takes, returned)| {
+                let input_t = takes.unwrap_or("core::convert::Infallible");
+                (input_t, returned.unwrap_or(input_t))
+            })
+            .collect();
+        Ok((output_t, signatures))
+    }
+}
+
+impl<I: Step> Deterministic<I> {
+    /// Check that every function reading payloads expects exactly the payload type tokens come with (if any).
+    #[inline]
+    fn check_payloads(&self, payload: Option<&str>) -> Result<(), IllFormed<I, usize>> {
+        for state in &self.states {
+            for transition in state.transitions.values() {
+                let mut next = Some(transition);
+                while let Some(t) = next.take() {
+                    match *t {
+                        Transition::Lateral {
+                            update: Some(ref update),
+                            ..
+                        } => match (payload, update.payload_t.as_deref()) {
+                            (_, None) => {}
+                            (None, Some(_)) => {
+                                return Err(IllFormed::MissingPayload(update.src.to_string()))
+                            }
+                            (Some(expected), Some(actual)) => {
+                                if expected != actual {
+                                    return Err(IllFormed::TypeMismatch(
+                                        expected.to_owned(),
+                                        actual.to_owned(),
+                                    ));
+                                }
+                            }
+                        },
+                        Transition::Call { ref dst, .. } => next = Some(dst),
+                        Transition::Lateral { update: None, .. } | Transition::Return { .. } => {}
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Doc comment (possibly empty) for each state's function.
+    #[inline]
+    fn state_docs(&self, docs: StateDocs) -> Vec<String> {
+        let limit = match docs {
+            StateDocs::Off => return vec![String::new(); self.states.len()],
+            StateDocs::Full => usize::MAX,
+            StateDocs::TruncatedTo(limit) => limit,
+        };
+        self.shortest_inputs()
+            .into_iter()
+            .map(|shortest| {
+                let Some(path) = shortest else {
+                    return "\n/// Not reached by any input without closing a region.".to_owned();
+                };
+                if path.is_empty() {
+                    return "\n/// Reached by the empty input.".to_owned();
+                }
+                let mut tokens: Vec<String> = path.iter().take(limit).map(ToSrc::to_src).collect();
+                if path.len() > limit {
+                    tokens.push("...".to_owned());
+                }
+                format!("\n/// Reached by, e.g., `{}`.", tokens.join(" "))
+            })
+            .collect()
+    }
+}
+
+impl Deterministic<u8> {
+    /// Translate this parser into Rust source code with its transitions as a `const` table
+    /// and a `const fn accept_bytes(&[u8]) -> bool`, e.g. to validate literals at compile time.
+    /// Only decides acceptance: any output is ignored.
+    /// # Errors
+    /// If this automaton is ill-formed or opens or closes a region.
+    #[inline]
+    pub fn to_const_src(&self) -> Result<String, IllFormed<u8, usize>> {
+        self.check()?;
+        let reject = self.states.len();
+        let mut rows = String::new();
+        for state in &self.states {
+            let mut dsts = Vec::with_capacity(256);
+            for token in 0..=u8::MAX {
+                dsts.push(match state.transitions.get(&token)? {
+                    None => reject,
+                    Some(&Transition::Lateral { dst, .. }) => dst,
+                    Some(&(Transition::Call { region, .. } | Transition::Return { region })) => {
+                        return Err(IllFormed::ConstOverRegion(region))
+                    }
+                });
+            }
+            let row: Vec<String> = dsts.iter().map(ToString::to_string).collect();
+            rows.push_str("    [");
+            rows.push_str(&row.join(", "));
+            rows.push_str("],\n");
+        }
+        let accepting: Vec<&str> = self
+            .states
+            .iter()
+            .map(|state| {
+                if state.non_accepting.is_empty() {
+                    "true"
+                } else {
+                    "false"
+                }
+            })
+            .collect();
+        Ok(format!(
+            r#"//! Automatically generated with [inator](https://crates.io/crates/inator).
+
+/// Sentinel state meaning the input has already been rejected.
+const REJECT: usize = {reject};
+
+/// Next state for each state (row) and byte (column).
+#[rustfmt::skip]
+const TRANSITIONS: [[usize; 256]; {reject}] = [
+{rows}];
+
+/// Whether input ending in each state would be accepted.
+const ACCEPTING: [bool; {reject}] = [{}];
+
+/// Decide whether the parser accepts this input (at compile time, if you'd like).
 #[inline]
-pub fn parse<I: IntoIterator<Item = {token_t}>>(input: I) -> Result<{output_t}, Error> {{
-    state_{}(&mut input.into_iter().enumerate(), (), None)
-}}{}
+#[must_use]
+pub const fn accept_bytes(input: &[u8]) -> bool {{
+    let mut state = {};
+    let mut i = 0;
+    while i < input.len() {{
+        state = TRANSITIONS[state][input[i] as usize];
+        if state == REJECT {{
+            return false;
+        }}
+        i += 1;
+    }}
+    ACCEPTING[state]
+}}
 "#,
+            accepting.join(", "),
             self.initial,
-            self.states
-                .iter()
-                .enumerate()
-                .try_fold(String::new(), |acc, (i, s)| Ok(acc + &s.to_src(i)?))?,
         ))
     }
 }
 
-impl<I: Input> State<I, usize> {
+/// Queue every state this transition leads to, along with what that state's function returns:
+/// the same as this one's, except for a region's detour, which returns whatever its region hands back.
+#[inline]
+fn follow<'graph, I: Input>(
+    transition: &'graph Transition<I, usize>,
+    returned: &'graph str,
+    queue: &mut Vec<(usize, &'graph str)>,
+) {
+    match *transition {
+        Transition::Lateral { dst, .. } => queue.push((dst, returned)),
+        Transition::Call {
+            detour,
+            ref dst,
+            ref combine,
+            ..
+        } => {
+            queue.push((detour, &combine.rhs_t));
+            follow(dst, returned, queue);
+        }
+        Transition::Return { .. } => {}
+    }
+}
+
+impl<I: Step> State<I, usize> {
     /// Translate a value into Rust source code that reproduces it.
     #[inline]
-    fn to_src(&self, i: usize) -> Result<String, IllFormed<I, usize>> {
-        let input_t = self.input_type()?.unwrap_or("core::convert::Infallible");
-        let token_t = I::src_type();
-        let on_some = self.transitions.to_src();
-        let on_none = self.non_accepting.first().map_or_else(
+    fn to_src(
+        &self,
+        name: &str,
+        doc: &str,
+        item_t: &str,
+        (input_t, output_t): (&str, &str),
+        calls: &mut Calls<'_, I>,
+    ) -> String {
+        let binding = calls.binding;
+        let on_some = self.transitions.to_src(calls);
+        let on_none = self.on_none();
+        format!(
+            r#"
+
+{doc}
+#[inline]
+fn {name}<I: Iterator<Item = (usize, {item_t})>>(input: &mut I, acc: {input_t}, stack_top: Option<(&'static str, usize)>) -> Result<{output_t}, Error> {{
+    match input.next() {{
+        None => {on_none},
+        Some({binding}) => match token {{{on_some}
+        }},
+    }}
+}}"#,
+        )
+    }
+
+    /// Source code for what to do if input ends in this state.
+    #[inline]
+    #[allow(clippy::arithmetic_side_effects)] // <-- String concatenation with `+`
+    fn on_none(&self) -> String {
+        self.non_accepting.first().map_or_else(
             || {
                 "stack_top.map_or(
             Ok(acc),
@@ -260,89 +722,113 @@ impl<I: Input> State<I, usize> {
                     .fold(
                         format!(
                             "Err(Error::UserDefined {{ messages: &[{}",
-                            fst.as_str().to_src(),
+                            fst.to_string().as_str().to_src(),
                         ),
-                        |acc, msg| format!("{acc}, {}", msg.as_str().to_src()),
+                        |acc, msg| format!("{acc}, {}", msg.to_string().as_str().to_src()),
                     )
                     + "] })"
             },
-        );
-        Ok(format!(
-            r#"
-
+        )
+    }
 
-#[inline]
-fn state_{i}<I: Iterator<Item = (usize, {token_t})>>(input: &mut I, acc: {input_t}, stack_top: Option<(&'static str, usize)>) -> Result<{input_t}, Error> {{
-    match input.next() {{
-        None => {on_none},
-        Some((index, token)) => match token {{{on_some}
-        }},
-    }}
-}}"#,
-        ))
+    /// If this state takes exactly one range of tokens to exactly one state without an update
+    /// (and nothing else), that range and that state.
+    #[inline]
+    fn pass_through(&self) -> Option<(&Range<I>, usize)> {
+        let Curry::Scrutinize {
+            ref filter,
+            fallback: None,
+        } = self.transitions
+        else {
+            return None;
+        };
+        match *filter.0.iter().collect::<Vec<_>>() {
+            [(range, &Transition::Lateral { dst, update: None })] => Some((range, dst)),
+            _ => None,
+        }
     }
 }
 
-impl<I: Input> Curry<I, usize> {
+impl<I: Step> Curry<I, usize> {
     /// Translate a value into Rust source code that reproduces it.
     #[inline]
     #[must_use]
-    fn to_src(&self) -> String {
+    fn to_src(&self, calls: &mut Calls<'_, I>) -> String {
         match *self {
             Self::Wildcard(ref etc) => format!(
                 r#"
             _ => {{
                 {}
             }}"#,
-                etc.to_src(),
+                etc.to_src(calls),
             ),
             Self::Scrutinize {
                 ref filter,
                 ref fallback,
             } => format!(
                 "{}
-            _ => {}",
-                filter.to_src(),
+            _ => {{
+                {}
+            }}",
+                filter.to_src(calls),
                 fallback.as_ref().map_or_else(
                     || "Err(Error::Absurd { index, token })".to_owned(),
-                    Transition::to_src,
+                    |f| f.to_src(calls),
                 )
             ),
         }
     }
 }
 
-impl<I: Input> RangeMap<I, usize> {
+impl<I: Step> RangeMap<I, usize> {
     /// Translate a value into Rust source code that reproduces it.
+    /// Adjacent ranges taking identical transitions merge into one range,
+    /// and disjoint ranges taking identical transitions share one arm.
     #[inline]
     #[must_use]
-    fn to_src(&self) -> String {
-        self.0.iter().fold(String::new(), |acc, (k, v)| {
+    fn to_src(&self, calls: &mut Calls<'_, I>) -> String {
+        let mut coalesced = self.clone();
+        coalesced.coalesce();
+        let mut arms: BTreeMap<&Transition<I, usize>, Vec<&Range<I>>> = BTreeMap::new();
+        for (k, v) in &coalesced.0 {
+            arms.entry(v).or_default().push(k);
+        }
+        arms.into_iter().fold(String::new(), |acc, (v, ks)| {
             format!(
                 r#"{acc}
             {} => {{
                 {}
             }},"#,
-                k.to_src(),
-                v.to_src(),
+                ks.into_iter()
+                    .map(range_pattern)
+                    .collect::<Vec<_>>()
+                    .join(" | "),
+                v.to_src(calls),
             )
         })
     }
 }
 
-impl<I: Input> Transition<I, usize> {
+impl<I: Step> Transition<I, usize> {
     /// Translate a value into Rust source code that reproduces it.
     #[inline]
     #[must_use]
-    fn to_src(&self) -> String {
+    fn to_src(&self, calls: &mut Calls<'_, I>) -> String {
         match *self {
-            Self::Lateral { dst, update: None } => {
-                format!("state_{dst}(input, acc, stack_top)")
-            }
+            Self::Lateral { dst, update: None } => calls.goto(dst, "acc"),
+            Self::Lateral {
+                dst,
+                update:
+                    Some(Update {
+                        ref src,
+                        payload_t: None,
+                        ..
+                    }),
+            } => calls.goto(dst, &format!("({src})(acc, token)")),
             Self::Lateral {
                 dst,
-                update: Some(Update { src, .. }),
-            } => format!("state_{dst}(input, ({src})(acc, token), stack_top)"),
+                update: Some(Update { ref src, .. }),
+            } => calls.goto(dst, &format!("({src})(acc, (token, payload))")),
             Self::Call {
                 region,
                 detour,
@@ -350,11 +836,15 @@ impl<I: Input> Transition<I, usize> {
                 combine: FF { ref src, .. },
             } => format!(
                 "\
-                let detour = state_{detour}(input, (), Some(({}, index)))?;
-                let postprocessed = ({src})(acc, detour);
+                let detour = {}(input, (), Some(({}, index)))?;
+                let acc = ({src})(acc, detour);
                 {}",
+                {
+                    let _ = calls.called.insert(detour);
+                    get!(calls.names, detour)
+                },
                 region.to_src(),
-                dst.to_src(),
+                dst.to_src(calls),
             ),
             Self::Return { region } => {
                 format!(
@@ -402,7 +892,38 @@ impl<T: ToSrc> ToSrc for Vec<T> {
     }
     #[inline]
     fn src_type() -> String {
-        format!("Vce::<{}>", T::src_type())
+        format!("Vec::<{}>", T::src_type())
+    }
+}
+
+impl<I: Input> ToSrc for Rejection<I> {
+    #[inline]
+    fn to_src(&self) -> String {
+        let range = |r: &Range<I>| {
+            format!(
+                "Range {{ first: {}, last: {} }}",
+                r.first.to_src(),
+                r.last.to_src(),
+            )
+        };
+        match *self {
+            Self::ExpectedRange(ref r) => format!("Rejection::ExpectedRange({})", range(r)),
+            Self::ExpectedEndAfter(ref r) => format!("Rejection::ExpectedEndAfter({})", range(r)),
+            Self::ExpectedEndAfterAny => "Rejection::ExpectedEndAfterAny".to_owned(),
+            Self::ExpectedEof(ref eof) => format!("Rejection::ExpectedEof({})", eof.to_src()),
+            Self::Terminated(ref token) => format!("Rejection::Terminated({})", token.to_src()),
+            Self::UnfinishedConcatenation => "Rejection::UnfinishedConcatenation".to_owned(),
+            Self::UnfinishedCall => "Rejection::UnfinishedCall".to_owned(),
+            Self::UnexpectedToken => "Rejection::UnexpectedToken".to_owned(),
+            Self::Product => "Rejection::Product".to_owned(),
+            Self::Custom(ref message) => {
+                format!("Rejection::Custom({}.into())", (&**message).to_src())
+            }
+        }
+    }
+    #[inline]
+    fn src_type() -> String {
+        format!("Rejection::<{}>", I::src_type())
     }
 }
 
@@ -527,7 +1048,15 @@ impl<I: Input, C: Ctrl<I>> ToSrc for Transition<I, C> {
 impl<I: Input> ToSrc for Update<I> {
     #[inline]
     fn to_src(&self) -> String {
-        format!("update!({})", self.src.to_src())
+        format!(
+            "{}!({})",
+            if self.payload_t.is_some() {
+                "update_with_payload"
+            } else {
+                "update"
+            },
+            (&*self.src).to_src(),
+        )
     }
     #[inline]
     fn src_type() -> String {
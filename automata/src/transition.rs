@@ -6,9 +6,9 @@
 
 //! Transition in an automaton: an action and a destination state.
 
-use crate::{Ctrl, Input, InputError, Merge, ParseError, Update, FF};
-use core::{cmp, iter, mem};
-use std::collections::BTreeSet;
+use crate::{Ctrl, IllFormed, Input, InputError, Merge, ParseError, Update, FF};
+use core::{cmp, hash, iter, mem};
+use std::collections::{BTreeSet, VecDeque};
 
 // TODO: rename `Call` to `Open` and `Return` to `Close`
 
@@ -26,10 +26,14 @@ pub enum Transition<I: Input, C: Ctrl<I>> {
     /// Call another function--i.e., push a pointer/index onto the stack.
     Call {
         /// Region (user-defined name) that we're opening. Sensible to be e.g. "parentheses" for `(...)`.
+        /// Regions are compared by name, never by address, so two copies of the same string are the same region.
         region: &'static str,
         /// Call (and require a successful run from) this state before continuing.
         detour: C,
-        /// After the call has succeeded, go to this state.
+        /// After the call has succeeded, take this transition without reading another token:
+        /// usually `Lateral` (see `call_lateral`), but `Return` closes the enclosing region at the same time,
+        /// and another `Call` opens a new region right away (e.g. from `^`).
+        /// Every nesting of these means something, so none is ill-formed.
         dst: Box<Self>,
         /// Combine the cached results and the results of the called parser with this function.
         combine: FF,
@@ -78,6 +82,34 @@ impl<I: Input, C: Ctrl<I>> PartialEq for Transition<I, C> {
 }
 impl<I: Input, C: Ctrl<I>> Eq for Transition<I, C> {}
 
+impl<I: Input + hash::Hash, C: Ctrl<I> + hash::Hash> hash::Hash for Transition<I, C> {
+    #[inline]
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match *self {
+            Self::Lateral {
+                ref dst,
+                ref update,
+            } => {
+                dst.hash(state);
+                update.hash(state);
+            }
+            Self::Call {
+                region,
+                ref detour,
+                ref dst,
+                ref combine,
+            } => {
+                region.hash(state);
+                detour.hash(state);
+                dst.hash(state);
+                combine.hash(state);
+            }
+            Self::Return { region } => region.hash(state),
+        }
+    }
+}
+
 impl<I: Input, C: Ctrl<I>> Ord for Transition<I, C> {
     #[inline]
     fn cmp(&self, other: &Self) -> cmp::Ordering {
@@ -129,6 +161,26 @@ impl<I: Input, C: Ctrl<I>> PartialOrd for Transition<I, C> {
 }
 
 impl<I: Input, C: Ctrl<I>> Transition<I, C> {
+    /// Open a region at `detour`, then, once it closes, combine outputs with `combine`
+    /// and go to `dst` (calling `update` on the way, if any).
+    /// Almost every call looks like this, and it's much less awkward than building a `Call` by hand.
+    #[inline]
+    #[must_use]
+    pub fn call_lateral(
+        region: &'static str,
+        detour: C,
+        dst: C,
+        update: Option<Update<I>>,
+        combine: FF,
+    ) -> Self {
+        Self::Call {
+            region,
+            detour,
+            dst: Box::new(Self::Lateral { dst, update }),
+            combine,
+        }
+    }
+
     /// Take this transition in an actual execution.
     /// Return the index of the machine's state after this transition.
     /// # Errors
@@ -153,20 +205,36 @@ impl<I: Input, C: Ctrl<I>> Transition<I, C> {
             ))),
             Self::Call {
                 ref detour,
-                ref dst,
+                ref combine,
                 ..
             } => {
-                stack.push(*dst.clone());
+                if output_t != &*combine.lhs_t {
+                    return Err(ParseError::BadParser(IllFormed::TypeMismatch(
+                        output_t.to_owned(),
+                        combine.lhs_t.to_string(),
+                    )));
+                }
+                // Keep the whole call, so we can combine its output once the region closes:
+                stack.push(self.clone());
                 Ok(Some((detour.clone(), "()".to_owned())))
             }
             Self::Return { .. } => {
                 let rtn_to = stack
                     .pop()
                     .ok_or(ParseError::BadInput(InputError::Unopened))?;
-                // Ok(Some((rtn_to, output_t.to_owned())))
                 // No longer strictly small-step semantics,
                 // but the alternative is a nightmare
-                rtn_to.invoke(output_t, stack)
+                match rtn_to {
+                    Self::Call {
+                        ref dst,
+                        ref combine,
+                        ..
+                    } => dst.invoke(
+                        &combine.invoke(output_t).map_err(ParseError::BadParser)?,
+                        stack,
+                    ),
+                    Self::Lateral { .. } | Self::Return { .. } => rtn_to.invoke(output_t, stack),
+                }
             }
         }
     }
@@ -176,7 +244,7 @@ impl<I: Input, C: Ctrl<I>> Transition<I, C> {
     #[must_use]
     pub fn input_type(&self) -> Option<&str> {
         match *self {
-            Self::Lateral { ref update, .. } => update.as_ref().map(|u| u.input_t.as_str()),
+            Self::Lateral { ref update, .. } => update.as_ref().map(|u| &*u.input_t),
             Self::Call { ref combine, .. } => Some(&combine.lhs_t),
             Self::Return { .. } => None,
         }
@@ -200,6 +268,36 @@ impl<I: Input, C: Ctrl<I>> Transition<I, C> {
         }
     }
 
+    /// Queue each state this transition could go to, in a canonical order
+    /// (a call's detour before its continuation) instead of by index.
+    #[inline]
+    pub(crate) fn search_order(&self, queue: &mut VecDeque<usize>) {
+        match *self {
+            Self::Lateral { ref dst, .. } => queue.extend(dst.view()),
+            Self::Call {
+                ref detour,
+                ref dst,
+                ..
+            } => {
+                queue.extend(detour.view());
+                dst.search_order(queue);
+            }
+            Self::Return { .. } => {}
+        }
+    }
+
+    /// Update this transition calls on the output, if any
+    /// (for calls, the update on the continuation after the region closes).
+    #[inline]
+    #[must_use]
+    pub fn update(&self) -> Option<&Update<I>> {
+        match *self {
+            Self::Lateral { ref update, .. } => update.as_ref(),
+            Self::Call { ref dst, .. } => dst.update(),
+            Self::Return { .. } => None,
+        }
+    }
+
     /// Natural-language representation of the action we're taking on the stack.
     #[inline]
     #[must_use]
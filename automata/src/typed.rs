@@ -0,0 +1,120 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Attach the Rust type a parser computes, checked once up front instead of on every run.
+
+use crate::{Ctrl, Deterministic, Graph, IllFormed, Input, Step, ToSrc};
+use core::{fmt, hash, marker::PhantomData, ops};
+
+/// A parser that promises to compute an `O`.
+///
+/// Interpreting it (e.g. with `accept`) produces an `O` directly.
+/// Only made by `Graph::typed`, which checks the promise against the output type in the source code of the parser's functions.
+/// Reads like the parser itself (e.g. `check`, `to_src`); combining it with anything (e.g. with `>>`) forgets `O`,
+/// as does `erase`, e.g. to call a method that takes the parser itself (like `star`).
+pub struct Typed<G, O> {
+    /// The parser itself.
+    graph: G,
+    /// The type it computes.
+    output: PhantomData<fn() -> O>,
+}
+
+impl<I: Input, C: Ctrl<I>> Graph<I, C> {
+    /// Promise that this parser computes an `O`, so interpreting it (e.g. with `accept`) produces an `O` directly.
+    /// Checked once, here, against the output type in the source code of this parser's functions.
+    /// # Errors
+    /// If this parser computes anything other than an `O` (or can't agree on what it computes).
+    #[inline]
+    pub fn typed<O: ToSrc>(self) -> Result<Typed<Self, O>, IllFormed<I, C>> {
+        let output_t = self.output_type()?.unwrap_or("()");
+        let expected = O::src_type();
+        if output_t != expected {
+            return Err(IllFormed::WrongReturnType(output_t.to_owned(), expected));
+        }
+        Ok(Typed {
+            graph: self,
+            output: PhantomData,
+        })
+    }
+}
+
+impl<G, O> Typed<G, O> {
+    /// Forget which Rust type this parser computes, e.g. to modify it by hand.
+    #[inline]
+    #[must_use]
+    pub fn erase(self) -> G {
+        self.graph
+    }
+}
+
+impl<I: Input, C: Ctrl<I>, O> From<Typed<Self, O>> for Graph<I, C> {
+    #[inline]
+    fn from(value: Typed<Self, O>) -> Self {
+        value.erase()
+    }
+}
+
+impl<G, O> ops::Deref for Typed<G, O> {
+    type Target = G;
+    #[inline]
+    fn deref(&self) -> &G {
+        &self.graph
+    }
+}
+
+impl<G: Clone, O> Clone for Typed<G, O> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            graph: self.graph.clone(),
+            output: PhantomData,
+        }
+    }
+}
+
+impl<G: fmt::Debug, O> fmt::Debug for Typed<G, O> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Typed").field(&self.graph).finish()
+    }
+}
+
+impl<G: Eq, O> Eq for Typed<G, O> {}
+
+impl<G: PartialEq, O> PartialEq for Typed<G, O> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.graph == other.graph
+    }
+}
+
+impl<G: hash::Hash, O> hash::Hash for Typed<G, O> {
+    #[inline]
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.graph.hash(state);
+    }
+}
+
+/// Implement a binary operator on typed parsers by forgetting the type, exactly as if the parsers were never typed.
+macro_rules! forget_type_then {
+    ($trait:ident, $method:ident) => {
+        impl<I: Step, O, Rhs: Into<Deterministic<I>>> ops::$trait<Rhs>
+            for Typed<Deterministic<I>, O>
+        {
+            type Output = Deterministic<I>;
+            #[inline]
+            fn $method(self, rhs: Rhs) -> Self::Output {
+                ops::$trait::$method(self.erase(), rhs)
+            }
+        }
+    };
+}
+
+forget_type_then!(BitOr, bitor);
+forget_type_then!(BitAnd, bitand);
+forget_type_then!(Add, add);
+forget_type_then!(Shr, shr);
+forget_type_then!(Shl, shl);
@@ -6,44 +6,135 @@
 
 //! A single-argument Rust function callable both in `build.rs` and in a source file.
 
-use crate::{Ctrl, IllFormed, Input, ToSrc};
-use core::{cmp, fmt, marker::PhantomData};
+use crate::{
+    f::{Dynamic, PayloadFn, UpdateFn},
+    Ctrl, Erased, IllFormed, Input, ToSrc,
+};
+use core::{cmp, fmt, hash, marker::PhantomData};
+use std::sync::Arc;
 
 /// A single-argument Rust function callable both in `build.rs` and in a source file.
 #[allow(clippy::exhaustive_structs)]
 pub struct Update<I: Input> {
     /// Source-code representation of the input type.
-    pub input_t: String,
-    /// Source-code representation of the input type.
-    pub output_t: String,
+    pub input_t: Arc<str>,
+    /// Source-code representation of the output type.
+    pub output_t: Arc<str>,
     /// Representation of the type of tokens.
     pub ghost: PhantomData<I>,
     /// Source-code representation that's promised to compile to a call operationally identical to `ptr`.
-    pub src: &'static str,
+    pub src: Arc<str>,
+    /// The function itself, to call while interpreting a parser.
+    pub ptr: Erased<UpdateFn<I>>,
+    /// Source-code representation of the type of each token's payload, if this function reads payloads
+    /// (in which case it takes each token paired with its payload instead of the token alone).
+    pub payload_t: Option<Arc<str>>,
+    /// The function itself, if it reads payloads, to call while interpreting a parser.
+    pub with_payload: Erased<PayloadFn<I>>,
 }
 
 impl<I: Input> Update<I> {
     /// Internals of the `update!` macro.
     #[inline]
     #[must_use]
-    pub fn _update_macro<T: ToSrc, U: ToSrc>(src: &'static str, _: fn(T, I) -> U) -> Self {
+    pub fn _update_macro<T: 'static + ToSrc, U: 'static + ToSrc>(
+        src: &'static str,
+        f: fn(T, I) -> U,
+    ) -> Self
+    where
+        I: 'static,
+    {
+        Self {
+            input_t: T::src_type().into(),
+            output_t: U::src_type().into(),
+            ghost: PhantomData,
+            src: src.into(),
+            ptr: Erased(Some(Arc::new(move |acc: Dynamic, token| {
+                let out: Dynamic = Box::new(f(*acc.downcast().ok()?, token));
+                Some(out)
+            }))),
+            payload_t: None,
+            with_payload: Erased(None),
+        }
+    }
+
+    /// Internals of the `update_with_payload!` macro.
+    #[inline]
+    #[must_use]
+    pub fn _payload_macro<T: 'static + ToSrc, P: 'static + ToSrc, U: 'static + ToSrc>(
+        src: &'static str,
+        f: fn(T, (I, P)) -> U,
+    ) -> Self
+    where
+        I: 'static,
+    {
         Self {
-            input_t: T::src_type(),
-            output_t: U::src_type(),
+            input_t: T::src_type().into(),
+            output_t: U::src_type().into(),
             ghost: PhantomData,
-            src,
+            src: src.into(),
+            ptr: Erased(None),
+            payload_t: Some(P::src_type().into()),
+            with_payload: Erased(Some(Arc::new(
+                move |acc: Dynamic, token, payload: Dynamic| {
+                    let out: Dynamic =
+                        Box::new(f(*acc.downcast().ok()?, (token, *payload.downcast().ok()?)));
+                    Some(out)
+                },
+            ))),
         }
     }
 
+    /// Fuse two updates into one that runs this one and then `next` on the same token,
+    /// e.g. to merge two transitions in a row into one without losing either action.
+    /// Generated code calls both, in order, from a single closure.
+    /// # Errors
+    /// If `next` doesn't take what this returns, or if either reads payloads
+    /// (since a payload can only be handed to one function).
+    #[inline]
+    pub fn then<C: Ctrl<I>>(&self, next: &Self) -> Result<Self, IllFormed<I, C>>
+    where
+        I: 'static,
+    {
+        if let Some(src) = [self, next]
+            .into_iter()
+            .find_map(|u| u.payload_t.is_some().then(|| u.src.to_string()))
+        {
+            return Err(IllFormed::FusedPayload(src));
+        }
+        let output_t = next.invoke(&self.output_t)?;
+        let src = format!(
+            "|acc, token: {}| ({})(({})(acc, token.clone()), token)",
+            I::src_type(),
+            next.src,
+            self.src,
+        );
+        let ptr: Erased<UpdateFn<I>> = match (self.ptr.0.clone(), next.ptr.0.clone()) {
+            (Some(first), Some(second)) => Erased(Some(Arc::new(move |acc: Dynamic, token: I| {
+                second(first(acc, token.clone())?, token)
+            }))),
+            _ => Erased(None),
+        };
+        Ok(Self {
+            input_t: Arc::clone(&self.input_t),
+            output_t: output_t.into(),
+            ghost: PhantomData,
+            src: src.into(),
+            ptr,
+            payload_t: None,
+            with_payload: Erased(None),
+        })
+    }
+
     /// Check types.
     /// # Errors
     /// If the argument type doesn't match the function's expected input type.
     #[inline]
     pub fn invoke<C: Ctrl<I>>(&self, input_t: &str) -> Result<String, IllFormed<I, C>> {
         input_t
-            .eq(&self.input_t)
-            .then(|| self.output_t.clone())
-            .ok_or_else(|| IllFormed::TypeMismatch(input_t.to_owned(), self.input_t.clone()))
+            .eq(&*self.input_t)
+            .then(|| self.output_t.to_string())
+            .ok_or_else(|| IllFormed::TypeMismatch(input_t.to_owned(), self.input_t.to_string()))
     }
 }
 
@@ -56,6 +147,13 @@ impl<I: Input> PartialEq for Update<I> {
 
 impl<I: Input> Eq for Update<I> {}
 
+impl<I: Input> hash::Hash for Update<I> {
+    #[inline]
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.src.hash(state);
+    }
+}
+
 impl<I: Input> PartialOrd for Update<I> {
     #[inline]
     fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
@@ -66,14 +164,25 @@ impl<I: Input> PartialOrd for Update<I> {
 impl<I: Input> Ord for Update<I> {
     #[inline]
     fn cmp(&self, other: &Self) -> cmp::Ordering {
-        self.src.cmp(other.src)
+        self.src.cmp(&other.src)
     }
 }
 
 impl<I: Input> fmt::Debug for Update<I> {
     #[inline]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "update!({})", self.src)
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl<I: Input> fmt::Display for Update<I> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "fn({}) : {} -> {}",
+            self.src, self.input_t, self.output_t
+        )
     }
 }
 
@@ -81,10 +190,13 @@ impl<I: Input> Clone for Update<I> {
     #[inline]
     fn clone(&self) -> Self {
         Self {
-            input_t: self.input_t.clone(),
-            output_t: self.output_t.clone(),
+            input_t: Arc::clone(&self.input_t),
+            output_t: Arc::clone(&self.output_t),
             ghost: self.ghost,
-            src: self.src,
+            src: Arc::clone(&self.src),
+            ptr: self.ptr.clone(),
+            payload_t: self.payload_t.clone(),
+            with_payload: self.with_payload.clone(),
         }
     }
 }
@@ -0,0 +1,178 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Walk a parser one transition at a time, for interpreters that do something along the way (e.g. compute an output).
+
+use crate::{
+    in_progress::ran_out, Ctrl, Deterministic, IllFormed, Input, InputError, ParseError,
+    Transition, Update, FF,
+};
+
+/// Where to pick up once a region closes.
+#[derive(Clone)]
+pub(crate) struct Frame<'graph, I: Input, C: Ctrl<I>, S> {
+    /// Transition to take after the region closes.
+    dst: &'graph Transition<I, C>,
+    /// How to combine the output from before the region with the output from inside it.
+    combine: &'graph FF,
+    /// Name of the region.
+    region: &'static str,
+    /// Token that opened the region, which takes `dst` once the region closes.
+    token: I,
+    /// Index of the token that opened the region.
+    opened: usize,
+    /// Whatever the interpreter set aside when the region opened (e.g. the output so far).
+    saved: S,
+}
+
+/// What an interpreter does as a walk takes each transition.
+pub(crate) trait Visit<'graph, I: Input, C: Ctrl<I>> {
+    /// What comes along with each token (e.g. a payload from a lexer).
+    type Payload;
+    /// What to set aside when a region opens.
+    type Saved;
+
+    /// Take whatever comes along with the next token, just before taking its transition.
+    #[inline]
+    fn read(&mut self, payload: Self::Payload) {
+        drop(payload);
+    }
+
+    /// Take a lateral transition (calling `update`, if any) on `token`, the token at index `start`,
+    /// while reading the token at index `index` (later than `start` only when a region just closed).
+    /// # Errors
+    /// If the interpreter can't act on this transition.
+    fn lateral(
+        &mut self,
+        update: Option<&'graph Update<I>>,
+        token: &I,
+        start: usize,
+        index: usize,
+    ) -> Result<(), ParseError<I, C>>;
+
+    /// Open a region, returning whatever to set aside until it closes.
+    fn open(&mut self) -> Self::Saved;
+
+    /// Close a region opened at index `opened` (by the token at index `index`), given whatever we set aside when it opened.
+    /// # Errors
+    /// If the interpreter can't combine the output from inside the region with the output from before it.
+    fn close(
+        &mut self,
+        combine: &'graph FF,
+        saved: Self::Saved,
+        opened: usize,
+        index: usize,
+    ) -> Result<(), ParseError<I, C>>;
+
+    /// React to the input so far being accepted (i.e. in an accepting state with no region open).
+    #[inline]
+    fn accepted(&mut self) {}
+}
+
+/// Take one transition on `token` (the token at index `start`) while reading the token at index `index`,
+/// returning wherever it leads.
+/// # Errors
+/// If this transition closes a region that was never opened, or if the interpreter errors.
+#[inline]
+pub(crate) fn take<'graph, I: Input, C: Ctrl<I>, V: Visit<'graph, I, C>>(
+    transition: &'graph Transition<I, C>,
+    token: I,
+    start: usize,
+    index: usize,
+    stack: &mut Vec<Frame<'graph, I, C, V::Saved>>,
+    visitor: &mut V,
+) -> Result<&'graph C, ParseError<I, C>> {
+    match *transition {
+        Transition::Lateral {
+            ref dst,
+            ref update,
+        } => {
+            visitor.lateral(update.as_ref(), &token, start, index)?;
+            Ok(dst)
+        }
+        Transition::Call {
+            region,
+            ref detour,
+            ref dst,
+            ref combine,
+        } => {
+            stack.push(Frame {
+                dst,
+                combine,
+                region,
+                token,
+                opened: index,
+                saved: visitor.open(),
+            });
+            Ok(detour)
+        }
+        Transition::Return { .. } => {
+            let Frame {
+                dst,
+                combine,
+                token: opened_by,
+                opened,
+                saved,
+                ..
+            } = stack
+                .pop()
+                .ok_or(ParseError::BadInput(InputError::Unopened))?;
+            visitor.close(combine, saved, opened, index)?;
+            take(dst, opened_by, opened, index, stack, visitor)
+        }
+    }
+}
+
+/// Each region a stack has open (outermost first) with the transition to take once it closes.
+#[inline]
+pub(crate) fn open_regions<'graph, I: Input, C: Ctrl<I>, S>(
+    stack: &[Frame<'graph, I, C, S>],
+) -> Vec<(&'static str, &'graph Transition<I, C>)> {
+    stack
+        .iter()
+        .map(|frame| (frame.region, frame.dst))
+        .collect()
+}
+
+impl<I: Input> Deterministic<I> {
+    /// Run this parser to completion, letting `visitor` act on every transition along the way.
+    /// # Errors
+    /// If the parser rejects this input or if `visitor` errors.
+    #[inline]
+    pub(crate) fn walk<
+        'graph,
+        V: Visit<'graph, I, usize>,
+        In: IntoIterator<Item = (I, V::Payload)>,
+    >(
+        &'graph self,
+        input: In,
+        visitor: &mut V,
+    ) -> Result<(), ParseError<I, usize>> {
+        let mut state = self.initial;
+        let mut stack = vec![];
+        let mut consumed: usize = 0;
+        for (token, payload) in input {
+            let transition = self
+                .states
+                .get(state)
+                .ok_or(ParseError::BadParser(IllFormed::OutOfBounds(state)))?
+                .transitions
+                .get(&token)
+                .map_err(ParseError::BadParser)?
+                .ok_or(ParseError::BadInput(InputError::Absurd))?;
+            visitor.read(payload);
+            state = *take(transition, token, consumed, consumed, &mut stack, visitor)?;
+            consumed = consumed.saturating_add(1);
+            if stack.is_empty() && get!(self.states, state).non_accepting.is_empty() {
+                visitor.accepted();
+            }
+        }
+        if !stack.is_empty() || !get!(self.states, state).non_accepting.is_empty() {
+            return Err(ran_out(self, &state, &open_regions(&stack), consumed));
+        }
+        Ok(())
+    }
+}
@@ -1,6 +1,6 @@
 //! Notice that the functional definition looks almost exactly like the formal spec at <https://www.json.org/json-en.html>!
 
-use inator::*;
+use inator::prelude::*;
 use std::io;
 
 type I = u8;
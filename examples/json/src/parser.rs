@@ -41,7 +41,7 @@ struct F<I>(fn(&mut I, core::convert::Infallible) -> R<I>);
 
 #[inline]
 pub fn parse<I: IntoIterator<Item = u8>>(input: I) -> Result<core::convert::Infallible, Error> {
-    state_1(&mut input.into_iter().enumerate(), (), None)
+    state_0(&mut input.into_iter().enumerate(), (), None)
 }
 
 #[inline]
@@ -51,11 +51,14 @@ fn state_0<I: Iterator<Item = (usize, u8)>>(
     stack_top: Option<(&'static str, usize)>,
 ) -> Result<core::convert::Infallible, Error> {
     match input.next() {
-        None => stack_top.map_or(Ok(acc), |(region, opened)| {
-            Err(Error::Unclosed { region, opened })
-        }),
+        None => Err(Error::UserDefined { messages: &["Expected only a single token on [b\'\\t\'..=b\'\\t\'] but got another token after it", "Expected only a single token on [b\'\\n\'..=b\'\\n\'] but got another token after it", "Expected only a single token on [b\'\\r\'..=b\'\\r\'] but got another token after it", "Expected only a single token on [b\' \'..=b\' \'] but got another token after it"] }),
         Some((index, token)) => match token {
-            _ => Err(Error::Absurd { index, token }),
+            b'\t'..=b'\n' | b'\r' | b' ' => {
+                state_1(input, acc, stack_top)
+            },
+            _ => {
+                Err(Error::Absurd { index, token })
+            }
         },
     }
 }
@@ -67,21 +70,11 @@ fn state_1<I: Iterator<Item = (usize, u8)>>(
     stack_top: Option<(&'static str, usize)>,
 ) -> Result<core::convert::Infallible, Error> {
     match input.next() {
-        None => Err(Error::UserDefined { messages: &["Expected only a single token on [b\' \'..=b\' \'] but got another token after it", "Expected only a single token on [b\'\\n\'..=b\'\\n\'] but got another token after it", "Expected only a single token on [b\'\\r\'..=b\'\\r\'] but got another token after it", "Expected only a single token on [b\'\\t\'..=b\'\\t\'] but got another token after it"] }),
+        None => stack_top.map_or(Ok(acc), |(region, opened)| {
+            Err(Error::Unclosed { region, opened })
+        }),
         Some((index, token)) => match token {
-            b'\t'..=b'\t' => {
-                state_0(input, acc, stack_top)
-            },
-            b'\n'..=b'\n' => {
-                state_0(input, acc, stack_top)
-            },
-            b'\r'..=b'\r' => {
-                state_0(input, acc, stack_top)
-            },
-            b' '..=b' ' => {
-                state_0(input, acc, stack_top)
-            },
-            _ => Err(Error::Absurd { index, token })
+            _ => Err(Error::Absurd { index, token }),
         },
     }
 }
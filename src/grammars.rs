@@ -0,0 +1,234 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Ready-made grammars for common formats.
+
+use crate::{on_any_of, toss, update, Deterministic, Range, ToSrc, Typed, Update};
+
+/// Date and time of day, as read by `date`, `time`, and `iso8601` (with zeros wherever a parser doesn't read a field).
+#[allow(clippy::exhaustive_structs)]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Timestamp {
+    /// Year, from 0 to 9999.
+    pub year: u16,
+    /// Month, from 1 to 12.
+    pub month: u8,
+    /// Day of the month, from 1 to 31.
+    pub day: u8,
+    /// Hour, from 0 to 23.
+    pub hour: u8,
+    /// Minute, from 0 to 59.
+    pub minute: u8,
+    /// Second, from 0 to 59.
+    pub second: u8,
+    /// Millisecond, from 0 to 999.
+    pub millisecond: u16,
+}
+
+impl Timestamp {
+    /// Append a decimal digit to the year.
+    #[inline]
+    #[must_use]
+    pub fn push_year(mut self, c: char) -> Self {
+        self.year = push_digit(self.year, c);
+        self
+    }
+    /// Append a decimal digit to the month.
+    #[inline]
+    #[must_use]
+    pub fn push_month(mut self, c: char) -> Self {
+        self.month = push_digit(self.month.into(), c)
+            .try_into()
+            .unwrap_or(u8::MAX);
+        self
+    }
+    /// Append a decimal digit to the day.
+    #[inline]
+    #[must_use]
+    pub fn push_day(mut self, c: char) -> Self {
+        self.day = push_digit(self.day.into(), c).try_into().unwrap_or(u8::MAX);
+        self
+    }
+    /// Append a decimal digit to the hour.
+    #[inline]
+    #[must_use]
+    pub fn push_hour(mut self, c: char) -> Self {
+        self.hour = push_digit(self.hour.into(), c)
+            .try_into()
+            .unwrap_or(u8::MAX);
+        self
+    }
+    /// Append a decimal digit to the minute.
+    #[inline]
+    #[must_use]
+    pub fn push_minute(mut self, c: char) -> Self {
+        self.minute = push_digit(self.minute.into(), c)
+            .try_into()
+            .unwrap_or(u8::MAX);
+        self
+    }
+    /// Append a decimal digit to the second.
+    #[inline]
+    #[must_use]
+    pub fn push_second(mut self, c: char) -> Self {
+        self.second = push_digit(self.second.into(), c)
+            .try_into()
+            .unwrap_or(u8::MAX);
+        self
+    }
+    /// Append a decimal digit to the millisecond.
+    #[inline]
+    #[must_use]
+    pub fn push_millisecond(mut self, c: char) -> Self {
+        self.millisecond = push_digit(self.millisecond, c);
+        self
+    }
+}
+
+impl ToSrc for Timestamp {
+    #[inline]
+    fn to_src(&self) -> String {
+        format!(
+            "{} {{ year: {}, month: {}, day: {}, hour: {}, minute: {}, second: {}, millisecond: {} }}",
+            Self::src_type(),
+            self.year,
+            self.month,
+            self.day,
+            self.hour,
+            self.minute,
+            self.second,
+            self.millisecond,
+        )
+    }
+    #[inline]
+    fn src_type() -> String {
+        "::inator::grammars::Timestamp".to_owned()
+    }
+}
+
+/// Shift a number one decimal digit left and add this digit (saturating instead of overflowing).
+#[inline]
+fn push_digit(n: u16, c: char) -> u16 {
+    let digit = c
+        .to_digit(10)
+        .and_then(|d| u16::try_from(d).ok())
+        .unwrap_or(0);
+    n.saturating_mul(10).saturating_add(digit)
+}
+
+/// Any one decimal digit from `first` to `last`.
+#[inline]
+fn digit(first: char, last: char, update: Update<char>) -> Deterministic<char> {
+    on_any_of::<_, Timestamp>(Range { first, last }, update).erase()
+}
+
+/// A date, `YYYY-MM-DD`, e.g. `2024-02-29`.
+///
+/// Months have to be from `01` to `12` and days from `01` to `31`,
+/// but a finite automaton can't check days against their month (or leap years) without spelling out every case,
+/// so e.g. `2023-02-31` parses just fine: check that afterward if it matters.
+#[inline]
+#[must_use]
+#[allow(clippy::arithmetic_side_effects)] // <-- Concatenation with `>>`
+pub fn date() -> Typed<Deterministic<char>, Timestamp> {
+    typed(untyped_date())
+}
+
+/// `date`, before promising that it computes a `Timestamp` (so we can keep concatenating onto it).
+#[inline]
+#[must_use]
+#[allow(clippy::arithmetic_side_effects)] // <-- Concatenation with `>>`
+fn untyped_date() -> Deterministic<char> {
+    digit(
+        '0',
+        '9',
+        update!(|(), c: char| Timestamp::default().push_year(c)),
+    ) >> digit('0', '9', update!(|t: Timestamp, c: char| t.push_year(c))).repeat(3)
+        >> toss('-')
+        >> month_and_day()
+}
+
+/// A time of day, `hh:mm:ss` with an optional `.fff` for milliseconds, e.g. `23:59:60` (no) or `23:59:59.999` (yes).
+///
+/// Hours have to be from `00` to `23` and minutes and seconds from `00` to `59` (so no leap seconds).
+#[inline]
+#[must_use]
+#[allow(clippy::arithmetic_side_effects)] // <-- Concatenation with `>>`
+pub fn time() -> Typed<Deterministic<char>, Timestamp> {
+    let hour = (digit(
+        '0',
+        '1',
+        update!(|(), c: char| Timestamp::default().push_hour(c)),
+    ) >> digit('0', '9', update!(|t: Timestamp, c: char| t.push_hour(c))))
+        | (digit(
+            '2',
+            '2',
+            update!(|(), c: char| Timestamp::default().push_hour(c)),
+        ) >> digit('0', '3', update!(|t: Timestamp, c: char| t.push_hour(c))));
+    typed(hour >> toss(':') >> minute_and_second())
+}
+
+/// A date and time of day together, `YYYY-MM-DDThh:mm:ss` with an optional `.fff`, e.g. `2024-02-29T12:34:56.789`.
+/// See `date` and `time` for exactly what each part accepts.
+#[inline]
+#[must_use]
+#[allow(clippy::arithmetic_side_effects)] // <-- Concatenation with `>>`
+pub fn iso8601() -> Typed<Deterministic<char>, Timestamp> {
+    let hour = (digit('0', '1', update!(|t: Timestamp, c: char| t.push_hour(c)))
+        >> digit('0', '9', update!(|t: Timestamp, c: char| t.push_hour(c))))
+        | (digit('2', '2', update!(|t: Timestamp, c: char| t.push_hour(c)))
+            >> digit('0', '3', update!(|t: Timestamp, c: char| t.push_hour(c))));
+    typed(untyped_date() >> toss('T') >> hour >> toss(':') >> minute_and_second())
+}
+
+/// Promise that a parser computes a `Timestamp`, which every update in this file does.
+#[inline]
+#[must_use]
+fn typed(parser: Deterministic<char>) -> Typed<Deterministic<char>, Timestamp> {
+    parser
+        .typed()
+        .expect("Every update in `grammars` computes a `Timestamp`")
+}
+
+/// `MM-DD`, once a year has already started a `Timestamp`.
+#[inline]
+#[must_use]
+#[allow(clippy::arithmetic_side_effects)] // <-- Concatenation with `>>`
+fn month_and_day() -> Deterministic<char> {
+    let month = |first, last| {
+        digit(
+            first,
+            last,
+            update!(|t: Timestamp, c: char| t.push_month(c)),
+        )
+    };
+    let day = |first, last| digit(first, last, update!(|t: Timestamp, c: char| t.push_day(c)));
+    ((month('0', '0') >> month('1', '9')) | (month('1', '1') >> month('0', '2')))
+        >> toss('-')
+        >> ((day('0', '0') >> day('1', '9'))
+            | (day('1', '2') >> day('0', '9'))
+            | (day('3', '3') >> day('0', '1')))
+}
+
+/// `mm:ss` with an optional `.fff`, once an hour has already started a `Timestamp`.
+#[inline]
+#[must_use]
+#[allow(clippy::arithmetic_side_effects)] // <-- Concatenation with `>>`
+fn minute_and_second() -> Deterministic<char> {
+    digit('0', '5', update!(|t: Timestamp, c: char| t.push_minute(c)))
+        >> digit('0', '9', update!(|t: Timestamp, c: char| t.push_minute(c)))
+        >> toss(':')
+        >> digit('0', '5', update!(|t: Timestamp, c: char| t.push_second(c)))
+        >> digit('0', '9', update!(|t: Timestamp, c: char| t.push_second(c)))
+        >> (toss('.')
+            >> digit(
+                '0',
+                '9',
+                update!(|t: Timestamp, c: char| t.push_millisecond(c)),
+            )
+            .repeat(3))
+        .optional()
+}
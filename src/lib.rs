@@ -156,13 +156,22 @@ macro_rules! get_mut {
 
 // TODO: derive ToSrc
 
+pub mod grammars;
+pub mod prelude;
+
 #[cfg(test)]
 mod test;
 
 pub use inator_automata::{Deterministic as Parser, *};
 
 use core::iter;
-use std::collections::{BTreeMap, BTreeSet};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ffi::OsStr,
+    fs, io,
+    path::Path,
+    process::Command,
+};
 
 #[cfg(feature = "quickcheck")]
 use quickcheck as _; // <-- TODO: remove if we write some implementations
@@ -170,8 +179,8 @@ use quickcheck as _; // <-- TODO: remove if we write some implementations
 /// Parser that accepts only the empty string.
 #[inline]
 #[must_use]
-pub fn empty<I: Input>() -> Deterministic<I> {
-    Graph {
+pub fn empty<I: Input>() -> Typed<Deterministic<I>, ()> {
+    forgetful(Graph {
         states: vec![State {
             transitions: Curry::Scrutinize {
                 filter: RangeMap(BTreeMap::new()),
@@ -180,13 +189,19 @@ pub fn empty<I: Input>() -> Deterministic<I> {
             non_accepting: BTreeSet::new(),
         }],
         initial: 0,
-    }
+    })
 }
 
-/// Accept exactly this range of tokens and do exactly these things.
+/// Accept exactly this range of tokens and do exactly these things, computing an `O` (e.g. `on_any_of::<_, u8>(...)`).
+/// # Panics
+/// If `update` doesn't compute an `O`.
 #[inline]
 #[must_use]
-pub fn on_any_of<I: Input>(range: Range<I>, update: Update<I>) -> Deterministic<I> {
+#[allow(clippy::panic)]
+pub fn on_any_of<I: Input, O: ToSrc>(
+    range: Range<I>,
+    update: Update<I>,
+) -> Typed<Deterministic<I>, O> {
     Graph {
         states: vec![
             State {
@@ -197,12 +212,7 @@ pub fn on_any_of<I: Input>(range: Range<I>, update: Update<I>) -> Deterministic<
                 non_accepting: BTreeSet::new(),
             },
             State {
-                non_accepting: iter::once(format!(
-                    "Expected only a single token on [{}..={}] but got another token after it",
-                    range.first.to_src(),
-                    range.last.to_src(),
-                ))
-                .collect(),
+                non_accepting: iter::once(Rejection::ExpectedEndAfter(range.clone())).collect(),
                 transitions: Curry::Scrutinize {
                     filter: RangeMap(
                         iter::once((
@@ -220,13 +230,15 @@ pub fn on_any_of<I: Input>(range: Range<I>, update: Update<I>) -> Deterministic<
         ],
         initial: 1,
     }
+    .typed()
+    .unwrap_or_else(|e| panic!("{e}"))
 }
 
 /// Accept exactly this range of tokens and forget their values.
 #[inline]
 #[must_use]
-pub fn any_of<I: Input>(range: Range<I>) -> Deterministic<I> {
-    Graph {
+pub fn any_of<I: Input>(range: Range<I>) -> Typed<Deterministic<I>, ()> {
+    forgetful(Graph {
         states: vec![
             State {
                 transitions: Curry::Scrutinize {
@@ -236,12 +248,7 @@ pub fn any_of<I: Input>(range: Range<I>) -> Deterministic<I> {
                 non_accepting: BTreeSet::new(),
             },
             State {
-                non_accepting: iter::once(format!(
-                    "Expected only a single token on [{}..={}] but got another token after it",
-                    range.first.to_src(),
-                    range.last.to_src(),
-                ))
-                .collect(),
+                non_accepting: iter::once(Rejection::ExpectedEndAfter(range.clone())).collect(),
                 transitions: Curry::Scrutinize {
                     filter: RangeMap(
                         iter::once((
@@ -258,12 +265,94 @@ pub fn any_of<I: Input>(range: Range<I>) -> Deterministic<I> {
             },
         ],
         initial: 1,
-    }
+    })
+}
+
+/// Accept any single token whatsoever and forget its value (like `.` in a regular expression).
+#[inline]
+#[must_use]
+pub fn any<I: Input>() -> Typed<Deterministic<I>, ()> {
+    forgetful(Graph {
+        states: vec![
+            State {
+                transitions: Curry::Scrutinize {
+                    filter: RangeMap(BTreeMap::new()),
+                    fallback: None,
+                },
+                non_accepting: BTreeSet::new(),
+            },
+            State {
+                non_accepting: iter::once(Rejection::ExpectedEndAfterAny).collect(),
+                transitions: Curry::Wildcard(Transition::Lateral {
+                    dst: 0,
+                    update: None,
+                }),
+            },
+        ],
+        initial: 1,
+    })
 }
 
 /// Accept exactly this token and forget its value.
 #[inline]
 #[must_use]
-pub fn toss<I: Input>(token: I) -> Deterministic<I> {
+pub fn toss<I: Input>(token: I) -> Typed<Deterministic<I>, ()> {
     any_of(Range::unit(token))
 }
+
+/// Promise that a parser computes `()`, which any parser without functions does
+/// (it only ever hands along the `()` it starts with).
+#[inline]
+#[allow(clippy::panic)]
+fn forgetful<I: Input>(parser: Deterministic<I>) -> Typed<Deterministic<I>, ()> {
+    parser
+        .typed()
+        .unwrap_or_else(|e| panic!("A parser without functions should compute `()`, but {e}"))
+}
+
+/// Read a parser from a data file (see `Deterministic::from_description`) and generate its source code,
+/// with an entry point called `name`, without writing any Rust to describe the parser itself.
+/// # Errors
+/// If the description is malformed or the parser it describes is ill-formed.
+#[inline]
+pub fn compile_description_to_source(
+    description: &str,
+    updates: &[(&str, Update<char>)],
+    combinators: &[(&str, FF)],
+    name: &'static str,
+) -> Result<String, DescriptionError> {
+    Parser::from_description(description, updates, combinators)?
+        .to_src_with(
+            |i| format!("state_{i}"),
+            CodegenOptions::default().entry_point(name),
+        )
+        .map_err(DescriptionError::IllFormed)
+}
+
+/// Write a parser as a Rust source file from `build.rs`, with its entry point named `name`.
+///
+/// For example, `codegen_to_file(&parser, "parse", "src/parser.rs")`, then `mod parser;` and `parser::parse(...)`.
+/// Formats the file with `rustfmt` if it's installed, and leaves the file as-is otherwise.
+///
+/// Doesn't print any `cargo:rerun-if-changed` directives, since only the caller knows what the parser was built from:
+/// without any, Cargo reruns the build script whenever anything in the package changes.
+/// # Errors
+/// If the parser is ill-formed (outer) or if we can't write the file (inner).
+#[inline]
+pub fn codegen_to_file<I: Step, P: AsRef<OsStr> + AsRef<Path>>(
+    parser: &Parser<I>,
+    name: &'static str,
+    path: P,
+) -> Result<io::Result<()>, IllFormed<I, usize>> {
+    parser
+        .to_src_with(
+            |i| format!("state_{i}"),
+            CodegenOptions::default().entry_point(name),
+        )
+        .map(|src| {
+            fs::write(&path, src)?;
+            // Formatting is cosmetic, so a missing or failing `rustfmt` isn't an error.
+            drop(Command::new("rustfmt").arg(path).output());
+            Ok(())
+        })
+}
@@ -0,0 +1,17 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Exactly what you need to write a grammar, without the automata underneath.
+//! ```rust
+//! use inator::prelude::*;
+//! let ab = toss('a') >> toss('b');
+//! assert!(ab.accept("ab".chars()).is_ok());
+//! ```
+
+pub use crate::{
+    any, any_of, codegen_to_file, empty, f, ff, on_any_of, toss, update, Deterministic, IllFormed,
+    Input, Parser, Range, ToSrc, Update, F, FF,
+};
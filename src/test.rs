@@ -28,6 +28,442 @@ fn sliceable<I: Input, C: Ctrl<I>>(parser: &Graph<I, C>, input: &[I]) -> bool {
 }
 */
 
+mod unit {
+    use crate::*;
+    use std::{
+        env, fs,
+        path::{Path, PathBuf},
+        process::Command,
+    };
+
+    /// A fresh temporary directory for one test.
+    fn scratch(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("inator-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Compile `main` (along with whatever else is already in `dir`) into a binary, run it,
+    /// and return what it printed, deleting `dir` afterward.
+    fn compile_and_run(dir: &Path, main: &str) -> String {
+        let root = dir.join("main.rs");
+        fs::write(&root, main).unwrap();
+        let binary = dir.join("main");
+        let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_owned());
+        let output = Command::new(rustc)
+            .args(["--edition", "2021", "--crate-type", "bin", "-o"])
+            .arg(&binary)
+            .arg(&root)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr),
+        );
+        let run = Command::new(&binary).output().unwrap();
+        assert!(
+            run.status.success(),
+            "{}",
+            String::from_utf8_lossy(&run.stderr)
+        );
+        fs::remove_dir_all(dir).unwrap();
+        String::from_utf8(run.stdout).unwrap()
+    }
+
+    #[test]
+    fn codegen_to_file_compiles() {
+        let dir = scratch("codegen");
+        let path = dir.join("parser.rs");
+        let parser = on_any_of::<_, ()>(Range::unit('a'), update!(|(), _| {}))
+            .erase()
+            .star();
+        codegen_to_file(&parser, "any_number_of_as", &path)
+            .unwrap()
+            .unwrap();
+        let src = fs::read_to_string(&path).unwrap();
+        assert!(src.contains("pub fn any_number_of_as<"), "{src}");
+        let printed = compile_and_run(
+            &dir,
+            r#"mod parser;
+
+fn main() {
+    for input in ["", "a", "aaa", "b", "aab"] {
+        println!("{}", parser::any_number_of_as(input.chars()).is_ok());
+    }
+}
+"#,
+        );
+        assert_eq!(printed, "true\ntrue\ntrue\nfalse\nfalse\n");
+    }
+
+    #[test]
+    fn codegen_handle_compiles() {
+        let dir = scratch("handle");
+        let parser = on_any_of::<_, ()>(Range::unit('a'), update!(|(), _| {}))
+            .erase()
+            .star();
+        let src = parser
+            .to_src_with(
+                |i| format!("state_{i}"),
+                CodegenOptions::default()
+                    .entry_point("any_number_of_as")
+                    .handle("AnyNumberOfAs"),
+            )
+            .unwrap();
+        fs::write(dir.join("parser.rs"), src).unwrap();
+        // Pass the handle around as a value, and make sure the free function agrees with it.
+        let printed = compile_and_run(
+            &dir,
+            r#"mod parser;
+
+fn run(handle: parser::AnyNumberOfAs, input: &str) -> bool {
+    handle.parse(input.chars()).is_ok()
+}
+
+fn main() {
+    assert_eq!(parser::AnyNumberOfAs::NAME, "any_number_of_as");
+    for input in ["", "aaa", "b", "aab"] {
+        let accepted = parser::any_number_of_as(input.chars()).is_ok();
+        assert_eq!(run(parser::AnyNumberOfAs, input), accepted);
+        println!("{accepted}");
+    }
+}
+"#,
+        );
+        assert_eq!(printed, "true\ntrue\nfalse\nfalse\n");
+    }
+
+    /// Define these items and keep their source code (e.g. to compile them alongside a generated parser).
+    macro_rules! with_src {
+        ($src:ident, $($item:item)*) => {
+            $($item)*
+            const $src: &str = stringify!($($item)*);
+        };
+    }
+
+    with_src!(
+        PHONE_NUMBER,
+        #[derive(Clone, Copy, Debug, Default, PartialEq)]
+        struct PhoneNumber {
+            exchange: u16,
+            line: u16,
+        }
+        impl PhoneNumber {
+            fn push_exchange(mut self, c: char) -> Self {
+                self.exchange = self.exchange * 10 + c.to_digit(10).and_then(|d| u16::try_from(d).ok()).unwrap_or(0);
+                self
+            }
+            fn push_line(mut self, c: char) -> Self {
+                self.line = self.line * 10 + c.to_digit(10).and_then(|d| u16::try_from(d).ok()).unwrap_or(0);
+                self
+            }
+        }
+    );
+
+    impl ToSrc for PhoneNumber {
+        fn to_src(&self) -> String {
+            format!(
+                "PhoneNumber {{ exchange: {}, line: {} }}",
+                self.exchange, self.line,
+            )
+        }
+        fn src_type() -> String {
+            "PhoneNumber".to_owned()
+        }
+    }
+
+    #[test]
+    fn interpreted_and_generated_phone_numbers_agree() {
+        let digit = |update| {
+            on_any_of::<_, PhoneNumber>(
+                Range {
+                    first: '0',
+                    last: '9',
+                },
+                update,
+            )
+            .erase()
+        };
+        let phone = (digit(update!(
+            |(), c: char| PhoneNumber::default().push_exchange(c)
+        )) >> digit(update!(|n: PhoneNumber, c: char| n.push_exchange(c))).repeat(2)
+            >> toss('-')
+            >> digit(update!(|n: PhoneNumber, c: char| n.push_line(c))).repeat(4))
+        .typed::<PhoneNumber>()
+        .unwrap();
+        assert_eq!(
+            phone
+                .accept("555-1234".chars())
+                .map(|n| (n.exchange, n.line)),
+            Ok((555, 1234)),
+        );
+        let inputs = [
+            "555-1234",
+            "012-3456",
+            "999-9999",
+            "",
+            "555",
+            "5551234",
+            "555-123",
+            "555-12345",
+            "55a-1234",
+        ];
+        let interpreted: String = inputs
+            .iter()
+            .map(|input| {
+                phone
+                    .accept(input.chars())
+                    .map_or_else(|_| "rejected\n".to_owned(), |n| format!("{n:?}\n"))
+            })
+            .collect();
+        assert!(
+            interpreted.starts_with(
+                "PhoneNumber { exchange: 555, line: 1234 }\nPhoneNumber { exchange: 12, line: 3456 }\n"
+            ),
+            "{interpreted}"
+        );
+        let parser = phone
+            .to_src_with(
+                |i| format!("state_{i}"),
+                CodegenOptions::default().entry_point("phone"),
+            )
+            .unwrap();
+        let main = format!(
+            r#"mod parser;
+
+{PHONE_NUMBER}
+
+fn main() {{
+    for input in {inputs:?} {{
+        match parser::phone(input.chars()) {{
+            Ok(n) => println!("{{n:?}}"),
+            Err(_) => println!("rejected"),
+        }}
+    }}
+}}
+"#,
+        );
+        let dir = scratch("phone");
+        fs::write(
+            dir.join("parser.rs"),
+            format!("{parser}\nuse crate::PhoneNumber;\n"),
+        )
+        .unwrap();
+        let generated = compile_and_run(&dir, &main);
+        assert_eq!(generated, interpreted);
+    }
+
+    #[test]
+    fn tokens_without_stepping() {
+        let keyword = toss("let") >> toss("x");
+        assert_eq!(keyword.accept(["let", "x"]), Ok("()".to_owned()));
+        assert!(matches!(keyword.accept(["let", "y"]), Err(..)));
+        let maybe = toss(Some('a')) | toss(None);
+        assert_eq!(maybe.accept([None]), Ok("()".to_owned()));
+        assert!(matches!(maybe.accept([Some('b')]), Err(..)));
+        // We can't tell whether anything lies between two tokens, so this doesn't count as covering everything.
+        let only: RangeMap<&str, usize> = RangeMap(
+            iter::once((
+                Range::unit(""),
+                Transition::Lateral {
+                    dst: 0,
+                    update: None,
+                },
+            ))
+            .collect(),
+        );
+        assert!(!only.covers_everything());
+    }
+
+    #[test]
+    fn any_single_token() {
+        let parser = any::<char>();
+        parser.check().unwrap();
+        assert_eq!(parser.accept_str("x"), Ok("()".to_owned()));
+        assert_eq!(parser.shortest_accepted(), Some(vec!['\0']));
+        assert_eq!(
+            parser.accept_str(""),
+            Err(ParseError::Incomplete {
+                expected: vec![],
+                consumed: 0,
+                open: vec![],
+            }),
+        );
+        assert!(matches!(
+            parser.accept_str("xy"),
+            Err(ParseError::BadInput(..)),
+        ));
+        let anything = parser.erase().star();
+        for input in ["", "x", "xy", "(]"] {
+            assert_eq!(anything.accept_str(input), Ok("()".to_owned()), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn terminator_after_any() {
+        // Everything but the terminator goes one way, and the fallback catches only the terminator...
+        let parser = any::<u8>()
+            | any_of(Range {
+                first: 0,
+                last: 254,
+            });
+        parser.check().unwrap();
+        // ...until the terminator takes that last token for itself, so the fallback has to go.
+        let terminated = parser.with_terminator(255).unwrap();
+        terminated.check().unwrap();
+        assert_eq!(terminated.accept([7, 255, 3]), Ok("()".to_owned()));
+        assert!(matches!(terminated.accept([255]), Err(..)));
+        let anything = any::<u8>().erase().star().with_terminator(0).unwrap();
+        anything.check().unwrap();
+        assert_eq!(anything.accept([1, 2, 0, 9]), Ok("()".to_owned()));
+    }
+
+    #[test]
+    fn rejection_messages() {
+        let range = Range {
+            first: 'a',
+            last: 'z',
+        };
+        for parser in [any_of(range), on_any_of(range, update!(|(), _| {}))] {
+            let messages: Vec<_> = parser.states[parser.initial].non_accepting.iter().collect();
+            assert_eq!(messages, [&Rejection::ExpectedEndAfter(range)]);
+            assert_eq!(
+                messages[0].to_string(),
+                "Expected only a single token on ['a'..='z'] but got another token after it",
+            );
+        }
+        let parser = any::<u8>();
+        let Some(&Rejection::ExpectedEndAfterAny) =
+            parser.states[parser.initial].non_accepting.first()
+        else {
+            panic!("Unexpected rejection from `any`");
+        };
+        assert_eq!(
+            Rejection::<u8>::ExpectedEndAfterAny.to_string(),
+            "Expected only a single token but got another token after it",
+        );
+    }
+
+    #[test]
+    fn concatenated_tosses_stay_a_chain() {
+        let parser = (0_u8..10).fold(empty::<char>().erase(), |acc, _| acc >> toss('a'));
+        // One state per token consumed so far, and nothing else.
+        assert_eq!(parser.states.len(), 11);
+        assert_eq!(parser.minimize().states.len(), 11);
+        for state in &parser.states {
+            assert!(state.transitions.values().count() <= 1);
+        }
+        assert_eq!(parser.accept_str("aaaaaaaaaa"), Ok("()".to_owned()));
+    }
+
+    #[test]
+    fn compile_description_to_source() {
+        let src = super::super::compile_description_to_source(
+            r#"
+            initial = "start"
+
+            [[state]]
+            name = "start"
+            errors = ["Expected `a`"]
+            transitions = [{ on = "a", to = "done" }]
+
+            [[state]]
+            name = "done"
+            accepting = true
+            "#,
+            &[],
+            &[],
+            "just_a",
+        )
+        .unwrap();
+        assert!(src.contains("pub fn just_a<"), "{src}");
+        assert!(matches!(
+            super::super::compile_description_to_source("initial = true", &[], &[], "oops"),
+            Err(DescriptionError::WrongType { .. }),
+        ));
+    }
+
+    #[test]
+    fn iso8601_accepts() {
+        use grammars::*;
+        let ts = |year, month, day, hour, minute, second, millisecond| Timestamp {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            millisecond,
+        };
+        for (input, expected) in [
+            ("2024-02-29", ts(2024, 2, 29, 0, 0, 0, 0)),
+            ("0000-01-01", ts(0, 1, 1, 0, 0, 0, 0)),
+            ("9999-12-31", ts(9999, 12, 31, 0, 0, 0, 0)),
+            ("2023-02-31", ts(2023, 2, 31, 0, 0, 0, 0)), // <-- Per-month day counts are out of scope
+        ] {
+            assert_eq!(date().accept(input.chars()), Ok(expected), "{input}");
+        }
+        for (input, expected) in [
+            ("00:00:00", ts(0, 0, 0, 0, 0, 0, 0)),
+            ("23:59:59", ts(0, 0, 0, 23, 59, 59, 0)),
+            ("19:05:07.042", ts(0, 0, 0, 19, 5, 7, 42)),
+        ] {
+            assert_eq!(time().accept(input.chars()), Ok(expected), "{input}");
+        }
+        for (input, expected) in [
+            ("2024-02-29T12:34:56", ts(2024, 2, 29, 12, 34, 56, 0)),
+            ("1970-01-01T00:00:00.000", ts(1970, 1, 1, 0, 0, 0, 0)),
+            ("2000-10-20T20:10:00.999", ts(2000, 10, 20, 20, 10, 0, 999)),
+        ] {
+            assert_eq!(iso8601().accept(input.chars()), Ok(expected), "{input}");
+        }
+    }
+
+    #[test]
+    fn iso8601_rejects() {
+        use grammars::*;
+        for input in [
+            "",
+            "2024",
+            "2024-02",
+            "24-02-29",
+            "20240-02-29",
+            "2024-00-10",
+            "2024-13-10",
+            "2024-02-00",
+            "2024-02-32",
+            "2024-1-10",
+            "2024/02/29",
+            "2024-02-29T",
+        ] {
+            assert!(date().accept(input.chars()).is_err(), "{input}");
+        }
+        for input in [
+            "24:00:00",
+            "12:60:00",
+            "12:00:60",
+            "12:00",
+            "1:00:00",
+            "12:00:00.",
+            "12:00:00.1",
+            "12:00:00.1234",
+            "12:00:00,123",
+        ] {
+            assert!(time().accept(input.chars()).is_err(), "{input}");
+        }
+        for input in [
+            "2024-02-29",
+            "12:34:56",
+            "2024-02-29 12:34:56",
+            "2024-02-29t12:34:56",
+            "2024-02-29T12:34:56Z",
+        ] {
+            assert!(iso8601().accept(input.chars()).is_err(), "{input}");
+        }
+    }
+}
+
 #[cfg(feature = "quickcheck")]
 mod prop {
     use super::*;
@@ -41,9 +477,30 @@ mod prop {
         }
 
         fn on_any_of_works(range: Range<u8>, input: Vec<u8>) -> bool {
-            let parser = on_any_of(range, update!(|(), _| {}));
+            let parser = on_any_of::<_, ()>(range, update!(|(), _| {}));
             if parser.check().is_err() { return false; }
             parser.accept(input.iter().copied()).is_ok() == (input.len() == 1 && range.contains(&input[0]))
         }
+
+        fn iso8601_round_trip(fields: (u16, u8, u8, u8, u8, u8, u16), millis: bool) -> bool {
+            let (year, month, day, hour, minute, second, millisecond) = fields;
+            let expected = grammars::Timestamp {
+                year: year.min(9999),
+                month: month.clamp(1, 12),
+                day: day.clamp(1, 31),
+                hour: hour.min(23),
+                minute: minute.min(59),
+                second: second.min(59),
+                millisecond: if millis { millisecond.min(999) } else { 0 },
+            };
+            let mut input = format!(
+                "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+                expected.year, expected.month, expected.day, expected.hour, expected.minute, expected.second,
+            );
+            if millis {
+                input = format!("{input}.{:03}", expected.millisecond);
+            }
+            grammars::iso8601().accept(input.chars()) == Ok(expected)
+        }
     }
 }